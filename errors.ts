@@ -0,0 +1,18 @@
+/** Thrown when a window icon has invalid dimensions or pixel data. */
+export class BadIconError extends Error {
+  constructor(message: string) {
+    super(message);
+    this.name = "BadIcon";
+  }
+}
+
+/**
+ * Thrown when an encoded image could not be decoded. Only thrown by plugins
+ * built with the `image` feature.
+ */
+export class BadImageError extends Error {
+  constructor(message: string) {
+    super(message);
+    this.name = "BadImage";
+  }
+}
@@ -1,4 +1,5 @@
 import "./plugin.ts";
 
+export * from "./errors.ts";
 export * from "./pane.ts";
 export * from "./types.ts";
@@ -1,22 +1,80 @@
 import { Plug } from "./deps.ts";
 import {
   CursorIcon,
+  FileDialogOptions,
+  Icon,
+  ImageFormat,
+  LogicalPosition,
+  LogicalSize,
+  MenuItem,
+  MessageBoxButton,
+  MessageBoxOptions,
   PaneEvent,
   PhysicalPosition,
   PhysicalSize,
+  PixelFormat,
   Position,
+  RawWindowHandle,
+  ScaleFactorPolicy,
   Size,
+  TextInputArea,
+  Unit,
   UserAttentionType,
+  WindowAttributes,
+  WindowGeometry,
+  WindowState,
 } from "./types.ts";
 
+/** Concatenates the pixels of icons, to be split again by their sizes. */
+function packIcons(icons: Icon | Icon[]): {
+  sizes: { width: number; height: number }[];
+  rgba: Uint8Array;
+} {
+  icons = Array.isArray(icons) ? icons : [icons];
+
+  const rgba = new Uint8Array(
+    icons.reduce((length, icon) => length + icon.rgba.byteLength, 0),
+  );
+  let offset = 0;
+  for (const icon of icons) {
+    rgba.set(icon.rgba, offset);
+    offset += icon.rgba.byteLength;
+  }
+
+  return {
+    sizes: icons.map(({ width, height }) => ({ width, height })),
+    rgba,
+  };
+}
+
+/** Options for creating a `PaneEventLoop`. */
+export interface PaneEventLoopOptions {
+  /**
+   * The unit `resized`, `moved` and `cursorMoved` events are reported in.
+   * Defaults to `"physical"`.
+   */
+  unit?: Unit;
+  /**
+   * Reports input method compositions as `ime` events on X11, with committed
+   * text replacing `receivedCharacter` events. Defaults to `false`.
+   *
+   * Pane then talks to the input method server itself. As winit reads the
+   * `XMODIFIERS` environment variable while the event loop is created, it is
+   * pointed at the local input method in the meantime, which changes the
+   * environment of the whole process. Only enable this while no other thread
+   * reads the environment.
+   */
+  imeEvents?: boolean;
+}
+
 /**
  * Represents a winit event loop
  */
 export class PaneEventLoop {
   readonly rid: number;
 
-  constructor() {
-    this.rid = Plug.core.opSync("pane_event_loop_new");
+  constructor(options: PaneEventLoopOptions = {}) {
+    this.rid = Plug.core.opSync("pane_event_loop_new", options);
   }
 
   /** Takes a step in this event loop, returning an array of `PaneEvent`s. */
@@ -36,8 +94,11 @@ export class PaneWindow {
     return Plug.core.opSync("pane_window_id", this.rid);
   }
 
-  constructor(eventLoop: PaneEventLoop) {
-    this.rid = Plug.core.opSync("pane_window_new", eventLoop.rid);
+  constructor(eventLoop: PaneEventLoop, attributes: WindowAttributes = {}) {
+    this.rid = Plug.core.opSync("pane_window_new", {
+      rid: eventLoop.rid,
+      attributes,
+    });
   }
 
   /**
@@ -48,6 +109,18 @@ export class PaneWindow {
     return Plug.core.opSync("pane_window_scale_factor", this.rid);
   }
 
+  /**
+   * Sets how the window is resized when its scale factor changes, for example
+   * when it is dragged to a monitor with a different DPI. The chosen size is
+   * reported in the `scaleFactorChanged` event.
+   */
+  setScaleFactorPolicy(policy: ScaleFactorPolicy): void {
+    Plug.core.opSync("pane_window_set_scale_factor_policy", {
+      rid: this.rid,
+      policy,
+    });
+  }
+
   /**
    * Emits a `redrawRequested` event in the event loop after all OS events have
    * been processed by the event loop.
@@ -68,9 +141,16 @@ export class PaneWindow {
    * hand corner of the desktop is the top-left hand corner of the monitor at the
    * top-left of the desktop. The coordinates can be negative if the top-left hand
    * corner of the window is outside of the visible screen region.
+   *
+   * The position is given in physical pixels unless another `unit` is given.
    */
-  innerPosition(): PhysicalPosition {
-    return Plug.core.opSync("pane_window_inner_position", this.rid);
+  innerPosition(
+    unit: Unit = "physical",
+  ): PhysicalPosition | LogicalPosition {
+    return Plug.core.opSync("pane_window_inner_position", {
+      rid: this.rid,
+      unit,
+    });
   }
 
   /**
@@ -82,9 +162,16 @@ export class PaneWindow {
    * hand corner of the desktop is the top-left hand corner of the monitor at the
    * top-left of the desktop. The coordinates can be negative if the top-left hand
    * corner of the window is outside of the visible screen region.
+   *
+   * The position is given in physical pixels unless another `unit` is given.
    */
-  outerPosition(): PhysicalPosition {
-    return Plug.core.opSync("pane_window_outer_position", this.rid);
+  outerPosition(
+    unit: Unit = "physical",
+  ): PhysicalPosition | LogicalPosition {
+    return Plug.core.opSync("pane_window_outer_position", {
+      rid: this.rid,
+      unit,
+    });
   }
 
   /** Modifies the position of the window. */
@@ -96,12 +183,13 @@ export class PaneWindow {
   }
 
   /**
-   * Returns the physical size of the window's client area.
+   * Returns the size of the window's client area, in physical pixels unless
+   * another `unit` is given.
    *
    * The client area is the content of the window, excluding the title bar and borders.
    */
-  innerSize(): PhysicalSize {
-    return Plug.core.opSync("pane_window_inner_size", this.rid);
+  innerSize(unit: Unit = "physical"): PhysicalSize | LogicalSize {
+    return Plug.core.opSync("pane_window_inner_size", { rid: this.rid, unit });
   }
 
   /**
@@ -115,13 +203,14 @@ export class PaneWindow {
   }
 
   /**
-   * Returns the physical size of the entire window.
+   * Returns the size of the entire window, in physical pixels unless another
+   * `unit` is given.
    *
    * These dimensions include the title bar and borders. If you don't want that
    * (and you usually don't), use `innerSize` instead.
    */
-  outerSize(): PhysicalSize {
-    return Plug.core.opSync("pane_window_outer_size", this.rid);
+  outerSize(unit: Unit = "physical"): PhysicalSize | LogicalSize {
+    return Plug.core.opSync("pane_window_outer_size", { rid: this.rid, unit });
   }
 
   /** Sets a minimum dimension size for the window. */
@@ -174,6 +263,34 @@ export class PaneWindow {
     return Plug.core.opSync("pane_window_is_maximized", this.rid);
   }
 
+  /**
+   * Returns the state of the window as set through pane. On X11 the visibility,
+   * minimized, fullscreen and always on top states are read back from the
+   * window manager, and the maximized state is queried on all platforms.
+   */
+  state(): WindowState {
+    return Plug.core.opSync("pane_window_state", this.rid);
+  }
+
+  /** Returns the window's geometry, for restoring it in a later session. */
+  geometry(): WindowGeometry {
+    return Plug.core.opSync("pane_window_geometry", this.rid);
+  }
+
+  /**
+   * Puts the window back where a saved geometry says it was.
+   *
+   * If the monitor the window was on is no longer connected, the window is
+   * moved to the monitor containing its old position or else the primary
+   * monitor. The window is always shrunk and moved to fit on its monitor.
+   */
+  restoreGeometry(geometry: WindowGeometry): void {
+    Plug.core.opSync("pane_window_restore_geometry", {
+      rid: this.rid,
+      geometry,
+    });
+  }
+
   /** Turn window decorations on or off. */
   setDecorations(decorations: boolean): void {
     Plug.core.opSync("pane_window_set_decorations", {
@@ -190,21 +307,82 @@ export class PaneWindow {
     });
   }
 
+  /**
+   * Sets the opacity of the whole window, from 0 for fully transparent to 1
+   * for opaque. Only supported on X11 where it requires a compositor.
+   */
+  setOpacity(opacity: number): void {
+    Plug.core.opSync("pane_window_set_opacity", {
+      rid: this.rid,
+      opacity,
+    });
+  }
+
   /**
    * Sets the window icon. On Windows and X11, this is typically the small icon
    * in the top-left corner of the titlebar.
+   *
+   * Several sizes of the same icon may be given, on X11 the window manager
+   * picks the best fitting one while other platforms use the largest. Passing
+   * no icons removes the window icon. Throws a `BadIconError` if the pixel
+   * data does not match the dimensions of an icon.
    */
+  setWindowIcon(icons?: Icon | Icon[]): void;
+  /** Sets the window icon from RGBA pixels, like `setWindowIcon(icon)`. */
+  setWindowIcon(rgba: Uint8Array, width: number, height: number): void;
   setWindowIcon(
-    rgba: Uint8Array,
-    width: number,
-    height: number,
+    icons: Icon | Icon[] | Uint8Array = [],
+    width?: number,
+    height?: number,
   ): void {
-    Plug.core.opSync("pane_window_set_window_icon", {
-      rid: this.rid,
+    if (icons instanceof Uint8Array) {
+      return this.setWindowIcon({
+        rgba: icons,
+        width: width!,
+        height: height!,
+      });
+    }
+
+    const { sizes, rgba } = packIcons(icons);
+
+    Plug.core.opSync(
+      "pane_window_set_window_icon",
+      { rid: this.rid, sizes },
       rgba,
-      width,
-      height,
-    });
+    );
+  }
+
+  /**
+   * Sets the window icon from encoded PNG, ICO or BMP images, which are
+   * decoded by the plugin. The format is guessed from the image data if not
+   * given. Throws a `BadImageError` if an image cannot be decoded.
+   *
+   * Requires the plugin to be built with the `image` feature.
+   */
+  setWindowIconImage(
+    images: Uint8Array | Uint8Array[] = [],
+    format?: ImageFormat,
+  ): void {
+    images = Array.isArray(images) ? images : [images];
+
+    const data = new Uint8Array(
+      images.reduce((length, image) => length + image.byteLength, 0),
+    );
+    let offset = 0;
+    for (const image of images) {
+      data.set(image, offset);
+      offset += image.byteLength;
+    }
+
+    Plug.core.opSync(
+      "pane_window_set_window_icon_image",
+      {
+        rid: this.rid,
+        lengths: images.map((image) => image.byteLength),
+        format,
+      },
+      data,
+    );
   }
 
   /**
@@ -218,6 +396,63 @@ export class PaneWindow {
     });
   }
 
+  /**
+   * Sets whether the input method is used for the window, it is enabled by
+   * default. Disabling it delivers key presses as plain characters, which suits
+   * game canvases.
+   *
+   * On X11 compositions are reported as `ime` events if the event loop was
+   * created with `imeEvents`.
+   */
+  setImeAllowed(allowed: boolean): void {
+    Plug.core.opSync("pane_window_set_ime_allowed", {
+      rid: this.rid,
+      allowed,
+    });
+  }
+
+  /**
+   * Starts or ends text input for the window, `receivedCharacter` events are
+   * only emitted while text input is active, which it is by default. Providing
+   * the area of the focused field, or of the caret and selection in it, keeps
+   * the candidate window of the input method next to it.
+   */
+  setTextInput(active: boolean, area?: TextInputArea): void {
+    Plug.core.opSync("pane_window_set_text_input", {
+      rid: this.rid,
+      active,
+      area,
+    });
+  }
+
+  /**
+   * Enables or disables gesture recognition for the window, it is disabled by
+   * default. Recognized gestures are emitted as `gesture` events next to the
+   * `touch` events they are recognized from.
+   */
+  setGestures(enabled: boolean): void {
+    Plug.core.opSync("pane_window_set_gestures", {
+      rid: this.rid,
+      enabled,
+    });
+  }
+
+  /**
+   * Locks the pointer to the window, or unlocks it. While locked the cursor is
+   * hidden and kept in the middle of the window, and its relative motion is
+   * emitted as `pointerMotion` events instead of `cursorMoved` events.
+   *
+   * The lock is only engaged while the window is focused, so locking an
+   * unfocused window engages the lock once it is focused. Unlocking restores
+   * the cursor grab and visibility.
+   */
+  setPointerLock(locked: boolean): void {
+    Plug.core.opSync("pane_window_set_pointer_lock", {
+      rid: this.rid,
+      locked,
+    });
+  }
+
   /**
    * Requests user attention to the window, this has no effect if the application
    * is already focused. How requesting for user attention manifests is platform
@@ -278,4 +513,296 @@ export class PaneWindow {
   dragWindow(): void {
     Plug.core.opSync("pane_window_set_cursor_visible", this.rid);
   }
+
+  /**
+   * Draws a software rendered framebuffer to the window's client area without
+   * requiring a GPU.
+   *
+   * The framebuffer is `width * height` 32bpp pixels in physical units, and
+   * should usually be as large as `innerSize`. Pixels outside of the window are
+   * clipped, so a frame rendered before a `resized` event has been handled is
+   * still presented. Currently only supported on X11.
+   */
+  present(
+    framebuffer: Uint8Array,
+    width: number,
+    height: number,
+    format: PixelFormat = "rgba",
+  ): void {
+    Plug.core.opSync(
+      "pane_window_present",
+      { rid: this.rid, width, height, format },
+      framebuffer,
+    );
+  }
+
+  /**
+   * Reads back what the window's client area currently shows as 32bpp RGBA
+   * pixels in physical units, for example for visual regression tests.
+   *
+   * Parts of the window covered by other windows may be undefined when no
+   * compositor is running. Throws if the window is resized during several
+   * attempts to capture it. Currently only supported on X11.
+   */
+  capture(): { size: PhysicalSize; rgba: Uint8Array } {
+    let { width, height } = this.innerSize();
+
+    // A window that is being resized may change size between every attempt.
+    for (let attempt = 0; attempt < 3; attempt++) {
+      const rgba = new Uint8Array(width * height * 4);
+      const size: PhysicalSize = Plug.core.opSync(
+        "pane_window_capture",
+        this.rid,
+        rgba,
+      );
+
+      // The window was resized since its size was read, nothing was captured.
+      if (size.width * size.height * 4 > rgba.length) {
+        ({ width, height } = size);
+        continue;
+      }
+
+      return {
+        size,
+        rgba: rgba.subarray(0, size.width * size.height * 4),
+      };
+    }
+
+    throw new Error("The window kept resizing while it was captured");
+  }
+
+  /**
+   * Returns the platform window handle, for passing the window to other
+   * native plugins.
+   */
+  rawHandle(): RawWindowHandle {
+    return Plug.core.opSync("pane_window_raw_handle", this.rid);
+  }
+
+  /**
+   * Uses the accelerators of a menu as the accelerator table of the window,
+   * activating its items with their key combinations while the window is
+   * focused, or removes the current table. The menu itself is not shown, a
+   * menu can be shown with `PaneMenu.popup`.
+   */
+  setAccelerators(menu?: PaneMenu): void {
+    Plug.core.opSync("pane_window_set_accelerators", {
+      rid: this.rid,
+      menu: menu?.rid,
+    });
+  }
+
+  /**
+   * Shows a message box that is modal to the window, resolving to the button
+   * that was chosen. Closing the message box or pressing Escape chooses
+   * `"cancel"`, or `"no"` or `"ok"` if there is no cancel button.
+   *
+   * While the message box is open, input to the window is blocked. It is drawn
+   * like `present` does, so it is currently only supported on X11, elsewhere
+   * the returned promise rejects with a `NotSupported` error.
+   */
+  messageBox(options: MessageBoxOptions): Promise<MessageBoxButton> {
+    return Plug.core.opAsync("pane_window_message_box", {
+      rid: this.rid,
+      ...options,
+    });
+  }
+
+  /**
+   * Shows a dialog for opening a file on top of the window, resolving to its
+   * path or to `undefined` if the dialog was cancelled.
+   *
+   * File dialogs use the XDG desktop portal, and require the plugin to be
+   * built with the `freedesktop` feature on Linux and the BSDs.
+   */
+  async openFileDialog(
+    options: FileDialogOptions = {},
+  ): Promise<string | undefined> {
+    return await Plug.core.opAsync("pane_window_open_file_dialog", {
+      rid: this.rid,
+      ...options,
+    }) ?? undefined;
+  }
+
+  /**
+   * Shows a dialog for opening several files on top of the window, resolving
+   * to their paths or to an empty array if the dialog was cancelled.
+   */
+  openFilesDialog(options: FileDialogOptions = {}): Promise<string[]> {
+    return Plug.core.opAsync("pane_window_open_files_dialog", {
+      rid: this.rid,
+      ...options,
+    });
+  }
+
+  /**
+   * Shows a dialog for choosing a directory on top of the window, resolving
+   * to its path or to `undefined` if the dialog was cancelled.
+   */
+  async openDirectoryDialog(
+    options: FileDialogOptions = {},
+  ): Promise<string | undefined> {
+    return await Plug.core.opAsync("pane_window_open_directory_dialog", {
+      rid: this.rid,
+      ...options,
+    }) ?? undefined;
+  }
+
+  /**
+   * Shows a dialog for saving a file on top of the window, resolving to the
+   * chosen path or to `undefined` if the dialog was cancelled.
+   */
+  async saveFileDialog(
+    options: FileDialogOptions = {},
+  ): Promise<string | undefined> {
+    return await Plug.core.opAsync("pane_window_save_file_dialog", {
+      rid: this.rid,
+      ...options,
+    }) ?? undefined;
+  }
+}
+
+/**
+ * Represents a menu tree, activated items are reported as `menuEvent`s.
+ */
+export class PaneMenu {
+  readonly rid: number;
+
+  constructor(items: MenuItem[]) {
+    this.rid = Plug.core.opSync("pane_menu_new", items);
+  }
+
+  /** Checks or unchecks the checkbox item with the given id. */
+  setChecked(id: string, checked: boolean): void {
+    Plug.core.opSync("pane_menu_set_checked", { rid: this.rid, id, checked });
+  }
+
+  /** Enables or disables the item with the given id. */
+  setEnabled(id: string, enabled: boolean): void {
+    Plug.core.opSync("pane_menu_set_enabled", { rid: this.rid, id, enabled });
+  }
+
+  /**
+   * Pops up the menu as a context menu at a position relative to the client
+   * area of the window. It is closed when an item is activated, or the window
+   * is clicked, loses focus or receives Escape. While it is open the arrow
+   * keys and Return of the window navigate the menu instead of emitting
+   * events. Currently only supported on X11.
+   */
+  popup(window: PaneWindow, position: Position): void {
+    Plug.core.opSync("pane_menu_popup", {
+      rid: this.rid,
+      window: window.rid,
+      position,
+    });
+  }
+}
+
+/** Options for creating a `PaneTray`. */
+export interface PaneTrayOptions {
+  /** The name of the application the tray belongs to. */
+  title?: string;
+  icon?: Icon | Icon[];
+  tooltip?: string;
+  /** The menu shown when the icon is right clicked. */
+  menu?: PaneMenu;
+}
+
+/**
+ * Represents a system tray icon, using the freedesktop StatusNotifierItem
+ * protocol. Clicks are reported as `trayEvent`s by the event loop. If no tray
+ * is running, the icon is shown once one starts.
+ *
+ * Requires the plugin to be built with the `freedesktop` feature, which is
+ * only available on Linux and the BSDs.
+ */
+export class PaneTray {
+  readonly rid: number;
+
+  constructor(options: PaneTrayOptions = {}) {
+    const { sizes, rgba } = packIcons(options.icon ?? []);
+
+    this.rid = Plug.core.opSync("pane_tray_new", {
+      title: options.title,
+      tooltip: options.tooltip,
+      sizes,
+      menu: options.menu?.rid,
+    }, rgba);
+  }
+
+  /**
+   * Sets the tray icon, several sizes of the same icon may be given. Throws a
+   * `BadIconError` if the pixel data does not match the dimensions of an icon.
+   */
+  setIcon(icons: Icon | Icon[]): void {
+    const { sizes, rgba } = packIcons(icons);
+
+    Plug.core.opSync("pane_tray_set_icon", { rid: this.rid, sizes }, rgba);
+  }
+
+  setTooltip(tooltip: string): void {
+    Plug.core.opSync("pane_tray_set_tooltip", { rid: this.rid, tooltip });
+  }
+
+  /** Sets the menu shown when the icon is right clicked, or removes it. */
+  setMenu(menu?: PaneMenu): void {
+    Plug.core.opSync("pane_tray_set_menu", { rid: this.rid, menu: menu?.rid });
+  }
+
+  /** Removes the icon from the tray. */
+  close(): void {
+    Plug.core.close(this.rid);
+  }
+}
+
+/** Options for showing a `PaneNotification`. */
+export interface PaneNotificationOptions {
+  /** The name of the application sending the notification. */
+  appName?: string;
+  title: string;
+  body?: string;
+  icon?: Icon;
+  /**
+   * Buttons shown on the notification. An action with the id `"default"` is
+   * invoked when the notification itself is clicked.
+   */
+  actions?: { id: string; label: string }[];
+  /**
+   * Milliseconds until the notification expires, `0` to never expire. The
+   * notification server decides when this is not given.
+   */
+  timeout?: number;
+  urgency?: "low" | "normal" | "critical";
+}
+
+/**
+ * Represents a desktop notification, using the freedesktop notifications
+ * protocol. Invoked actions and the closing of the notification are reported
+ * as `notificationEvent`s by the event loop.
+ *
+ * Requires the plugin to be built with the `freedesktop` feature, which is
+ * only available on Linux and the BSDs.
+ */
+export class PaneNotification {
+  /** The id the notification server assigned to this notification. */
+  readonly id: number;
+
+  /** Shows a notification. */
+  constructor(options: PaneNotificationOptions) {
+    const { icon, ...notification } = options;
+
+    this.id = Plug.core.opSync(
+      "pane_notification_show",
+      {
+        ...notification,
+        icon: icon && { width: icon.width, height: icon.height },
+      },
+      icon?.rgba,
+    );
+  }
+
+  /** Closes the notification, if it is still shown. */
+  close(): void {
+    Plug.core.opSync("pane_notification_close", this.id);
+  }
 }
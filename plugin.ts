@@ -1,4 +1,5 @@
 import { Plug } from "./deps.ts";
+import { BadIconError, BadImageError } from "./errors.ts";
 
 const VERSION = "0.2.0";
 const POLICY = Deno.env.get("PLUGIN_URL") === undefined
@@ -12,3 +13,6 @@ await Plug.prepare({
   url: PLUGIN_URL,
   policy: POLICY,
 });
+
+Plug.core.registerErrorClass("BadIcon", BadIconError);
+Plug.core.registerErrorClass("BadImage", BadImageError);
@@ -0,0 +1,212 @@
+//! A stable Rust API for other deno extensions that want to use pane windows,
+//! for example to create a GPU surface for one.
+//!
+//! Extensions should depend on this crate and reach `deno_core`, `winit` and
+//! `raw_window_handle` through the re-exports below, so that the types they
+//! use are the same ones pane was built with. Windows are looked up by the
+//! `ResourceId` of a `PaneWindow`, from the same `OpState` pane's ops use.
+//! Surfaces can be kept up to date by listening for `SurfaceEvent`s with
+//! `WindowResource::add_surface_listener`.
+//!
+//! ```no_run
+//! use pane::api;
+//! use pane::api::deno_core::error::AnyError;
+//! use pane::api::deno_core::OpState;
+//! use pane::api::deno_core::ResourceId;
+//!
+//! fn op_surface_size(
+//!   state: &mut OpState,
+//!   rid: ResourceId,
+//! ) -> Result<(u32, u32), AnyError> {
+//!   let window = api::borrow_window(state, rid)?;
+//!   let size = window.window().inner_size();
+//!
+//!   Ok((size.width, size.height))
+//! }
+//! ```
+
+use std::rc::Rc;
+
+use deno_core::error::bad_resource_id;
+use deno_core::error::generic_error;
+use deno_core::error::AnyError;
+use deno_core::OpState;
+use deno_core::ResourceId;
+
+use raw_window_handle::HasRawWindowHandle;
+use raw_window_handle::RawWindowHandle;
+
+pub use deno_core;
+pub use raw_window_handle;
+pub use winit;
+
+pub use crate::surface::ListenerId;
+pub use crate::surface::SurfaceEvent;
+pub use crate::WindowResource;
+
+/// The version of this API. It is incremented whenever a breaking change is
+/// made to anything reachable from this module, so extensions can check they
+/// were built against a compatible pane.
+pub const API_VERSION: u32 = 1;
+
+/// Errors unless `version`, the `API_VERSION` an extension was built against,
+/// is the version of this pane.
+///
+/// ```no_run
+/// pane::api::check_version(1).expect("incompatible pane");
+/// ```
+pub fn check_version(version: u32) -> Result<(), AnyError> {
+  if version != API_VERSION {
+    return Err(generic_error(format!(
+      "Built against version {} of the pane API, but pane provides version {}",
+      version, API_VERSION
+    )));
+  }
+
+  Ok(())
+}
+
+/// Borrows the window with the given resource id.
+pub fn borrow_window(
+  state: &OpState,
+  rid: ResourceId,
+) -> Result<Rc<WindowResource>, AnyError> {
+  state
+    .resource_table
+    .get::<WindowResource>(rid)
+    .ok_or_else(bad_resource_id)
+}
+
+/// Returns the raw handle of the window with the given resource id.
+pub fn raw_handle(
+  state: &OpState,
+  rid: ResourceId,
+) -> Result<RawWindowHandle, AnyError> {
+  Ok(borrow_window(state, rid)?.raw_window_handle())
+}
+
+/// Implemented by extensions that create surfaces, such as GPU swapchains,
+/// for pane windows.
+pub trait CreateSurface {
+  type Surface;
+
+  fn create_surface(
+    &self,
+    window: &WindowResource,
+  ) -> Result<Self::Surface, AnyError>;
+}
+
+/// Creates a surface for the window with the given resource id.
+pub fn create_surface<T: CreateSurface>(
+  state: &OpState,
+  rid: ResourceId,
+  creator: &T,
+) -> Result<T::Surface, AnyError> {
+  creator.create_surface(&*borrow_window(state, rid)?)
+}
+
+#[cfg(test)]
+mod tests {
+  use deno_core::error::get_custom_error_class;
+  use deno_core::op_sync;
+  use deno_core::Extension;
+  use deno_core::JsRuntime;
+  use deno_core::RuntimeOptions;
+  use deno_core::ZeroCopyBuf;
+
+  use super::*;
+
+  /// What the test extension found when it looked up a window.
+  type Lookup = Result<RawWindowHandle, Option<&'static str>>;
+
+  /// A second extension, like the ones this API is for, that looks windows up
+  /// from the state shared with pane's ops.
+  fn test_extension() -> Extension {
+    Extension::builder()
+      .ops(vec![("test_raw_handle", op_sync(op_raw_handle))])
+      .build()
+  }
+
+  fn op_raw_handle(
+    state: &mut OpState,
+    rid: ResourceId,
+    _zero_copy: Option<ZeroCopyBuf>,
+  ) -> Result<(), AnyError> {
+    let lookup: Lookup =
+      raw_handle(state, rid).map_err(|error| get_custom_error_class(&error));
+    state.put(lookup);
+
+    Ok(())
+  }
+
+  fn runtime() -> JsRuntime {
+    JsRuntime::new(RuntimeOptions {
+      extensions: vec![
+        #[cfg(feature = "init")]
+        crate::init(),
+        test_extension(),
+      ],
+      ..Default::default()
+    })
+  }
+
+  fn look_up(runtime: &mut JsRuntime, rid: ResourceId) -> Lookup {
+    let source = format!("Deno.core.opSync(\"test_raw_handle\", {});", rid);
+    runtime.execute("look_up.js", &source).unwrap();
+
+    runtime.op_state().borrow_mut().take()
+  }
+
+  #[test]
+  fn accepts_the_current_version() {
+    assert!(check_version(API_VERSION).is_ok());
+  }
+
+  #[test]
+  fn rejects_other_versions() {
+    let error = check_version(API_VERSION + 1).unwrap_err();
+
+    assert!(error
+      .to_string()
+      .contains(&format!("version {} of the pane API", API_VERSION + 1)));
+  }
+
+  #[test]
+  fn rejects_unknown_resource_ids() {
+    let mut runtime = runtime();
+
+    let state = runtime.op_state();
+    let error = borrow_window(&state.borrow(), 0).err().unwrap();
+    assert_eq!(get_custom_error_class(&error), Some("BadResource"));
+    assert_eq!(look_up(&mut runtime, 0), Err(Some("BadResource")));
+  }
+
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  #[test]
+  #[ignore = "needs an X server, run with xvfb-run cargo test -- --ignored"]
+  fn looks_up_windows() {
+    use winit::event_loop::EventLoop;
+    use winit::platform::unix::EventLoopExtUnix;
+
+    let event_loop = EventLoop::new_any_thread();
+    let window = WindowResource::new(&event_loop).unwrap();
+    let handle = window.raw_window_handle();
+    let id = window.window().id();
+    let mut runtime = runtime();
+    let state = runtime.op_state();
+    let rid = state.borrow_mut().resource_table.add(window);
+
+    assert_eq!(
+      borrow_window(&state.borrow(), rid).unwrap().window().id(),
+      id
+    );
+    // Another extension sees the window pane's ops added.
+    assert_eq!(look_up(&mut runtime, rid), Ok(handle));
+  }
+}
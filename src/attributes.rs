@@ -0,0 +1,524 @@
+use deno_core::error::AnyError;
+use deno_core::serde::Deserialize;
+use deno_core::ResourceId;
+
+use winit::dpi::PhysicalPosition;
+use winit::dpi::Position;
+use winit::dpi::Size;
+use winit::event_loop::EventLoopWindowTarget;
+use winit::window::Window;
+use winit::window::WindowBuilder;
+
+use crate::state::WindowState;
+
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+use winit::platform::unix::WindowBuilderExtUnix;
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+use winit::platform::unix::XWindowType;
+#[cfg(target_os = "windows")]
+use winit::platform::windows::WindowBuilderExtWindows;
+
+/// The attributes a window is created with, unset attributes use winit's
+/// defaults.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WindowAttributes {
+  title: Option<String>,
+  inner_size: Option<Size>,
+  resizable: Option<bool>,
+  visible: Option<bool>,
+  decorations: Option<bool>,
+  always_on_top: Option<bool>,
+  transparent: Option<bool>,
+  /// The window that owns this one, such as the main window of a dialog.
+  owner: Option<ResourceId>,
+  /// Whether the window blocks its owner, only a hint to the window manager.
+  modal: Option<bool>,
+  popup: Option<PopupAttributes>,
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  unix: Option<UnixAttributes>,
+}
+
+/// Makes the window a popup, such as a menu or tooltip, owned by `parent`.
+/// Popups are undecorated and are closed when focus moves away from them and
+/// their parent. On X11 they skip the taskbar as override-redirect windows.
+#[derive(Deserialize)]
+pub struct PopupAttributes {
+  parent: ResourceId,
+  /// The position of the popup relative to the client area of its parent.
+  position: Position,
+}
+
+/// Attributes only relevant on Linux and the BSDs, ignored elsewhere.
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnixAttributes {
+  /// The `WM_CLASS` of the window on X11, defaults to the name of the binary.
+  class: Option<WindowClass>,
+  /// The `_NET_WM_WINDOW_TYPE` of the window on X11, in order of preference.
+  window_types: Option<Vec<WindowType>>,
+  /// Whether the window manager should ignore the window on X11.
+  override_redirect: Option<bool>,
+  /// The application id on Wayland, matching the `.desktop` file.
+  app_id: Option<String>,
+  /// The screen to create the window on on X11.
+  x11_screen: Option<i32>,
+}
+
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+#[derive(Deserialize)]
+pub struct WindowClass {
+  instance: String,
+  class: String,
+}
+
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WindowType {
+  Desktop,
+  Dock,
+  Toolbar,
+  Menu,
+  Utility,
+  Splash,
+  Dialog,
+  DropdownMenu,
+  PopupMenu,
+  Tooltip,
+  Notification,
+  Combo,
+  Dnd,
+  Normal,
+}
+
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+impl From<WindowType> for XWindowType {
+  fn from(window_type: WindowType) -> Self {
+    match window_type {
+      WindowType::Desktop => XWindowType::Desktop,
+      WindowType::Dock => XWindowType::Dock,
+      WindowType::Toolbar => XWindowType::Toolbar,
+      WindowType::Menu => XWindowType::Menu,
+      WindowType::Utility => XWindowType::Utility,
+      WindowType::Splash => XWindowType::Splash,
+      WindowType::Dialog => XWindowType::Dialog,
+      WindowType::DropdownMenu => XWindowType::DropdownMenu,
+      WindowType::PopupMenu => XWindowType::PopupMenu,
+      WindowType::Tooltip => XWindowType::Tooltip,
+      WindowType::Notification => XWindowType::Notification,
+      WindowType::Combo => XWindowType::Combo,
+      WindowType::Dnd => XWindowType::Dnd,
+      WindowType::Normal => XWindowType::Normal,
+    }
+  }
+}
+
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+impl UnixAttributes {
+  fn apply(&self, mut builder: WindowBuilder) -> WindowBuilder {
+    if let Some(WindowClass { instance, class }) = &self.class {
+      builder = builder.with_class(instance.clone(), class.clone());
+    }
+    if let Some(window_types) = &self.window_types {
+      builder = builder.with_x11_window_type(
+        window_types
+          .iter()
+          .copied()
+          .map(XWindowType::from)
+          .collect(),
+      );
+    }
+    if let Some(override_redirect) = self.override_redirect {
+      builder = builder.with_override_redirect(override_redirect);
+    }
+    if let Some(app_id) = &self.app_id {
+      builder = builder.with_app_id(app_id.clone());
+    }
+    if let Some(x11_screen) = self.x11_screen {
+      builder = builder.with_x11_screen(x11_screen);
+    }
+
+    builder
+  }
+}
+
+impl WindowAttributes {
+  /// The window that owns this one, the parent if this is a popup.
+  pub fn owner(&self) -> Option<ResourceId> {
+    self.popup.as_ref().map(|popup| popup.parent).or(self.owner)
+  }
+
+  pub fn is_popup(&self) -> bool {
+    self.popup.is_some()
+  }
+
+  #[allow(unused_variables)]
+  pub fn builder(
+    &self,
+    event_loop: &EventLoopWindowTarget<()>,
+    owner: Option<&Window>,
+  ) -> Result<WindowBuilder, AnyError> {
+    let mut builder = match (&self.popup, owner) {
+      (Some(popup), Some(parent)) => popup_builder(parent, popup.position)?,
+      _ => WindowBuilder::new(),
+    };
+
+    if let Some(title) = &self.title {
+      builder = builder.with_title(title);
+    }
+    if let Some(inner_size) = self.inner_size {
+      builder = builder.with_inner_size(inner_size);
+    }
+    if let Some(resizable) = self.resizable {
+      builder = builder.with_resizable(resizable);
+    }
+    if let Some(visible) = self.visible {
+      builder = builder.with_visible(visible);
+    }
+    if let Some(decorations) = self.decorations {
+      builder = builder.with_decorations(decorations);
+    }
+    if let Some(always_on_top) = self.always_on_top {
+      builder = builder.with_always_on_top(always_on_top);
+    }
+    if let Some(transparent) = self.transparent {
+      builder = builder.with_transparent(transparent);
+    }
+
+    // winit creates X11 windows with the parent's visual, which has no alpha
+    // channel, so transparent windows need a 32 bit visual.
+    #[cfg(any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    ))]
+    if self.transparent == Some(true) {
+      if let Some(visual) = crate::x11::argb_visual(event_loop)? {
+        builder = builder.with_x11_visual(&visual);
+      }
+    }
+
+    #[cfg(any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    ))]
+    if let Some(unix) = &self.unix {
+      builder = unix.apply(builder);
+    }
+
+    #[cfg(target_os = "windows")]
+    if let Some(raw_window_handle::RawWindowHandle::Windows(handle)) =
+      owner.map(raw_window_handle::HasRawWindowHandle::raw_window_handle)
+    {
+      builder = builder.with_owner_window(handle.hwnd as _);
+    }
+
+    // The owner and modal hints are set on the window before it is shown, as
+    // window managers only read them when a window is mapped.
+    #[cfg(any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    ))]
+    if self.has_owner_hints() {
+      builder = builder.with_visible(false);
+    }
+
+    Ok(builder)
+  }
+
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  fn has_owner_hints(&self) -> bool {
+    self.owner().is_some() || self.modal.unwrap_or(false)
+  }
+
+  /// Applies the attributes that can only be set once the window exists.
+  #[allow(unused_variables)]
+  pub fn apply(
+    &self,
+    window: &Window,
+    owner: Option<&Window>,
+  ) -> Result<(), AnyError> {
+    #[cfg(any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    ))]
+    if self.has_owner_hints() {
+      if let Some(x_window) = crate::x11::XWindow::new(window)? {
+        if let Some(owner) = owner {
+          if let Some(owner) = crate::x11::XWindow::new(owner)? {
+            x_window.set_transient_for(&owner);
+          }
+        }
+        // Window managers ignore state change requests for unmapped windows,
+        // so the state winit would have requested is set here as well.
+        let mut net_wm_state = Vec::new();
+        if self.modal.unwrap_or(false) {
+          net_wm_state.push("_NET_WM_STATE_MODAL");
+        }
+        if self.always_on_top.unwrap_or(false) {
+          net_wm_state.push("_NET_WM_STATE_ABOVE");
+        }
+        if !net_wm_state.is_empty() {
+          x_window.set_atoms("_NET_WM_STATE", &net_wm_state);
+        }
+      }
+
+      window.set_visible(self.visible.unwrap_or(true));
+    }
+
+    Ok(())
+  }
+
+  /// The state of a window created with these attributes.
+  pub fn state(&self) -> WindowState {
+    let default = WindowState::default();
+
+    WindowState {
+      title: self.title.clone().unwrap_or(default.title),
+      resizable: self.resizable.unwrap_or(default.resizable),
+      visible: self.visible.unwrap_or(default.visible),
+      decorations: self
+        .decorations
+        .unwrap_or(default.decorations && !self.is_popup()),
+      always_on_top: self.always_on_top.unwrap_or(default.always_on_top),
+      transparent: self.transparent.unwrap_or(default.transparent),
+      ..default
+    }
+  }
+}
+
+/// Returns a builder for an undecorated popup window, positioned relative to
+/// the client area of `parent`.
+pub fn popup_builder(
+  parent: &Window,
+  position: Position,
+) -> Result<WindowBuilder, AnyError> {
+  let origin = parent.inner_position()?;
+  let offset = position.to_physical::<i32>(parent.scale_factor());
+
+  let builder = WindowBuilder::new().with_decorations(false).with_position(
+    PhysicalPosition::new(origin.x + offset.x, origin.y + offset.y),
+  );
+
+  // Override-redirect windows are placed exactly where they are asked to be,
+  // and are left out of the taskbar.
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  let builder = builder
+    .with_override_redirect(true)
+    .with_x11_window_type(vec![XWindowType::PopupMenu]);
+
+  Ok(builder)
+}
+
+#[cfg(test)]
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+mod tests {
+  use std::ffi::CStr;
+  use std::mem::MaybeUninit;
+
+  use raw_window_handle::HasRawWindowHandle;
+  use raw_window_handle::RawWindowHandle;
+
+  use winit::event_loop::EventLoop;
+  use winit::platform::unix::EventLoopExtUnix;
+
+  use x11_dl::xlib;
+  use x11_dl::xlib::Xlib;
+
+  use crate::x11::XWindow;
+
+  use super::*;
+
+  /// Returns the `WM_CLASS` and override-redirect flag of a window.
+  fn class_and_override_redirect(window: &Window) -> ((String, String), bool) {
+    let handle = match window.raw_window_handle() {
+      RawWindowHandle::Xlib(handle) => handle,
+      _ => panic!("not an X11 window"),
+    };
+    let display = handle.display as *mut xlib::Display;
+    let xlib = Xlib::open().unwrap();
+
+    unsafe {
+      let mut hint = MaybeUninit::<xlib::XClassHint>::uninit();
+      assert_ne!(
+        (xlib.XGetClassHint)(display, handle.window, hint.as_mut_ptr()),
+        0
+      );
+      let hint = hint.assume_init();
+      let class = (
+        CStr::from_ptr(hint.res_name).to_string_lossy().into_owned(),
+        CStr::from_ptr(hint.res_class)
+          .to_string_lossy()
+          .into_owned(),
+      );
+      (xlib.XFree)(hint.res_name as *mut _);
+      (xlib.XFree)(hint.res_class as *mut _);
+
+      let mut attributes = MaybeUninit::uninit();
+      assert_ne!(
+        (xlib.XGetWindowAttributes)(
+          display,
+          handle.window,
+          attributes.as_mut_ptr()
+        ),
+        0
+      );
+
+      (class, attributes.assume_init().override_redirect != 0)
+    }
+  }
+
+  #[test]
+  #[ignore = "needs an X server, run with xvfb-run cargo test -- --ignored"]
+  fn creates_windows_with_unix_attributes() {
+    let event_loop = EventLoop::<()>::new_any_thread();
+    let attributes = WindowAttributes {
+      unix: Some(UnixAttributes {
+        class: Some(WindowClass {
+          instance: "pane-test".into(),
+          class: "PaneTest".into(),
+        }),
+        window_types: Some(vec![WindowType::Dialog, WindowType::Utility]),
+        override_redirect: Some(true),
+        app_id: None,
+        x11_screen: None,
+      }),
+      ..WindowAttributes::default()
+    };
+    let window = attributes
+      .builder(&event_loop, None)
+      .unwrap()
+      .build(&event_loop)
+      .unwrap();
+    let x_window = XWindow::new(&window).unwrap().expect("an X11 window");
+
+    let (class, override_redirect) = class_and_override_redirect(&window);
+    assert_eq!(class, ("pane-test".into(), "PaneTest".into()));
+    assert!(override_redirect);
+    assert_eq!(
+      x_window
+        .get_longs("_NET_WM_WINDOW_TYPE", xlib::XA_ATOM)
+        .unwrap(),
+      [
+        x_window.atom("_NET_WM_WINDOW_TYPE_DIALOG"),
+        x_window.atom("_NET_WM_WINDOW_TYPE_UTILITY"),
+      ]
+    );
+  }
+
+  #[test]
+  #[ignore = "needs an X server, run with xvfb-run cargo test -- --ignored"]
+  fn creates_windows_without_override_redirect_by_default() {
+    let event_loop = EventLoop::<()>::new_any_thread();
+    let window = WindowAttributes::default()
+      .builder(&event_loop, None)
+      .unwrap()
+      .build(&event_loop)
+      .unwrap();
+
+    let (_, override_redirect) = class_and_override_redirect(&window);
+    assert!(!override_redirect);
+  }
+
+  #[test]
+  #[ignore = "needs an X server, run with xvfb-run cargo test -- --ignored"]
+  fn keeps_the_state_of_modal_windows() {
+    let event_loop = EventLoop::<()>::new_any_thread();
+    let attributes = WindowAttributes {
+      always_on_top: Some(true),
+      modal: Some(true),
+      ..WindowAttributes::default()
+    };
+    let window = attributes
+      .builder(&event_loop, None)
+      .unwrap()
+      .build(&event_loop)
+      .unwrap();
+    attributes.apply(&window, None).unwrap();
+    let x_window = XWindow::new(&window).unwrap().expect("an X11 window");
+
+    let net_wm_state =
+      x_window.get_longs("_NET_WM_STATE", xlib::XA_ATOM).unwrap();
+    assert!(net_wm_state.contains(&x_window.atom("_NET_WM_STATE_MODAL")));
+    assert!(net_wm_state.contains(&x_window.atom("_NET_WM_STATE_ABOVE")));
+  }
+}
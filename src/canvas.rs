@@ -0,0 +1,333 @@
+use deno_core::error::AnyError;
+
+use winit::dpi::PhysicalSize;
+use winit::window::Window;
+
+use crate::framebuffer;
+
+/// The size of a glyph of the built-in font, before scaling.
+pub const GLYPH_WIDTH: i32 = 8;
+pub const GLYPH_HEIGHT: i32 = 8;
+
+/// BGRA pixels drawn in software and presented with `framebuffer::present`,
+/// for the few widgets pane draws itself.
+///
+/// Coordinates are in canvas units, each of which is `scale` by `scale`
+/// physical pixels, so the 8x8 built-in font stays readable on high DPI
+/// screens. Drawing is clipped to the canvas.
+pub struct Canvas {
+  width: i32,
+  height: i32,
+  scale: i32,
+  bgra: Vec<u8>,
+}
+
+impl Canvas {
+  pub fn new(width: u32, height: u32, scale: u32) -> Self {
+    let scale = scale.max(1);
+    let size = (width * scale) as usize * (height * scale) as usize * 4;
+
+    Self {
+      width: width as i32,
+      height: height as i32,
+      scale: scale as i32,
+      bgra: vec![0; size],
+    }
+  }
+
+  /// The size of the canvas in physical pixels.
+  pub fn size(&self) -> PhysicalSize<u32> {
+    PhysicalSize::new(
+      (self.width * self.scale) as u32,
+      (self.height * self.scale) as u32,
+    )
+  }
+
+  /// Converts a location in physical pixels to canvas units.
+  pub fn to_canvas(&self, x: f64, y: f64) -> (i32, i32) {
+    let scale = self.scale as f64;
+
+    ((x / scale).floor() as i32, (y / scale).floor() as i32)
+  }
+
+  /// Returns the BGRA pixel at a physical location, for tests.
+  #[cfg(test)]
+  fn pixel(&self, x: i32, y: i32) -> [u8; 4] {
+    let offset = ((y * self.width * self.scale + x) * 4) as usize;
+    let mut pixel = [0; 4];
+    pixel.copy_from_slice(&self.bgra[offset..offset + 4]);
+
+    pixel
+  }
+
+  /// Fills the whole canvas with a `0xRRGGBB` color.
+  pub fn clear(&mut self, color: u32) {
+    self.fill_rect(color, 0, 0, self.width, self.height);
+  }
+
+  /// Fills a rectangle with a `0xRRGGBB` color.
+  pub fn fill_rect(
+    &mut self,
+    color: u32,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+  ) {
+    let left = x.max(0) * self.scale;
+    let top = y.max(0) * self.scale;
+    let right = (x + width).min(self.width) * self.scale;
+    let bottom = (y + height).min(self.height) * self.scale;
+    if left >= right || top >= bottom {
+      return;
+    }
+
+    let [blue, green, red, _] = color.to_le_bytes();
+    let pixel = [blue, green, red, 0xff];
+    let stride = (self.width * self.scale * 4) as usize;
+
+    for row in top..bottom {
+      let start = row as usize * stride + left as usize * 4;
+      let end = row as usize * stride + right as usize * 4;
+
+      for destination in self.bgra[start..end].chunks_exact_mut(4) {
+        destination.copy_from_slice(&pixel);
+      }
+    }
+  }
+
+  /// Draws the one unit wide outline of a rectangle.
+  pub fn stroke_rect(
+    &mut self,
+    color: u32,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+  ) {
+    self.fill_rect(color, x, y, width, 1);
+    self.fill_rect(color, x, y + height - 1, width, 1);
+    self.fill_rect(color, x, y, 1, height);
+    self.fill_rect(color, x + width - 1, y, 1, height);
+  }
+
+  /// Draws a line of text with its top left corner at `x` and `y`. Characters
+  /// the built-in font does not cover are drawn as `?`.
+  pub fn draw_text(&mut self, color: u32, x: i32, y: i32, text: &str) {
+    for (index, character) in text.chars().enumerate() {
+      let glyph = glyph(character);
+      let left = x + index as i32 * GLYPH_WIDTH;
+
+      for (row, bits) in glyph.iter().enumerate() {
+        for column in 0..GLYPH_WIDTH {
+          if bits & (1 << column) != 0 {
+            self.fill_rect(color, left + column, y + row as i32, 1, 1);
+          }
+        }
+      }
+    }
+  }
+
+  /// Blits the canvas to the top left of the window's client area.
+  pub fn present(&self, window: &Window) -> Result<(), AnyError> {
+    let size = self.size();
+
+    framebuffer::present(window, &self.bgra, size.width, size.height)
+  }
+}
+
+/// The width of a line of text in canvas units.
+pub fn text_width(text: &str) -> i32 {
+  text.chars().count() as i32 * GLYPH_WIDTH
+}
+
+fn glyph(character: char) -> &'static [u8; 8] {
+  let index = match character {
+    ' '..='~' => character as usize - ' ' as usize,
+    _ => '?' as usize - ' ' as usize,
+  };
+
+  &FONT[index]
+}
+
+/// The printable ASCII characters from the public domain font8x8 by Daniel
+/// Hepper. Each byte is a row from the top, its lowest bit the leftmost pixel.
+const FONT: [[u8; 8]; 95] = [
+  [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+  [0x18, 0x3C, 0x3C, 0x18, 0x18, 0x00, 0x18, 0x00], // '!'
+  [0x36, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '"'
+  [0x36, 0x36, 0x7F, 0x36, 0x7F, 0x36, 0x36, 0x00], // '#'
+  [0x0C, 0x3E, 0x03, 0x1E, 0x30, 0x1F, 0x0C, 0x00], // '$'
+  [0x00, 0x63, 0x33, 0x18, 0x0C, 0x66, 0x63, 0x00], // '%'
+  [0x1C, 0x36, 0x1C, 0x6E, 0x3B, 0x33, 0x6E, 0x00], // '&'
+  [0x06, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00], // '''
+  [0x18, 0x0C, 0x06, 0x06, 0x06, 0x0C, 0x18, 0x00], // '('
+  [0x06, 0x0C, 0x18, 0x18, 0x18, 0x0C, 0x06, 0x00], // ')'
+  [0x00, 0x66, 0x3C, 0xFF, 0x3C, 0x66, 0x00, 0x00], // '*'
+  [0x00, 0x0C, 0x0C, 0x3F, 0x0C, 0x0C, 0x00, 0x00], // '+'
+  [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C, 0x06], // ','
+  [0x00, 0x00, 0x00, 0x3F, 0x00, 0x00, 0x00, 0x00], // '-'
+  [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C, 0x00], // '.'
+  [0x60, 0x30, 0x18, 0x0C, 0x06, 0x03, 0x01, 0x00], // '/'
+  [0x3E, 0x63, 0x73, 0x7B, 0x6F, 0x67, 0x3E, 0x00], // '0'
+  [0x0C, 0x0E, 0x0C, 0x0C, 0x0C, 0x0C, 0x3F, 0x00], // '1'
+  [0x1E, 0x33, 0x30, 0x1C, 0x06, 0x33, 0x3F, 0x00], // '2'
+  [0x1E, 0x33, 0x30, 0x1C, 0x30, 0x33, 0x1E, 0x00], // '3'
+  [0x38, 0x3C, 0x36, 0x33, 0x7F, 0x30, 0x78, 0x00], // '4'
+  [0x3F, 0x03, 0x1F, 0x30, 0x30, 0x33, 0x1E, 0x00], // '5'
+  [0x1C, 0x06, 0x03, 0x1F, 0x33, 0x33, 0x1E, 0x00], // '6'
+  [0x3F, 0x33, 0x30, 0x18, 0x0C, 0x0C, 0x0C, 0x00], // '7'
+  [0x1E, 0x33, 0x33, 0x1E, 0x33, 0x33, 0x1E, 0x00], // '8'
+  [0x1E, 0x33, 0x33, 0x3E, 0x30, 0x18, 0x0E, 0x00], // '9'
+  [0x00, 0x0C, 0x0C, 0x00, 0x00, 0x0C, 0x0C, 0x00], // ':'
+  [0x00, 0x0C, 0x0C, 0x00, 0x00, 0x0C, 0x0C, 0x06], // ';'
+  [0x18, 0x0C, 0x06, 0x03, 0x06, 0x0C, 0x18, 0x00], // '<'
+  [0x00, 0x00, 0x3F, 0x00, 0x00, 0x3F, 0x00, 0x00], // '='
+  [0x06, 0x0C, 0x18, 0x30, 0x18, 0x0C, 0x06, 0x00], // '>'
+  [0x1E, 0x33, 0x30, 0x18, 0x0C, 0x00, 0x0C, 0x00], // '?'
+  [0x3E, 0x63, 0x7B, 0x7B, 0x7B, 0x03, 0x1E, 0x00], // '@'
+  [0x0C, 0x1E, 0x33, 0x33, 0x3F, 0x33, 0x33, 0x00], // 'A'
+  [0x3F, 0x66, 0x66, 0x3E, 0x66, 0x66, 0x3F, 0x00], // 'B'
+  [0x3C, 0x66, 0x03, 0x03, 0x03, 0x66, 0x3C, 0x00], // 'C'
+  [0x1F, 0x36, 0x66, 0x66, 0x66, 0x36, 0x1F, 0x00], // 'D'
+  [0x7F, 0x46, 0x16, 0x1E, 0x16, 0x46, 0x7F, 0x00], // 'E'
+  [0x7F, 0x46, 0x16, 0x1E, 0x16, 0x06, 0x0F, 0x00], // 'F'
+  [0x3C, 0x66, 0x03, 0x03, 0x73, 0x66, 0x7C, 0x00], // 'G'
+  [0x33, 0x33, 0x33, 0x3F, 0x33, 0x33, 0x33, 0x00], // 'H'
+  [0x1E, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // 'I'
+  [0x78, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1E, 0x00], // 'J'
+  [0x67, 0x66, 0x36, 0x1E, 0x36, 0x66, 0x67, 0x00], // 'K'
+  [0x0F, 0x06, 0x06, 0x06, 0x46, 0x66, 0x7F, 0x00], // 'L'
+  [0x63, 0x77, 0x7F, 0x7F, 0x6B, 0x63, 0x63, 0x00], // 'M'
+  [0x63, 0x67, 0x6F, 0x7B, 0x73, 0x63, 0x63, 0x00], // 'N'
+  [0x1C, 0x36, 0x63, 0x63, 0x63, 0x36, 0x1C, 0x00], // 'O'
+  [0x3F, 0x66, 0x66, 0x3E, 0x06, 0x06, 0x0F, 0x00], // 'P'
+  [0x1E, 0x33, 0x33, 0x33, 0x3B, 0x1E, 0x38, 0x00], // 'Q'
+  [0x3F, 0x66, 0x66, 0x3E, 0x36, 0x66, 0x67, 0x00], // 'R'
+  [0x1E, 0x33, 0x07, 0x0E, 0x38, 0x33, 0x1E, 0x00], // 'S'
+  [0x3F, 0x2D, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // 'T'
+  [0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x3F, 0x00], // 'U'
+  [0x33, 0x33, 0x33, 0x33, 0x33, 0x1E, 0x0C, 0x00], // 'V'
+  [0x63, 0x63, 0x63, 0x6B, 0x7F, 0x77, 0x63, 0x00], // 'W'
+  [0x63, 0x63, 0x36, 0x1C, 0x1C, 0x36, 0x63, 0x00], // 'X'
+  [0x33, 0x33, 0x33, 0x1E, 0x0C, 0x0C, 0x1E, 0x00], // 'Y'
+  [0x7F, 0x63, 0x31, 0x18, 0x4C, 0x66, 0x7F, 0x00], // 'Z'
+  [0x1E, 0x06, 0x06, 0x06, 0x06, 0x06, 0x1E, 0x00], // '['
+  [0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x40, 0x00], // '\'
+  [0x1E, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1E, 0x00], // ']'
+  [0x08, 0x1C, 0x36, 0x63, 0x00, 0x00, 0x00, 0x00], // '^'
+  [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF], // '_'
+  [0x0C, 0x0C, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00], // '`'
+  [0x00, 0x00, 0x1E, 0x30, 0x3E, 0x33, 0x6E, 0x00], // 'a'
+  [0x07, 0x06, 0x06, 0x3E, 0x66, 0x66, 0x3B, 0x00], // 'b'
+  [0x00, 0x00, 0x1E, 0x33, 0x03, 0x33, 0x1E, 0x00], // 'c'
+  [0x38, 0x30, 0x30, 0x3E, 0x33, 0x33, 0x6E, 0x00], // 'd'
+  [0x00, 0x00, 0x1E, 0x33, 0x3F, 0x03, 0x1E, 0x00], // 'e'
+  [0x1C, 0x36, 0x06, 0x0F, 0x06, 0x06, 0x0F, 0x00], // 'f'
+  [0x00, 0x00, 0x6E, 0x33, 0x33, 0x3E, 0x30, 0x1F], // 'g'
+  [0x07, 0x06, 0x36, 0x6E, 0x66, 0x66, 0x67, 0x00], // 'h'
+  [0x0C, 0x00, 0x0E, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // 'i'
+  [0x30, 0x00, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1E], // 'j'
+  [0x07, 0x06, 0x66, 0x36, 0x1E, 0x36, 0x67, 0x00], // 'k'
+  [0x0E, 0x0C, 0x0C, 0x0C, 0x0C, 0x0C, 0x1E, 0x00], // 'l'
+  [0x00, 0x00, 0x33, 0x7F, 0x7F, 0x6B, 0x63, 0x00], // 'm'
+  [0x00, 0x00, 0x1F, 0x33, 0x33, 0x33, 0x33, 0x00], // 'n'
+  [0x00, 0x00, 0x1E, 0x33, 0x33, 0x33, 0x1E, 0x00], // 'o'
+  [0x00, 0x00, 0x3B, 0x66, 0x66, 0x3E, 0x06, 0x0F], // 'p'
+  [0x00, 0x00, 0x6E, 0x33, 0x33, 0x3E, 0x30, 0x78], // 'q'
+  [0x00, 0x00, 0x3B, 0x6E, 0x66, 0x06, 0x0F, 0x00], // 'r'
+  [0x00, 0x00, 0x3E, 0x03, 0x1E, 0x30, 0x1F, 0x00], // 's'
+  [0x08, 0x0C, 0x3E, 0x0C, 0x0C, 0x2C, 0x18, 0x00], // 't'
+  [0x00, 0x00, 0x33, 0x33, 0x33, 0x33, 0x6E, 0x00], // 'u'
+  [0x00, 0x00, 0x33, 0x33, 0x33, 0x1E, 0x0C, 0x00], // 'v'
+  [0x00, 0x00, 0x63, 0x6B, 0x7F, 0x7F, 0x36, 0x00], // 'w'
+  [0x00, 0x00, 0x63, 0x36, 0x1C, 0x36, 0x63, 0x00], // 'x'
+  [0x00, 0x00, 0x33, 0x33, 0x33, 0x3E, 0x30, 0x1F], // 'y'
+  [0x00, 0x00, 0x3F, 0x19, 0x0C, 0x26, 0x3F, 0x00], // 'z'
+  [0x38, 0x0C, 0x0C, 0x07, 0x0C, 0x0C, 0x38, 0x00], // '{'
+  [0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x18, 0x00], // '|'
+  [0x07, 0x0C, 0x0C, 0x38, 0x0C, 0x0C, 0x07, 0x00], // '}'
+  [0x6E, 0x3B, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // '~'
+];
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const RED: u32 = 0xff0000;
+  const BLUE: u32 = 0x0000ff;
+
+  #[test]
+  fn fills_scaled_rectangles_in_bgra() {
+    let mut canvas = Canvas::new(4, 3, 2);
+    canvas.fill_rect(RED, 1, 1, 2, 1);
+
+    assert_eq!(canvas.size(), PhysicalSize::new(8, 6));
+    assert_eq!(canvas.pixel(1, 2), [0, 0, 0, 0]);
+    assert_eq!(canvas.pixel(2, 2), [0, 0, 0xff, 0xff]);
+    assert_eq!(canvas.pixel(5, 3), [0, 0, 0xff, 0xff]);
+    assert_eq!(canvas.pixel(6, 3), [0, 0, 0, 0]);
+    assert_eq!(canvas.pixel(2, 4), [0, 0, 0, 0]);
+  }
+
+  #[test]
+  fn clips_drawing_to_the_canvas() {
+    let mut canvas = Canvas::new(4, 4, 1);
+    canvas.fill_rect(BLUE, -2, -2, 4, 4);
+    canvas.fill_rect(BLUE, 3, 3, 10, 10);
+    canvas.fill_rect(BLUE, 8, 0, 2, 2);
+
+    assert_eq!(canvas.pixel(0, 0), [0xff, 0, 0, 0xff]);
+    assert_eq!(canvas.pixel(1, 1), [0xff, 0, 0, 0xff]);
+    assert_eq!(canvas.pixel(2, 2), [0, 0, 0, 0]);
+    assert_eq!(canvas.pixel(3, 3), [0xff, 0, 0, 0xff]);
+  }
+
+  #[test]
+  fn strokes_outlines() {
+    let mut canvas = Canvas::new(4, 4, 1);
+    canvas.stroke_rect(RED, 0, 0, 4, 4);
+
+    assert_eq!(canvas.pixel(0, 0), [0, 0, 0xff, 0xff]);
+    assert_eq!(canvas.pixel(3, 0), [0, 0, 0xff, 0xff]);
+    assert_eq!(canvas.pixel(0, 3), [0, 0, 0xff, 0xff]);
+    assert_eq!(canvas.pixel(3, 3), [0, 0, 0xff, 0xff]);
+    assert_eq!(canvas.pixel(1, 1), [0, 0, 0, 0]);
+    assert_eq!(canvas.pixel(2, 2), [0, 0, 0, 0]);
+  }
+
+  #[test]
+  fn draws_glyphs_of_the_built_in_font() {
+    let mut canvas = Canvas::new(16, 8, 1);
+    canvas.draw_text(RED, 0, 0, "T|");
+
+    // The top bar of the T, and the gap in the middle of the bar.
+    assert_eq!(canvas.pixel(0, 0), [0, 0, 0xff, 0xff]);
+    assert_eq!(canvas.pixel(5, 0), [0, 0, 0xff, 0xff]);
+    assert_eq!(canvas.pixel(6, 0), [0, 0, 0, 0]);
+    assert_eq!(canvas.pixel(11, 2), [0, 0, 0xff, 0xff]);
+    assert_eq!(canvas.pixel(11, 3), [0, 0, 0, 0]);
+  }
+
+  #[test]
+  fn draws_unknown_characters_as_question_marks() {
+    assert_eq!(glyph('é'), glyph('?'));
+    assert_eq!(glyph('\u{7f}'), glyph('?'));
+    assert_eq!(glyph('A'), &FONT[33]);
+  }
+
+  #[test]
+  fn measures_text_in_characters() {
+    assert_eq!(text_width(""), 0);
+    assert_eq!(text_width("Cancel"), 6 * GLYPH_WIDTH);
+    assert_eq!(text_width("né"), 2 * GLYPH_WIDTH);
+  }
+
+  #[test]
+  fn converts_physical_locations() {
+    let canvas = Canvas::new(10, 10, 2);
+
+    assert_eq!(canvas.to_canvas(5.0, 19.5), (2, 9));
+    assert_eq!(canvas.to_canvas(-0.5, 0.0), (-1, 0));
+  }
+}
@@ -0,0 +1,530 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use deno_core::error::AnyError;
+use deno_core::ResourceId;
+
+use winit::dpi::PhysicalPosition;
+use winit::dpi::PhysicalSize;
+use winit::dpi::Position;
+use winit::event::ElementState;
+use winit::event::KeyboardInput;
+use winit::event::MouseButton;
+use winit::event::VirtualKeyCode;
+use winit::event::WindowEvent;
+use winit::event_loop::EventLoopWindowTarget;
+use winit::window::Window;
+use winit::window::WindowId;
+
+use crate::attributes::popup_builder;
+use crate::event::Event;
+use crate::helpers::hash;
+use crate::menu::MenuItem;
+use crate::menu::MenuResource;
+use crate::x11::XCanvas;
+use crate::x11::XWindow;
+
+/// The core font every X server provides.
+const FONT: &str = "fixed";
+
+const BACKGROUND: u32 = 0xf6f5f4;
+const BORDER: u32 = 0xc0bfbc;
+const TEXT: u32 = 0x241f31;
+const DISABLED_TEXT: u32 = 0x9a9996;
+const HIGHLIGHT: u32 = 0x3584e4;
+const HIGHLIGHT_TEXT: u32 = 0xffffff;
+
+/// Space left of labels, where check boxes are drawn.
+const INDENT: i32 = 24;
+const MARGIN: i32 = 4;
+const ROW_PADDING: i32 = 8;
+const SEPARATOR_HEIGHT: i32 = 7;
+const MIN_WIDTH: i32 = 120;
+
+struct Row {
+  y: i32,
+  height: i32,
+}
+
+/// A menu, or one of its submenus, popped up in an override-redirect window
+/// and drawn with a core X11 font.
+pub struct ContextMenu {
+  window: Window,
+  menu: Rc<MenuResource>,
+  menu_rid: ResourceId,
+  /// The window the menu was popped up for, activations are reported for it.
+  owner: WindowId,
+  /// The item indices leading to the shown submenu.
+  path: Vec<usize>,
+  rows: Vec<Row>,
+  hovered: Cell<Option<usize>>,
+}
+
+/// The open context menu of the event loop, followed by its open submenus.
+#[derive(Default)]
+pub struct ContextMenus(pub Vec<ContextMenu>);
+
+impl ContextMenu {
+  /// Opens the submenu at `path` at a position relative to the client area of
+  /// `parent`. Returns `None` if the parent is not an X11 window.
+  #[allow(clippy::too_many_arguments)]
+  pub fn open(
+    target: &EventLoopWindowTarget<()>,
+    parent: &Window,
+    position: Position,
+    owner: WindowId,
+    menu: Rc<MenuResource>,
+    menu_rid: ResourceId,
+    path: Vec<usize>,
+  ) -> Result<Option<Self>, AnyError> {
+    let parent_window = match XWindow::new(parent)? {
+      Some(parent_window) => parent_window,
+      None => return Ok(None),
+    };
+
+    let items = menu.items(&path);
+    let (rows, size) = layout(&parent_window.canvas(FONT)?, &items);
+
+    let window = popup_builder(parent, position)?
+      .with_inner_size(size)
+      .build(target)?;
+
+    Ok(Some(Self {
+      window,
+      menu,
+      menu_rid,
+      owner,
+      path,
+      rows,
+      hovered: Cell::new(None),
+    }))
+  }
+
+  fn row_at(&self, y: f64) -> Option<usize> {
+    let y = y as i32;
+
+    self
+      .rows
+      .iter()
+      .position(|row| y >= row.y && y < row.y + row.height)
+  }
+
+  fn draw(&self) -> Result<(), AnyError> {
+    let window = match XWindow::new(&self.window)? {
+      Some(window) => window,
+      None => return Ok(()),
+    };
+    let canvas = window.canvas(FONT)?;
+    let size = self.window.inner_size();
+    let width = size.width as i32;
+
+    canvas.fill_rect(BACKGROUND, 0, 0, size.width, size.height);
+    canvas.stroke_rect(BORDER, 0, 0, size.width - 1, size.height - 1);
+
+    let items = self.menu.items(&self.path);
+
+    for (index, (item, row)) in items.iter().zip(&self.rows).enumerate() {
+      let label = match item.label() {
+        Some(label) => label,
+        None => {
+          let y = row.y + row.height / 2;
+          canvas.fill_rect(BORDER, MARGIN, y, (width - 2 * MARGIN) as u32, 1);
+          continue;
+        }
+      };
+
+      let hovered = self.hovered.get() == Some(index) && item.is_enabled();
+      let color = if !item.is_enabled() {
+        DISABLED_TEXT
+      } else if hovered {
+        HIGHLIGHT_TEXT
+      } else {
+        TEXT
+      };
+
+      if hovered {
+        canvas.fill_rect(
+          HIGHLIGHT,
+          1,
+          row.y,
+          (width - 2) as u32,
+          row.height as u32,
+        );
+      }
+
+      let ascent = canvas.ascent();
+      let baseline = row.y + (row.height + ascent - canvas.descent()) / 2;
+      canvas.draw_text(color, INDENT, baseline, label);
+
+      if let MenuItem::Checkbox { checked, .. } = item {
+        let box_size = ascent - 2;
+        let box_x = (INDENT - box_size) / 2;
+        let box_y = baseline - box_size;
+
+        canvas.stroke_rect(
+          color,
+          box_x,
+          box_y,
+          box_size as u32,
+          box_size as u32,
+        );
+        if *checked {
+          canvas.fill_rect(
+            color,
+            box_x + 2,
+            box_y + 2,
+            (box_size - 3) as u32,
+            (box_size - 3) as u32,
+          );
+        }
+      }
+
+      let hint = match item {
+        MenuItem::Submenu { .. } => Some(">"),
+        _ => item.accelerator().map(|accelerator| accelerator.label()),
+      };
+      if let Some(hint) = hint {
+        let x = width - INDENT / 2 - canvas.text_width(hint);
+        canvas.draw_text(color, x, baseline, hint);
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Lays out the rows of a menu, returning them with the size of the window.
+fn layout(
+  canvas: &XCanvas,
+  items: &[MenuItem],
+) -> (Vec<Row>, PhysicalSize<u32>) {
+  let line_height = canvas.ascent() + canvas.descent() + ROW_PADDING;
+  let mut rows = Vec::with_capacity(items.len());
+  let mut y = MARGIN;
+  let mut width = MIN_WIDTH;
+
+  for item in items {
+    let height = match item.label() {
+      Some(label) => {
+        let hint_width = match item {
+          MenuItem::Submenu { .. } => canvas.text_width(">"),
+          _ => item
+            .accelerator()
+            .map_or(0, |accelerator| canvas.text_width(accelerator.label())),
+        };
+        let gap = if hint_width > 0 { INDENT * 2 } else { 0 };
+
+        width = width
+          .max(INDENT + canvas.text_width(label) + gap + hint_width + INDENT);
+        line_height
+      }
+      None => SEPARATOR_HEIGHT,
+    };
+
+    rows.push(Row { y, height });
+    y += height;
+  }
+
+  (rows, PhysicalSize::new(width as u32, (y + MARGIN) as u32))
+}
+
+impl ContextMenus {
+  pub fn close(&mut self) {
+    self.0.clear();
+  }
+
+  /// Handles the events of context menu windows and the keys that navigate
+  /// them, and closes the menus when their owner is clicked or loses focus.
+  /// Returns whether the event belonged to a context menu, in which case it
+  /// should not be passed on.
+  pub fn handle_event(
+    &mut self,
+    target: &EventLoopWindowTarget<()>,
+    event: &winit::event::Event<()>,
+    events: &mut Vec<Event>,
+  ) -> Result<bool, AnyError> {
+    let (window_id, window_event) = match event {
+      winit::event::Event::WindowEvent { window_id, event } => {
+        (*window_id, Some(event))
+      }
+      winit::event::Event::RedrawRequested(window_id) => (*window_id, None),
+      _ => return Ok(false),
+    };
+
+    let level =
+      match self.0.iter().position(|menu| menu.window.id() == window_id) {
+        Some(level) => level,
+        None => {
+          if self.0.first().map(|menu| menu.owner) != Some(window_id) {
+            return Ok(false);
+          }
+
+          // Menus do not take focus, so they are navigated with the keys
+          // pressed in their owner.
+          if let Some(key) = window_event.and_then(navigation_key) {
+            self.handle_key(target, key, events)?;
+            return Ok(true);
+          }
+          if matches!(window_event, Some(event) if closes_menus(event)) {
+            self.close();
+          }
+
+          return Ok(false);
+        }
+      };
+
+    let menu = &self.0[level];
+
+    match window_event {
+      None => menu.draw()?,
+      Some(WindowEvent::CursorMoved { position, .. }) => {
+        let row = menu.row_at(position.y);
+
+        if menu.hovered.replace(row) != row {
+          menu.window.request_redraw();
+          self.0.truncate(level + 1);
+          self.open_submenu(target, level, row)?;
+        }
+      }
+      Some(WindowEvent::CursorLeft { .. })
+        if menu.hovered.replace(None).is_some() =>
+      {
+        menu.window.request_redraw();
+      }
+      Some(WindowEvent::MouseInput {
+        state: ElementState::Released,
+        button: MouseButton::Left,
+        ..
+      }) => {
+        let activation = menu
+          .hovered
+          .get()
+          .and_then(|index| menu.menu.activate(&menu.path, index));
+
+        if let Some(activation) = activation {
+          events.push(activation.into_event(hash(menu.owner), menu.menu_rid));
+          self.close();
+        }
+      }
+      _ => {}
+    }
+
+    Ok(true)
+  }
+
+  /// Moves the highlight of the innermost menu with the arrow keys, opens and
+  /// closes submenus with the right and left keys, and activates the
+  /// highlighted item with Return.
+  fn handle_key(
+    &mut self,
+    target: &EventLoopWindowTarget<()>,
+    key: VirtualKeyCode,
+    events: &mut Vec<Event>,
+  ) -> Result<(), AnyError> {
+    let level = match self.0.len().checked_sub(1) {
+      Some(level) => level,
+      None => return Ok(()),
+    };
+    let menu = &self.0[level];
+    let items = menu.menu.items(&menu.path);
+    let hovered = menu.hovered.get();
+    let opens_submenu = matches!(
+      hovered.and_then(|index| items.get(index)),
+      Some(MenuItem::Submenu { enabled: true, .. })
+    );
+
+    match key {
+      VirtualKeyCode::Up | VirtualKeyCode::Down => {
+        let row = next_row(&items, hovered, key == VirtualKeyCode::Down);
+
+        if row.is_some() && menu.hovered.replace(row) != row {
+          menu.window.request_redraw();
+        }
+      }
+      VirtualKeyCode::Right
+      | VirtualKeyCode::Return
+      | VirtualKeyCode::NumpadEnter
+        if opens_submenu =>
+      {
+        self.open_submenu(target, level, hovered)?;
+
+        if let Some(submenu) = self.0.get(level + 1) {
+          let items = submenu.menu.items(&submenu.path);
+          submenu.hovered.set(next_row(&items, None, true));
+          submenu.window.request_redraw();
+        }
+      }
+      VirtualKeyCode::Left if level > 0 => self.0.truncate(level),
+      VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+        let activation =
+          hovered.and_then(|index| menu.menu.activate(&menu.path, index));
+
+        if let Some(activation) = activation {
+          events.push(activation.into_event(hash(menu.owner), menu.menu_rid));
+          self.close();
+        }
+      }
+      VirtualKeyCode::Escape => self.close(),
+      _ => {}
+    }
+
+    Ok(())
+  }
+
+  /// Opens the submenu at `row` of the menu at `level`, next to the row.
+  fn open_submenu(
+    &mut self,
+    target: &EventLoopWindowTarget<()>,
+    level: usize,
+    row: Option<usize>,
+  ) -> Result<(), AnyError> {
+    let menu = &self.0[level];
+    let index = match row {
+      Some(index) => index,
+      None => return Ok(()),
+    };
+
+    let items = menu.menu.items(&menu.path);
+    if !matches!(
+      items.get(index),
+      Some(MenuItem::Submenu { enabled: true, .. })
+    ) {
+      return Ok(());
+    }
+
+    let mut path = menu.path.clone();
+    path.push(index);
+
+    let position = PhysicalPosition::new(
+      menu.window.inner_size().width as i32,
+      menu.rows[index].y - MARGIN,
+    );
+    let submenu = ContextMenu::open(
+      target,
+      &menu.window,
+      position.into(),
+      menu.owner,
+      menu.menu.clone(),
+      menu.menu_rid,
+      path,
+    )?;
+
+    self.0.extend(submenu);
+
+    Ok(())
+  }
+}
+
+/// Returns the enabled row after `row`, or before it if not `forward`,
+/// wrapping around the ends of the menu.
+fn next_row(
+  items: &[MenuItem],
+  row: Option<usize>,
+  forward: bool,
+) -> Option<usize> {
+  let count = items.len();
+  let step = if forward { 1 } else { count.checked_sub(1)? };
+  let start = match (row, forward) {
+    (Some(row), _) => row,
+    (None, true) => count.checked_sub(1)?,
+    (None, false) => 0,
+  };
+
+  (1..=count)
+    .map(|offset| (start + offset * step) % count)
+    .find(|&index| items[index].is_enabled())
+}
+
+/// Returns the key of an event of the owner window if it navigates its
+/// context menus.
+fn navigation_key(event: &WindowEvent) -> Option<VirtualKeyCode> {
+  match event {
+    WindowEvent::KeyboardInput {
+      input:
+        KeyboardInput {
+          state: ElementState::Pressed,
+          virtual_keycode: Some(key),
+          ..
+        },
+      ..
+    } => match key {
+      VirtualKeyCode::Up
+      | VirtualKeyCode::Down
+      | VirtualKeyCode::Left
+      | VirtualKeyCode::Right
+      | VirtualKeyCode::Return
+      | VirtualKeyCode::NumpadEnter
+      | VirtualKeyCode::Escape => Some(*key),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+/// Whether an event of the owner window dismisses its context menus.
+fn closes_menus(event: &WindowEvent) -> bool {
+  matches!(
+    event,
+    WindowEvent::Focused(false)
+      | WindowEvent::Destroyed
+      | WindowEvent::MouseInput {
+        state: ElementState::Pressed,
+        ..
+      }
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn item(enabled: bool) -> MenuItem {
+    MenuItem::Item {
+      id: "item".into(),
+      label: "Item".into(),
+      accelerator: None,
+      enabled,
+    }
+  }
+
+  /// An enabled item, a separator, a disabled item and another enabled item.
+  fn items() -> Vec<MenuItem> {
+    vec![item(true), MenuItem::Separator, item(false), item(true)]
+  }
+
+  #[test]
+  fn starts_at_the_first_or_last_enabled_row() {
+    assert_eq!(next_row(&items(), None, true), Some(0));
+    assert_eq!(next_row(&items(), None, false), Some(3));
+  }
+
+  #[test]
+  fn skips_separators_and_disabled_items() {
+    assert_eq!(next_row(&items(), Some(0), true), Some(3));
+    assert_eq!(next_row(&items(), Some(3), false), Some(0));
+  }
+
+  #[test]
+  fn wraps_around() {
+    assert_eq!(next_row(&items(), Some(3), true), Some(0));
+    assert_eq!(next_row(&items(), Some(0), false), Some(3));
+  }
+
+  #[test]
+  fn stays_on_the_only_enabled_row() {
+    let items = vec![MenuItem::Separator, item(true), item(false)];
+
+    assert_eq!(next_row(&items, Some(1), true), Some(1));
+    assert_eq!(next_row(&items, Some(1), false), Some(1));
+  }
+
+  #[test]
+  fn finds_nothing_without_enabled_rows() {
+    let items = vec![MenuItem::Separator, item(false)];
+
+    assert_eq!(next_row(&items, None, true), None);
+    assert_eq!(next_row(&items, Some(0), false), None);
+    assert_eq!(next_row(&[], None, true), None);
+    assert_eq!(next_row(&[], None, false), None);
+  }
+}
@@ -0,0 +1,194 @@
+use std::io::Cursor;
+
+use deno_core::error::custom_error;
+use deno_core::error::AnyError;
+use deno_core::serde::Deserialize;
+
+use image::io::Reader;
+use image::ImageError;
+use image::RgbaImage;
+
+/// The most pixels an image may decode to, which keeps a small compressed
+/// image from allocating gigabytes.
+const MAX_PIXELS: u64 = 4096 * 4096;
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ImageFormat {
+  Png,
+  Ico,
+  Bmp,
+}
+
+impl From<ImageFormat> for image::ImageFormat {
+  fn from(format: ImageFormat) -> Self {
+    match format {
+      ImageFormat::Png => image::ImageFormat::Png,
+      ImageFormat::Ico => image::ImageFormat::Ico,
+      ImageFormat::Bmp => image::ImageFormat::Bmp,
+    }
+  }
+}
+
+/// Decodes an encoded image into RGBA pixels, guessing the format from the
+/// image's magic bytes if none is given.
+pub fn decode(
+  bytes: &[u8],
+  format: Option<ImageFormat>,
+) -> Result<RgbaImage, AnyError> {
+  let (width, height) = reader(bytes, format)?
+    .into_dimensions()
+    .map_err(bad_image)?;
+  if width as u64 * height as u64 > MAX_PIXELS {
+    return Err(custom_error(
+      "BadImage",
+      format!(
+        "A {}x{} image is larger than the {} pixels allowed",
+        width, height, MAX_PIXELS
+      ),
+    ));
+  }
+
+  let image = reader(bytes, format)?.decode().map_err(bad_image)?;
+
+  Ok(image.into_rgba8())
+}
+
+fn reader(
+  bytes: &[u8],
+  format: Option<ImageFormat>,
+) -> Result<Reader<Cursor<&[u8]>>, AnyError> {
+  let reader = Reader::new(Cursor::new(bytes));
+
+  match format {
+    Some(format) => Ok(Reader::with_format(reader.into_inner(), format.into())),
+    None => reader
+      .with_guessed_format()
+      .map_err(|error| custom_error("BadImage", error.to_string())),
+  }
+}
+
+fn bad_image(error: ImageError) -> AnyError {
+  custom_error("BadImage", error.to_string())
+}
+
+/// Splits a buffer of concatenated encoded images by their byte lengths and
+/// decodes each of them.
+pub fn decode_all(
+  mut bytes: &[u8],
+  lengths: &[usize],
+  format: Option<ImageFormat>,
+) -> Result<Vec<RgbaImage>, AnyError> {
+  let mut images = Vec::with_capacity(lengths.len());
+
+  for &length in lengths {
+    if length > bytes.len() {
+      return Err(custom_error(
+        "BadImage",
+        format!(
+          "An image of {} bytes was expected, but only {} remain",
+          length,
+          bytes.len()
+        ),
+      ));
+    }
+
+    let (image, rest) = bytes.split_at(length);
+    images.push(decode(image, format)?);
+    bytes = rest;
+  }
+
+  if !bytes.is_empty() {
+    return Err(custom_error(
+      "BadImage",
+      format!("{} bytes remain after the last image", bytes.len()),
+    ));
+  }
+
+  Ok(images)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use deno_core::error::get_custom_error_class;
+  use image::codecs::png::PngEncoder;
+  use image::ColorType;
+
+  fn png(width: u32, height: u32) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let rgba = vec![255; width as usize * height as usize * 4];
+    PngEncoder::new(&mut bytes)
+      .encode(&rgba, width, height, ColorType::Rgba8)
+      .unwrap();
+
+    bytes
+  }
+
+  #[test]
+  fn decodes_concatenated_images() {
+    let (first, second) = (png(1, 2), png(3, 4));
+    let bytes = [first.clone(), second.clone()].concat();
+
+    let images =
+      decode_all(&bytes, &[first.len(), second.len()], None).unwrap();
+    let sizes: Vec<_> = images.iter().map(|image| image.dimensions()).collect();
+    assert_eq!(sizes, [(1, 2), (3, 4)]);
+  }
+
+  #[test]
+  fn rejects_trailing_bytes() {
+    let mut bytes = png(1, 1);
+    let length = bytes.len();
+    bytes.push(0);
+
+    let error = decode_all(&bytes, &[length], Some(ImageFormat::Png));
+    assert_eq!(
+      get_custom_error_class(&error.unwrap_err()),
+      Some("BadImage")
+    );
+  }
+
+  #[test]
+  fn rejects_missing_bytes() {
+    let bytes = png(1, 1);
+
+    let error = decode_all(&bytes, &[bytes.len() + 1], None);
+    assert_eq!(
+      get_custom_error_class(&error.unwrap_err()),
+      Some("BadImage")
+    );
+  }
+
+  #[test]
+  fn rejects_images_over_the_pixel_limit() {
+    // Only the header is read before the limit is checked, so the size in the
+    // header of a small image is patched.
+    let mut bytes = png(1, 1);
+    bytes[16..20].copy_from_slice(&4097u32.to_be_bytes());
+    bytes[20..24].copy_from_slice(&4096u32.to_be_bytes());
+    let crc = crc32(&bytes[12..29]);
+    bytes[29..33].copy_from_slice(&crc.to_be_bytes());
+
+    let error = decode(&bytes, None).unwrap_err();
+    assert_eq!(get_custom_error_class(&error), Some("BadImage"));
+    assert!(error.to_string().contains("4097x4096"));
+  }
+
+  fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+      crc ^= *byte as u32;
+      for _ in 0..8 {
+        crc = if crc & 1 != 0 {
+          (crc >> 1) ^ 0xedb8_8320
+        } else {
+          crc >> 1
+        };
+      }
+    }
+
+    !crc
+  }
+}
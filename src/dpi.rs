@@ -0,0 +1,72 @@
+use deno_core::serde::Deserialize;
+use deno_core::serde::Serialize;
+
+use winit::dpi::LogicalPosition;
+use winit::dpi::LogicalSize;
+use winit::dpi::PhysicalPosition;
+use winit::dpi::PhysicalSize;
+use winit::dpi::Pixel;
+
+/// The unit positions and sizes are reported in.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Unit {
+  Physical,
+  Logical,
+}
+
+/// A position in either unit, serialized as a plain position.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(untagged)]
+pub enum UnitPosition<P> {
+  Physical(PhysicalPosition<P>),
+  Logical(LogicalPosition<f64>),
+}
+
+impl<P: Pixel> UnitPosition<P> {
+  pub fn new(
+    position: PhysicalPosition<P>,
+    unit: Unit,
+    scale_factor: f64,
+  ) -> Self {
+    UnitPosition::Physical(position).to_unit(unit, scale_factor)
+  }
+
+  pub fn to_unit(self, unit: Unit, scale_factor: f64) -> Self {
+    match (self, unit) {
+      (UnitPosition::Physical(position), Unit::Logical) => {
+        UnitPosition::Logical(position.to_logical(scale_factor))
+      }
+      (UnitPosition::Logical(position), Unit::Physical) => {
+        UnitPosition::Physical(position.to_physical(scale_factor))
+      }
+      (position, _) => position,
+    }
+  }
+}
+
+/// A size in either unit, serialized as a plain size.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(untagged)]
+pub enum UnitSize<P> {
+  Physical(PhysicalSize<P>),
+  Logical(LogicalSize<f64>),
+}
+
+impl<P: Pixel> UnitSize<P> {
+  pub fn new(size: PhysicalSize<P>, unit: Unit, scale_factor: f64) -> Self {
+    UnitSize::Physical(size).to_unit(unit, scale_factor)
+  }
+
+  pub fn to_unit(self, unit: Unit, scale_factor: f64) -> Self {
+    match (self, unit) {
+      (UnitSize::Physical(size), Unit::Logical) => {
+        UnitSize::Logical(size.to_logical(scale_factor))
+      }
+      (UnitSize::Logical(size), Unit::Physical) => {
+        UnitSize::Physical(size.to_physical(scale_factor))
+      }
+      (size, _) => size,
+    }
+  }
+}
@@ -4,7 +4,6 @@ use std::time::Instant;
 use deno_core::serde::Serialize;
 
 use winit::dpi::PhysicalPosition;
-use winit::dpi::PhysicalSize;
 use winit::event::AxisId;
 use winit::event::ButtonId;
 use winit::event::ElementState;
@@ -16,6 +15,10 @@ use winit::event::ScanCode;
 use winit::event::TouchPhase;
 use winit::event::VirtualKeyCode;
 
+use crate::dpi::Unit;
+use crate::dpi::UnitPosition;
+use crate::dpi::UnitSize;
+use crate::gesture::Gesture;
 use crate::helpers::hash;
 
 #[derive(Serialize)]
@@ -78,6 +81,47 @@ pub enum Event {
     device_id: u32,
     event: DeviceEvent,
   },
+  /// A menu item was activated, by a click or its accelerator.
+  #[serde(rename = "menuEvent", rename_all = "camelCase")]
+  Menu {
+    window_id: u32,
+    menu_id: u32,
+    item_id: String,
+    checked: Option<bool>,
+  },
+  /// An event of a system tray icon created with `pane_tray_new`.
+  #[cfg(all(
+    feature = "freedesktop",
+    any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    )
+  ))]
+  #[serde(rename_all = "camelCase")]
+  TrayEvent {
+    tray_id: u32,
+    event: crate::tray::TrayEvent,
+  },
+  /// An event of a desktop notification shown with
+  /// `pane_notification_show`.
+  #[cfg(all(
+    feature = "freedesktop",
+    any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    )
+  ))]
+  #[serde(rename_all = "camelCase")]
+  NotificationEvent {
+    notification_id: u32,
+    event: crate::notification::NotificationEvent,
+  },
   UserEvent,
   Suspended,
   Resumed,
@@ -163,8 +207,8 @@ impl From<winit::event::StartCause> for StartCause {
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase", tag = "type", content = "value")]
 pub enum WindowEvent {
-  Resized(PhysicalSize<u32>),
-  Moved(PhysicalPosition<i32>),
+  Resized(UnitSize<u32>),
+  Moved(UnitPosition<i32>),
   CloseRequested,
   Destroyed,
   DroppedFile(PathBuf),
@@ -183,7 +227,7 @@ pub enum WindowEvent {
   #[serde(rename_all = "camelCase")]
   CursorMoved {
     device_id: u32,
-    position: PhysicalPosition<f64>,
+    position: UnitPosition<f64>,
   },
   #[serde(rename_all = "camelCase")]
   CursorEntered {
@@ -225,9 +269,72 @@ pub enum WindowEvent {
   #[serde(rename_all = "camelCase")]
   ScaleFactorChanged {
     scale_factor: f64,
-    new_inner_size: PhysicalSize<u32>,
+    new_inner_size: UnitSize<u32>,
   },
   ThemeChanged(Theme),
+  /// A composition of the input method, only emitted for windows with IME
+  /// enabled.
+  Ime(Ime),
+  /// A gesture recognized from the touches of the window, only emitted for
+  /// windows with gestures enabled.
+  Gesture(Gesture),
+  /// The state of a pen on a tablet, emitted whenever it moves over the
+  /// window.
+  ///
+  /// At the moment this is only supported on X11.
+  Pen(PenEvent),
+  /// The relative motion of the mouse while the pointer is locked to the
+  /// window, which replaces `CursorMoved` events.
+  PointerMotion {
+    delta: (f64, f64),
+  },
+  /// The popup was closed as focus moved away from it and its parent, its
+  /// resource is closed along with it.
+  PopupClosed,
+}
+
+/// An input method composition, offsets are in UTF-16 code units.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type", content = "value")]
+pub enum Ime {
+  Start,
+  /// The composition text changed, `cursor` is the range of the converted
+  /// clause, or an empty range at the caret.
+  Update {
+    text: String,
+    cursor: Option<(usize, usize)>,
+  },
+  /// Text was committed, it is not also sent as `receivedCharacter` events.
+  Commit(String),
+  End,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PenEvent {
+  pub device_id: u32,
+  pub tool: PenTool,
+  pub location: PhysicalPosition<f64>,
+  /// Whether the tip, or the eraser, touches the tablet.
+  pub contact: bool,
+  /// How hard the tablet is pressed, from 0 to 1.
+  pub pressure: f64,
+  /// How far the pen leans to the right and towards the bottom, in degrees
+  /// from -90 to 90, if the tablet reports it.
+  pub tilt: Option<(f64, f64)>,
+  /// The clockwise rotation of the pen around its own axis, in degrees, if
+  /// the pen reports it.
+  pub twist: Option<f64>,
+  pub barrel_button: bool,
+  pub secondary_barrel_button: bool,
+}
+
+/// The end of the pen that is used.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PenTool {
+  Pen,
+  Eraser,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -301,8 +408,12 @@ impl From<winit::window::Theme> for Theme {
 impl From<winit::event::WindowEvent<'_>> for WindowEvent {
   fn from(window_event: winit::event::WindowEvent) -> Self {
     match window_event {
-      winit::event::WindowEvent::Resized(size) => WindowEvent::Resized(size),
-      winit::event::WindowEvent::Moved(pos) => WindowEvent::Moved(pos),
+      winit::event::WindowEvent::Resized(size) => {
+        WindowEvent::Resized(UnitSize::Physical(size))
+      }
+      winit::event::WindowEvent::Moved(pos) => {
+        WindowEvent::Moved(UnitPosition::Physical(pos))
+      }
       winit::event::WindowEvent::CloseRequested => WindowEvent::CloseRequested,
       winit::event::WindowEvent::Destroyed => WindowEvent::Destroyed,
       winit::event::WindowEvent::DroppedFile(file) => {
@@ -338,7 +449,7 @@ impl From<winit::event::WindowEvent<'_>> for WindowEvent {
         modifiers: _,
       } => WindowEvent::CursorMoved {
         device_id: hash(device_id),
-        position,
+        position: UnitPosition::Physical(position),
       },
       winit::event::WindowEvent::CursorEntered { device_id } => {
         WindowEvent::CursorEntered {
@@ -398,7 +509,7 @@ impl From<winit::event::WindowEvent<'_>> for WindowEvent {
         new_inner_size,
       } => WindowEvent::ScaleFactorChanged {
         scale_factor,
-        new_inner_size: *new_inner_size,
+        new_inner_size: UnitSize::Physical(*new_inner_size),
       },
       winit::event::WindowEvent::ThemeChanged(theme) => {
         WindowEvent::ThemeChanged(Theme::from(theme))
@@ -407,6 +518,37 @@ impl From<winit::event::WindowEvent<'_>> for WindowEvent {
   }
 }
 
+impl WindowEvent {
+  /// Converts the positions and sizes of the event to the given unit.
+  pub fn into_unit(self, unit: Unit, scale_factor: f64) -> Self {
+    match self {
+      WindowEvent::Resized(size) => {
+        WindowEvent::Resized(size.to_unit(unit, scale_factor))
+      }
+      WindowEvent::Moved(position) => {
+        WindowEvent::Moved(position.to_unit(unit, scale_factor))
+      }
+      WindowEvent::CursorMoved {
+        device_id,
+        position,
+      } => WindowEvent::CursorMoved {
+        device_id,
+        position: position.to_unit(unit, scale_factor),
+      },
+      // The size is converted with the new scale factor, which the window
+      // may not report yet.
+      WindowEvent::ScaleFactorChanged {
+        scale_factor,
+        new_inner_size,
+      } => WindowEvent::ScaleFactorChanged {
+        scale_factor,
+        new_inner_size: new_inner_size.to_unit(unit, scale_factor),
+      },
+      event => event,
+    }
+  }
+}
+
 #[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase", tag = "type", content = "value")]
 pub enum DeviceEvent {
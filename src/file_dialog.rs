@@ -0,0 +1,492 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::rc::Rc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+
+use deno_core::error::generic_error;
+use deno_core::error::AnyError;
+use deno_core::futures::channel::oneshot;
+use deno_core::serde::Deserialize;
+
+use dbus::arg::prop_cast;
+use dbus::arg::PropMap;
+use dbus::arg::RefArg;
+use dbus::arg::Variant;
+use dbus::blocking::LocalConnection;
+use dbus::channel::Sender;
+use dbus::message::MatchRule;
+use dbus::Message;
+
+use raw_window_handle::HasRawWindowHandle;
+use raw_window_handle::RawWindowHandle;
+
+const PORTAL_NAME: &str = "org.freedesktop.portal.Desktop";
+const PORTAL_PATH: &str = "/org/freedesktop/portal/desktop";
+const FILE_CHOOSER_INTERFACE: &str = "org.freedesktop.portal.FileChooser";
+const REQUEST_INTERFACE: &str = "org.freedesktop.portal.Request";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The number of dialogs shown by this process, to give each request a
+/// unique handle.
+static DIALOG_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Clone, Copy)]
+pub enum FileDialogKind {
+  OpenFile,
+  OpenFiles,
+  OpenDirectory,
+  SaveFile,
+}
+
+/// Files with one of the extensions are shown when the filter is selected.
+#[derive(Deserialize)]
+pub struct FileFilter {
+  name: String,
+  extensions: Vec<String>,
+}
+
+/// The options of a file dialog, as described in JSON.
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct FileDialogOptions {
+  title: String,
+  /// The label of the button accepting the selection.
+  accept_label: Option<String>,
+  filters: Vec<FileFilter>,
+  /// The directory the dialog starts in.
+  directory: Option<String>,
+  /// The file name suggested by save dialogs.
+  file_name: Option<String>,
+}
+
+/// Returns the identifier the portal uses to place a dialog on top of a
+/// window, or an empty string if it is not an X11 window.
+pub fn parent_window(window: &impl HasRawWindowHandle) -> String {
+  match window.raw_window_handle() {
+    #[cfg(any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    ))]
+    RawWindowHandle::Xlib(handle) => format!("x11:{:x}", handle.window),
+    #[cfg(any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    ))]
+    RawWindowHandle::Xcb(handle) => format!("x11:{:x}", handle.window),
+    _ => String::new(),
+  }
+}
+
+/// Shows a file dialog through the XDG desktop portal, resolving to the
+/// selected paths, which are empty if the dialog was cancelled.
+///
+/// The portal is waited on by a thread with its own session bus connection,
+/// so the dialog does not block the event loop. The dialog is closed if the
+/// future is dropped before it resolves.
+pub fn show(
+  kind: FileDialogKind,
+  parent_window: String,
+  options: FileDialogOptions,
+) -> impl Future<Output = Result<Vec<String>, AnyError>> {
+  let (sender, receiver) = oneshot::channel();
+
+  thread::spawn(move || {
+    let result = LocalConnection::new_session()
+      .map_err(AnyError::from)
+      .and_then(|connection| {
+        request(&connection, kind, &parent_window, options, || {
+          sender.is_canceled()
+        })
+      });
+    let _ = sender.send(result);
+  });
+
+  async move {
+    receiver
+      .await
+      .map_err(|_| generic_error("The file dialog was interrupted"))?
+  }
+}
+
+/// Makes a portal request and waits for its response, or until `cancelled`
+/// returns true.
+fn request(
+  connection: &LocalConnection,
+  kind: FileDialogKind,
+  parent_window: &str,
+  options: FileDialogOptions,
+  cancelled: impl Fn() -> bool,
+) -> Result<Vec<String>, AnyError> {
+  let token = format!(
+    "pane{}_{}",
+    std::process::id(),
+    DIALOG_COUNT.fetch_add(1, Ordering::Relaxed) + 1
+  );
+  let sender = connection
+    .unique_name()
+    .trim_start_matches(':')
+    .replace('.', "_");
+  let handle = format!("{}/request/{}/{}", PORTAL_PATH, sender, token);
+
+  // The response is matched before the request is made, as the portal may
+  // respond before the request returns.
+  let response = Rc::new(RefCell::new(None));
+  let response_sender = response.clone();
+  connection.add_match(
+    MatchRule::new_signal(REQUEST_INTERFACE, "Response")
+      .with_path(handle.clone()),
+    move |(code, results): (u32, PropMap), _, _| {
+      response_sender.replace(Some((code, results)));
+      false
+    },
+  )?;
+
+  let title = options.title.clone();
+  let method = match kind {
+    FileDialogKind::SaveFile => "SaveFile",
+    _ => "OpenFile",
+  };
+  let (_,): (dbus::Path,) = connection
+    .with_proxy(PORTAL_NAME, PORTAL_PATH, TIMEOUT)
+    .method_call(
+      FILE_CHOOSER_INTERFACE,
+      method,
+      (parent_window, title, portal_options(kind, options, token)),
+    )?;
+
+  let (code, results) = loop {
+    connection.process(Duration::from_millis(100))?;
+
+    if let Some(response) = response.take() {
+      break response;
+    }
+    if cancelled() {
+      let close = Message::new_method_call(
+        PORTAL_NAME,
+        handle.as_str(),
+        REQUEST_INTERFACE,
+        "Close",
+      )
+      .expect("method names are valid");
+      let _ = connection.send(close);
+      connection.channel().flush();
+
+      return Err(generic_error("The file dialog was interrupted"));
+    }
+  };
+
+  match code {
+    0 => Ok(
+      prop_cast::<Vec<String>>(&results, "uris")
+        .map(|uris| uris.iter().map(|uri| uri_to_path(uri)).collect())
+        .unwrap_or_default(),
+    ),
+    1 => Ok(Vec::new()),
+    _ => Err(generic_error("The file dialog failed")),
+  }
+}
+
+fn variant(value: impl RefArg + 'static) -> Variant<Box<dyn RefArg>> {
+  Variant(Box::new(value))
+}
+
+/// Returns the options of a portal request, as a dictionary of variants.
+fn portal_options(
+  kind: FileDialogKind,
+  options: FileDialogOptions,
+  token: String,
+) -> PropMap {
+  let mut portal_options = PropMap::new();
+
+  portal_options.insert("handle_token".to_string(), variant(token));
+  portal_options.insert("modal".to_string(), variant(true));
+  if let Some(accept_label) = options.accept_label {
+    portal_options.insert("accept_label".to_string(), variant(accept_label));
+  }
+
+  match kind {
+    FileDialogKind::OpenFiles => {
+      portal_options.insert("multiple".to_string(), variant(true));
+    }
+    FileDialogKind::OpenDirectory => {
+      portal_options.insert("directory".to_string(), variant(true));
+    }
+    FileDialogKind::OpenFile | FileDialogKind::SaveFile => {}
+  }
+
+  if !options.filters.is_empty() {
+    // Filters are a name with glob (0) or MIME type (1) patterns.
+    let filters: Vec<(String, Vec<(u32, String)>)> = options
+      .filters
+      .into_iter()
+      .map(|filter| {
+        let patterns = filter
+          .extensions
+          .iter()
+          .map(|extension| (0, extension_glob(extension)))
+          .collect();
+
+        (filter.name, patterns)
+      })
+      .collect();
+    portal_options.insert("filters".to_string(), variant(filters));
+  }
+
+  if let Some(directory) = options.directory {
+    // Paths are passed as null terminated byte strings.
+    let mut directory = directory.into_bytes();
+    directory.push(0);
+    portal_options.insert("current_folder".to_string(), variant(directory));
+  }
+  if let Some(file_name) = options.file_name {
+    portal_options.insert("current_name".to_string(), variant(file_name));
+  }
+
+  portal_options
+}
+
+/// Returns a glob matching file names with an extension in any case, as
+/// portal globs are case sensitive.
+fn extension_glob(extension: &str) -> String {
+  let mut glob = String::from("*.");
+
+  for character in extension.chars() {
+    if character.is_ascii_alphabetic() {
+      glob.push('[');
+      glob.push(character.to_ascii_lowercase());
+      glob.push(character.to_ascii_uppercase());
+      glob.push(']');
+    } else {
+      glob.push(character);
+    }
+  }
+
+  glob
+}
+
+/// Converts a `file://` URI to a path, other URIs are returned as is.
+fn uri_to_path(uri: &str) -> String {
+  let path = match uri.strip_prefix("file://") {
+    Some(path) => path.as_bytes(),
+    None => return uri.to_string(),
+  };
+  let mut bytes = Vec::with_capacity(path.len());
+  let mut index = 0;
+
+  while index < path.len() {
+    let escaped = path
+      .get(index + 1..index + 3)
+      .filter(|_| path[index] == b'%')
+      .and_then(|hex| std::str::from_utf8(hex).ok())
+      .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+    match escaped {
+      Some(byte) => {
+        bytes.push(byte);
+        index += 3;
+      }
+      None => {
+        bytes.push(path[index]);
+        index += 1;
+      }
+    }
+  }
+
+  String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::mpsc;
+
+  use dbus::blocking::Connection;
+  use dbus::channel::MatchingReceiver;
+
+  use crate::helpers::test_bus::TestBus;
+
+  use super::*;
+
+  /// A request received by the stand-in portal.
+  #[derive(Debug, PartialEq)]
+  enum PortalCall {
+    Open {
+      method: String,
+      parent_window: String,
+      title: String,
+    },
+    Close {
+      handle: String,
+    },
+  }
+
+  /// Stands in for the desktop portal. Requests are answered with `uris`,
+  /// or left open for the client to close if there are none.
+  fn serve(
+    connection: Connection,
+    uris: Vec<String>,
+    calls: mpsc::Sender<PortalCall>,
+  ) {
+    connection.start_receive(
+      MatchRule::new_method_call(),
+      Box::new(move |message, connection| {
+        let member = message.member().unwrap().to_string();
+        if member == "Close" {
+          let handle = message.path().unwrap().to_string();
+          let _ = calls.send(PortalCall::Close { handle });
+          return true;
+        }
+
+        let (parent_window, title, options): (String, String, PropMap) =
+          message.read3().unwrap();
+        let sender = message.sender().unwrap();
+        let token: String = prop_cast::<String>(&options, "handle_token")
+          .unwrap()
+          .clone();
+        let handle = dbus::Path::from(format!(
+          "{}/request/{}/{}",
+          PORTAL_PATH,
+          sender.trim_start_matches(':').replace('.', "_"),
+          token
+        ));
+
+        let _ = calls.send(PortalCall::Open {
+          method: member,
+          parent_window,
+          title,
+        });
+        let _ = connection.send(message.method_return().append1(&handle));
+
+        if !uris.is_empty() {
+          let mut results = PropMap::new();
+          results.insert("uris".to_string(), variant(uris.clone()));
+          let response =
+            Message::new_signal(&*handle, REQUEST_INTERFACE, "Response")
+              .unwrap()
+              .append2(0u32, results);
+          let _ = connection.send(response);
+        }
+        true
+      }),
+    );
+
+    while connection.process(Duration::from_millis(100)).is_ok() {}
+  }
+
+  fn portal(bus: &TestBus, uris: &[&str]) -> mpsc::Receiver<PortalCall> {
+    let connection = bus.connect_sync();
+    connection
+      .request_name(PORTAL_NAME, false, true, true)
+      .unwrap();
+    let uris = uris.iter().map(|uri| uri.to_string()).collect();
+    let (sender, calls) = mpsc::channel();
+    thread::spawn(move || serve(connection, uris, sender));
+
+    calls
+  }
+
+  fn options(title: &str) -> FileDialogOptions {
+    FileDialogOptions {
+      title: title.to_string(),
+      ..FileDialogOptions::default()
+    }
+  }
+
+  #[test]
+  fn returns_the_selected_paths() {
+    let bus = match TestBus::start() {
+      Some(bus) => bus,
+      None => return,
+    };
+    let calls = portal(&bus, &["file:///tmp/a%20b.txt", "file:///tmp/c"]);
+
+    let paths = request(
+      &bus.connect(),
+      FileDialogKind::OpenFiles,
+      "x11:2a",
+      options("Open"),
+      || false,
+    )
+    .unwrap();
+
+    assert_eq!(paths, ["/tmp/a b.txt", "/tmp/c"]);
+    assert_eq!(
+      calls.try_recv().unwrap(),
+      PortalCall::Open {
+        method: "OpenFile".into(),
+        parent_window: "x11:2a".into(),
+        title: "Open".into(),
+      }
+    );
+  }
+
+  #[test]
+  fn closes_cancelled_requests() {
+    let bus = match TestBus::start() {
+      Some(bus) => bus,
+      None => return,
+    };
+    let calls = portal(&bus, &[]);
+    let connection = bus.connect();
+
+    let result = request(
+      &connection,
+      FileDialogKind::SaveFile,
+      "",
+      options("Save"),
+      || true,
+    );
+
+    assert!(result.is_err());
+    assert!(matches!(
+      calls.recv_timeout(TIMEOUT).unwrap(),
+      PortalCall::Open { method, .. } if method == "SaveFile"
+    ));
+    match calls.recv_timeout(TIMEOUT).unwrap() {
+      PortalCall::Close { handle } => assert!(handle.starts_with(&format!(
+        "{}/request/{}/",
+        PORTAL_PATH,
+        connection
+          .unique_name()
+          .trim_start_matches(':')
+          .replace('.', "_")
+      ))),
+      call => panic!("expected the request to be closed, got {:?}", call),
+    }
+  }
+
+  #[test]
+  fn matches_extensions_in_any_case() {
+    let options = FileDialogOptions {
+      filters: vec![FileFilter {
+        name: "Images".into(),
+        extensions: vec!["png".into(), "JPG".into(), "mp4".into()],
+      }],
+      ..FileDialogOptions::default()
+    };
+    let options =
+      portal_options(FileDialogKind::OpenFile, options, "token".into());
+
+    let filters =
+      prop_cast::<Vec<(String, Vec<(u32, String)>)>>(&options, "filters");
+    assert_eq!(
+      filters.unwrap(),
+      &[(
+        "Images".to_string(),
+        vec![
+          (0, "*.[pP][nN][gG]".to_string()),
+          (0, "*.[jJ][pP][gG]".to_string()),
+          (0, "*.[mM][pP]4".to_string()),
+        ]
+      )]
+    );
+  }
+}
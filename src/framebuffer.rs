@@ -0,0 +1,181 @@
+use std::borrow::Cow;
+
+use deno_core::error::custom_error;
+use deno_core::error::not_supported;
+use deno_core::error::AnyError;
+use deno_core::serde::Deserialize;
+
+use winit::dpi::PhysicalSize;
+use winit::window::Window;
+
+/// The channel order of 32bpp pixel data passed to and from windows.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PixelFormat {
+  Rgba,
+  Bgra,
+}
+
+/// Errors if `data` is not exactly `width` by `height` 32bpp pixels.
+pub fn check_len(data: &[u8], width: u32, height: u32) -> Result<(), AnyError> {
+  let byte_count = width as usize * height as usize * 4;
+
+  if data.len() != byte_count {
+    return Err(custom_error(
+      "RangeError",
+      format!(
+        "A {}x{} framebuffer needs {} bytes, but got {}",
+        width,
+        height,
+        byte_count,
+        data.len()
+      ),
+    ));
+  }
+
+  Ok(())
+}
+
+/// Swaps the red and blue channels of every pixel in place, converting
+/// between RGBA and BGRA.
+pub fn swap_red_blue(data: &mut [u8]) {
+  for pixel in data.chunks_exact_mut(4) {
+    pixel.swap(0, 2);
+  }
+}
+
+/// Returns the pixels in BGRA order, only copying them if they need to be
+/// converted.
+pub fn to_bgra(data: &[u8], format: PixelFormat) -> Cow<[u8]> {
+  match format {
+    PixelFormat::Bgra => Cow::Borrowed(data),
+    PixelFormat::Rgba => {
+      let mut data = data.to_vec();
+      swap_red_blue(&mut data);
+      Cow::Owned(data)
+    }
+  }
+}
+
+/// Whether pixels can be presented to the window, which currently is only
+/// the case for X11 windows.
+#[allow(unused_variables)]
+pub fn is_supported(window: &Window) -> bool {
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  if let raw_window_handle::RawWindowHandle::Xlib(_) =
+    raw_window_handle::HasRawWindowHandle::raw_window_handle(window)
+  {
+    return true;
+  }
+
+  false
+}
+
+/// Blits BGRA pixels in physical units to the top left of the window's client
+/// area. Currently only X11 is supported.
+#[allow(unused_variables)]
+pub fn present(
+  window: &Window,
+  bgra: &[u8],
+  width: u32,
+  height: u32,
+) -> Result<(), AnyError> {
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  if let Some(window) = crate::x11::XWindow::new(window)? {
+    return window.put_image(bgra, width, height);
+  }
+
+  Err(not_supported())
+}
+
+/// Reads the window's client area into `rgba` as RGBA pixels in physical
+/// units, returning its size. Nothing is read if `rgba` is too small, so the
+/// capture can be retried with the returned size. Currently only X11 is
+/// supported.
+#[allow(unused_variables)]
+pub fn capture(
+  window: &Window,
+  rgba: &mut [u8],
+) -> Result<PhysicalSize<u32>, AnyError> {
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  if let Some(window) = crate::x11::XWindow::new(window)? {
+    let (width, height) = window.get_image(rgba)?;
+    return Ok(PhysicalSize::new(width, height));
+  }
+
+  Err(not_supported())
+}
+
+#[cfg(test)]
+mod tests {
+  use deno_core::error::get_custom_error_class;
+
+  use super::*;
+
+  #[test]
+  fn accepts_exactly_sized_data() {
+    assert!(check_len(&[0; 2 * 3 * 4], 2, 3).is_ok());
+    assert!(check_len(&[], 0, 0).is_ok());
+  }
+
+  #[test]
+  fn rejects_short_and_long_data() {
+    let error = check_len(&[0; 2 * 3 * 4 - 1], 2, 3).unwrap_err();
+    assert_eq!(get_custom_error_class(&error), Some("RangeError"));
+    assert!(error.to_string().contains("needs 24 bytes, but got 23"));
+
+    let error = check_len(&[0; 2 * 3 * 4 + 4], 2, 3).unwrap_err();
+    assert_eq!(get_custom_error_class(&error), Some("RangeError"));
+  }
+
+  #[test]
+  fn swaps_red_and_blue() {
+    let mut data = [1, 2, 3, 4, 5, 6, 7, 8];
+    swap_red_blue(&mut data);
+    assert_eq!(data, [3, 2, 1, 4, 7, 6, 5, 8]);
+  }
+
+  #[test]
+  fn leaves_a_trailing_partial_pixel_alone() {
+    let mut data = [1, 2, 3, 4, 5, 6];
+    swap_red_blue(&mut data);
+    assert_eq!(data, [3, 2, 1, 4, 5, 6]);
+  }
+
+  #[test]
+  fn borrows_bgra_data() {
+    let data = [1, 2, 3, 4];
+    let bgra = to_bgra(&data, PixelFormat::Bgra);
+
+    assert!(matches!(bgra, Cow::Borrowed(_)));
+    assert_eq!(&*bgra, &data);
+  }
+
+  #[test]
+  fn converts_rgba_data() {
+    let data = [1, 2, 3, 4, 5, 6, 7, 8];
+    let bgra = to_bgra(&data, PixelFormat::Rgba);
+
+    assert!(matches!(bgra, Cow::Owned(_)));
+    assert_eq!(&*bgra, &[3, 2, 1, 4, 7, 6, 5, 8]);
+    assert_eq!(data, [1, 2, 3, 4, 5, 6, 7, 8]);
+  }
+}
@@ -0,0 +1,269 @@
+use std::cell::Cell;
+
+use deno_core::serde::Deserialize;
+use deno_core::serde::Serialize;
+
+use winit::dpi::PhysicalPosition;
+use winit::dpi::PhysicalSize;
+use winit::event::WindowEvent;
+use winit::monitor::MonitorHandle;
+use winit::window::Fullscreen;
+use winit::window::Window;
+
+/// The layout of a window, in a form that can be persisted and restored in a
+/// later session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowGeometry {
+  /// The position of the window, unless the platform does not expose it, as
+  /// on Wayland.
+  pub outer_position: Option<PhysicalPosition<i32>>,
+  pub inner_size: PhysicalSize<u32>,
+  pub maximized: bool,
+  pub fullscreen: bool,
+  /// The name of the monitor the window was on, or fullscreen on.
+  pub monitor: Option<String>,
+}
+
+impl WindowGeometry {
+  /// Returns the geometry of the window. The position and size of a maximized
+  /// or fullscreen window are the ones it had before, so that it can be
+  /// unmaximized to them once it is restored.
+  pub fn new(window: &Window, normal_bounds: &NormalBounds) -> Self {
+    let maximized = window.is_maximized();
+    let fullscreen = window.fullscreen().is_some();
+    let outer_position = window.outer_position().ok();
+    let inner_size = window.inner_size();
+
+    let (outer_position, inner_size) = if maximized || fullscreen {
+      (
+        normal_bounds.outer_position.get().or(outer_position),
+        normal_bounds.inner_size.get().unwrap_or(inner_size),
+      )
+    } else {
+      (outer_position, inner_size)
+    };
+
+    Self {
+      outer_position,
+      inner_size,
+      maximized,
+      fullscreen,
+      monitor: window.current_monitor().and_then(|monitor| monitor.name()),
+    }
+  }
+
+  /// Puts the window back where the geometry was saved. If the monitor it was
+  /// on is no longer connected the window is moved to the monitor containing
+  /// its old position, or else the primary monitor, and it is always clamped
+  /// to fit on the monitor it ends up on.
+  pub fn restore(&self, window: &Window) {
+    let monitor = self.find_monitor(window);
+
+    let decorations = PhysicalSize::new(
+      window
+        .outer_size()
+        .width
+        .saturating_sub(window.inner_size().width),
+      window
+        .outer_size()
+        .height
+        .saturating_sub(window.inner_size().height),
+    );
+    let (position, inner_size) = match &monitor {
+      Some(monitor) => {
+        let (position, inner_size) = clamp(
+          self.outer_position.unwrap_or_else(|| monitor.position()),
+          self.inner_size,
+          decorations,
+          monitor.position(),
+          monitor.size(),
+        );
+        (self.outer_position.map(|_| position), inner_size)
+      }
+      None => (self.outer_position, self.inner_size),
+    };
+
+    window.set_fullscreen(None);
+    window.set_maximized(false);
+    window.set_inner_size(inner_size);
+    if let Some(position) = position {
+      window.set_outer_position(position);
+    }
+
+    if self.maximized {
+      window.set_maximized(true);
+    }
+
+    if self.fullscreen {
+      window.set_fullscreen(Some(Fullscreen::Borderless(monitor)));
+    }
+  }
+
+  fn find_monitor(&self, window: &Window) -> Option<MonitorHandle> {
+    let by_name = self.monitor.as_ref().and_then(|name| {
+      window
+        .available_monitors()
+        .find(|monitor| monitor.name().as_ref() == Some(name))
+    });
+    let by_position = || {
+      let position = self.outer_position?;
+      window.available_monitors().find(|monitor| {
+        let PhysicalPosition { x, y } = monitor.position();
+        let PhysicalSize { width, height } = monitor.size();
+
+        (x..x + width as i32).contains(&position.x)
+          && (y..y + height as i32).contains(&position.y)
+      })
+    };
+
+    by_name
+      .or_else(by_position)
+      .or_else(|| window.primary_monitor())
+      .or_else(|| window.available_monitors().next())
+  }
+}
+
+/// The bounds a window last had while it was neither maximized nor
+/// fullscreen, which are saved in place of the bounds of a maximized window.
+#[derive(Default)]
+pub struct NormalBounds {
+  outer_position: Cell<Option<PhysicalPosition<i32>>>,
+  inner_size: Cell<Option<PhysicalSize<u32>>>,
+}
+
+impl NormalBounds {
+  pub fn handle_event(&self, window: &Window, event: &WindowEvent) {
+    let normal = || !window.is_maximized() && window.fullscreen().is_none();
+
+    match event {
+      WindowEvent::Moved(position) if normal() => {
+        self.outer_position.set(Some(*position))
+      }
+      WindowEvent::Resized(size) if normal() => {
+        self.inner_size.set(Some(*size))
+      }
+      _ => {}
+    }
+  }
+}
+
+/// Shrinks and moves a window so that it, including its decorations, fits in
+/// the given monitor area.
+fn clamp(
+  position: PhysicalPosition<i32>,
+  inner_size: PhysicalSize<u32>,
+  decorations: PhysicalSize<u32>,
+  monitor_position: PhysicalPosition<i32>,
+  monitor_size: PhysicalSize<u32>,
+) -> (PhysicalPosition<i32>, PhysicalSize<u32>) {
+  let inner_size = PhysicalSize::new(
+    inner_size
+      .width
+      .min(monitor_size.width.saturating_sub(decorations.width))
+      .max(1),
+    inner_size
+      .height
+      .min(monitor_size.height.saturating_sub(decorations.height))
+      .max(1),
+  );
+  let outer_width = (inner_size.width + decorations.width) as i32;
+  let outer_height = (inner_size.height + decorations.height) as i32;
+
+  let position = PhysicalPosition::new(
+    position
+      .x
+      .min(monitor_position.x + monitor_size.width as i32 - outer_width)
+      .max(monitor_position.x),
+    position
+      .y
+      .min(monitor_position.y + monitor_size.height as i32 - outer_height)
+      .max(monitor_position.y),
+  );
+
+  (position, inner_size)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const MONITOR_POSITION: PhysicalPosition<i32> =
+    PhysicalPosition { x: 0, y: 0 };
+  const MONITOR_SIZE: PhysicalSize<u32> = PhysicalSize {
+    width: 1920,
+    height: 1080,
+  };
+  const DECORATIONS: PhysicalSize<u32> = PhysicalSize {
+    width: 10,
+    height: 40,
+  };
+
+  fn clamp_to_monitor(
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+  ) -> ((i32, i32), (u32, u32)) {
+    let (position, size) = clamp(
+      PhysicalPosition::new(x, y),
+      PhysicalSize::new(width, height),
+      DECORATIONS,
+      MONITOR_POSITION,
+      MONITOR_SIZE,
+    );
+
+    ((position.x, position.y), (size.width, size.height))
+  }
+
+  #[test]
+  fn keeps_windows_that_fit() {
+    assert_eq!(
+      clamp_to_monitor(100, 200, 800, 600),
+      ((100, 200), (800, 600))
+    );
+  }
+
+  #[test]
+  fn moves_partially_off_screen_windows_back() {
+    // Off the bottom right edges.
+    assert_eq!(
+      clamp_to_monitor(1500, 900, 800, 600),
+      ((1110, 440), (800, 600))
+    );
+    // Off the top left edges.
+    assert_eq!(clamp_to_monitor(-300, -20, 800, 600), ((0, 0), (800, 600)));
+  }
+
+  #[test]
+  fn moves_windows_of_a_monitor_that_is_gone() {
+    // The window was on a monitor to the right of this one.
+    assert_eq!(
+      clamp_to_monitor(2200, 100, 800, 600),
+      ((1110, 100), (800, 600))
+    );
+  }
+
+  #[test]
+  fn shrinks_windows_larger_than_the_monitor() {
+    // The window was on a larger monitor that is gone.
+    assert_eq!(
+      clamp_to_monitor(-100, 50, 2560, 1440),
+      ((0, 0), (1910, 1040))
+    );
+  }
+
+  #[test]
+  fn clamps_to_monitors_that_are_not_at_the_origin() {
+    let (position, size) = clamp(
+      PhysicalPosition::new(0, 0),
+      PhysicalSize::new(800, 600),
+      DECORATIONS,
+      PhysicalPosition::new(1920, -200),
+      PhysicalSize::new(1280, 1024),
+    );
+
+    assert_eq!(position, PhysicalPosition::new(1920, 0));
+    assert_eq!(size, PhysicalSize::new(800, 600));
+  }
+}
@@ -0,0 +1,742 @@
+use std::time::Duration;
+use std::time::Instant;
+
+use deno_core::serde::Serialize;
+
+use winit::dpi::PhysicalPosition;
+use winit::event::Touch;
+use winit::event::TouchPhase;
+
+use crate::event::TouchPhaseDef;
+
+/// How far touches may move, in logical pixels, before they are no longer a
+/// tap or long press.
+const SLOP: f64 = 10.0;
+/// How far the fingers of a rotation have to turn, in radians, before it
+/// starts.
+const ROTATION_SLOP: f64 = 0.1;
+const LONG_PRESS: Duration = Duration::from_millis(500);
+/// How long after a tap a second tap makes it a double tap.
+const DOUBLE_TAP: Duration = Duration::from_millis(300);
+/// How much of the velocity of a pan is made up by its latest movement.
+const VELOCITY_SMOOTHING: f64 = 0.5;
+/// How quickly a pan loses its velocity once the touches stop moving, the
+/// velocity drops to about a third in this time.
+const VELOCITY_DECAY: Duration = Duration::from_millis(50);
+
+/// A gesture recognized from the touches of a window, locations are in
+/// physical pixels like those of `Touch`.
+///
+/// Pans, pinches and rotations report their phase, they start once the
+/// touches have moved far enough and end when the touches involved lift.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type", content = "value")]
+pub enum Gesture {
+  /// A single touch lifted shortly after it started, without moving.
+  Tap { location: PhysicalPosition<f64> },
+  /// A tap shortly after and close to the previous tap, which is also
+  /// reported as a tap.
+  DoubleTap { location: PhysicalPosition<f64> },
+  /// A single touch that has been held without moving.
+  LongPress { location: PhysicalPosition<f64> },
+  /// The touches moved, `location` is their centroid. `translation` is the
+  /// movement since the last pan event and `velocity` is in pixels per
+  /// second.
+  Pan {
+    #[serde(with = "TouchPhaseDef")]
+    phase: TouchPhase,
+    location: PhysicalPosition<f64>,
+    translation: (f64, f64),
+    velocity: (f64, f64),
+  },
+  /// Two touches moved apart or together, `scale` is their distance relative
+  /// to when they were put down.
+  Pinch {
+    #[serde(with = "TouchPhaseDef")]
+    phase: TouchPhase,
+    location: PhysicalPosition<f64>,
+    scale: f64,
+  },
+  /// Two touches turned around each other, `rotation` is the clockwise angle
+  /// in radians since they were put down.
+  Rotate {
+    #[serde(with = "TouchPhaseDef")]
+    phase: TouchPhase,
+    location: PhysicalPosition<f64>,
+    rotation: f64,
+  },
+}
+
+/// The touches of two fingers, tracked from when the second finger was put
+/// down.
+struct TwoFingers {
+  distance: f64,
+  angle: f64,
+  pinching: bool,
+  rotating: bool,
+  /// The last reported scale and rotation.
+  scale: f64,
+  rotation: f64,
+}
+
+/// The touches from when the first one was put down until all of them lifted.
+struct Sequence {
+  started: Instant,
+  origin: PhysicalPosition<f64>,
+  max_touches: usize,
+  /// Whether the touches moved too far for a tap or long press.
+  moved: bool,
+  long_pressed: bool,
+  cancelled: bool,
+  panning: bool,
+  centroid: PhysicalPosition<f64>,
+  moved_at: Instant,
+  velocity: (f64, f64),
+  two_fingers: Option<TwoFingers>,
+}
+
+/// Recognizes gestures from the touches of a window.
+///
+/// The time of every touch is passed in, so synthetic touch sequences can be
+/// fed to it.
+#[derive(Default)]
+pub struct GestureRecognizer {
+  touches: Vec<(u64, PhysicalPosition<f64>)>,
+  sequence: Option<Sequence>,
+  last_tap: Option<(Instant, PhysicalPosition<f64>)>,
+}
+
+impl GestureRecognizer {
+  /// Handles a touch of the window, returning the gestures it completes or
+  /// continues.
+  pub fn handle_touch(
+    &mut self,
+    touch: &Touch,
+    scale_factor: f64,
+    now: Instant,
+  ) -> Vec<Gesture> {
+    let mut gestures = Vec::new();
+    let slop = SLOP * scale_factor;
+
+    match touch.phase {
+      TouchPhase::Started => {
+        self.touches.retain(|(id, _)| *id != touch.id);
+        self.touches.push((touch.id, touch.location));
+
+        let sequence = self.sequence.get_or_insert(Sequence {
+          started: now,
+          origin: touch.location,
+          max_touches: 0,
+          moved: false,
+          long_pressed: false,
+          cancelled: false,
+          panning: false,
+          centroid: touch.location,
+          moved_at: now,
+          velocity: (0.0, 0.0),
+          two_fingers: None,
+        });
+        sequence.max_touches = sequence.max_touches.max(self.touches.len());
+        self.regroup(&mut gestures);
+      }
+      TouchPhase::Moved => {
+        match self.touches.iter_mut().find(|(id, _)| *id == touch.id) {
+          Some((_, location)) => *location = touch.location,
+          None => return gestures,
+        }
+        self.track(slop, now, &mut gestures);
+      }
+      TouchPhase::Ended | TouchPhase::Cancelled => {
+        let count = self.touches.len();
+        self.touches.retain(|(id, _)| *id != touch.id);
+        if self.touches.len() == count {
+          return gestures;
+        }
+
+        if let Some(sequence) = &mut self.sequence {
+          if touch.phase == TouchPhase::Cancelled {
+            sequence.cancelled = true;
+          }
+        }
+
+        if self.touches.is_empty() {
+          self.finish(touch.location, slop, now, &mut gestures);
+        } else {
+          self.regroup(&mut gestures);
+        }
+      }
+    }
+
+    gestures
+  }
+
+  /// Returns a long press once a single touch has been held long enough,
+  /// which is not tied to a touch event.
+  pub fn poll(&mut self, now: Instant) -> Option<Gesture> {
+    let sequence = self.sequence.as_mut()?;
+
+    if sequence.max_touches != 1
+      || sequence.moved
+      || sequence.long_pressed
+      || sequence.cancelled
+      || now.duration_since(sequence.started) < LONG_PRESS
+    {
+      return None;
+    }
+
+    sequence.long_pressed = true;
+    Some(Gesture::LongPress {
+      location: self.touches.first()?.1,
+    })
+  }
+
+  fn centroid(&self) -> PhysicalPosition<f64> {
+    let count = self.touches.len().max(1) as f64;
+    let (x, y) = self
+      .touches
+      .iter()
+      .fold((0.0, 0.0), |(x, y), (_, location)| {
+        (x + location.x, y + location.y)
+      });
+
+    PhysicalPosition::new(x / count, y / count)
+  }
+
+  /// The distance and angle between the first two touches.
+  fn span(&self) -> Option<(f64, f64)> {
+    match self.touches.as_slice() {
+      [(_, first), (_, second), ..] => {
+        let (x, y) = (second.x - first.x, second.y - first.y);
+        Some((x.hypot(y), y.atan2(x)))
+      }
+      _ => None,
+    }
+  }
+
+  /// Starts tracking the touches again after a finger was put down or lifted,
+  /// so the centroid and span do not jump.
+  fn regroup(&mut self, gestures: &mut Vec<Gesture>) {
+    let centroid = self.centroid();
+    let span = self.span();
+    let sequence = match &mut self.sequence {
+      Some(sequence) => sequence,
+      None => return,
+    };
+
+    sequence.centroid = centroid;
+    if let Some(two_fingers) = sequence.two_fingers.take() {
+      end_two_fingers(two_fingers, centroid, TouchPhase::Ended, gestures);
+    }
+    sequence.two_fingers = span.map(|(distance, angle)| TwoFingers {
+      distance,
+      angle,
+      pinching: false,
+      rotating: false,
+      scale: 1.0,
+      rotation: 0.0,
+    });
+  }
+
+  /// Continues the gestures of the sequence after a touch moved.
+  fn track(&mut self, slop: f64, now: Instant, gestures: &mut Vec<Gesture>) {
+    let centroid = self.centroid();
+    let span = self.span();
+    let location = self.touches.first().map(|(_, location)| *location);
+    let sequence = match &mut self.sequence {
+      Some(sequence) => sequence,
+      None => return,
+    };
+
+    if let Some(location) = location {
+      if distance(location, sequence.origin) > slop {
+        sequence.moved = true;
+      }
+    }
+
+    let translation = (
+      centroid.x - sequence.centroid.x,
+      centroid.y - sequence.centroid.y,
+    );
+    let seconds = now.duration_since(sequence.moved_at).as_secs_f64();
+    if seconds > 0.0 {
+      sequence.velocity = (
+        smooth(sequence.velocity.0, translation.0 / seconds),
+        smooth(sequence.velocity.1, translation.1 / seconds),
+      );
+    }
+
+    if !sequence.panning && sequence.moved {
+      sequence.panning = true;
+      gestures.push(Gesture::Pan {
+        phase: TouchPhase::Started,
+        location: centroid,
+        translation,
+        velocity: sequence.velocity,
+      });
+    } else if sequence.panning && translation != (0.0, 0.0) {
+      gestures.push(Gesture::Pan {
+        phase: TouchPhase::Moved,
+        location: centroid,
+        translation,
+        velocity: sequence.velocity,
+      });
+    }
+    if sequence.panning {
+      sequence.centroid = centroid;
+      sequence.moved_at = now;
+    }
+
+    let (two_fingers, (distance, angle)) =
+      match (&mut sequence.two_fingers, span) {
+        (Some(two_fingers), Some(span)) => (two_fingers, span),
+        _ => return,
+      };
+
+    if two_fingers.distance > 0.0 {
+      let scale = distance / two_fingers.distance;
+      let phase = if two_fingers.pinching {
+        TouchPhase::Moved
+      } else {
+        TouchPhase::Started
+      };
+
+      if two_fingers.pinching || (distance - two_fingers.distance).abs() > slop
+      {
+        two_fingers.pinching = true;
+        two_fingers.scale = scale;
+        gestures.push(Gesture::Pinch {
+          phase,
+          location: centroid,
+          scale,
+        });
+      }
+    }
+
+    let rotation = normalize_angle(angle - two_fingers.angle);
+    if two_fingers.rotating || rotation.abs() > ROTATION_SLOP {
+      let phase = if two_fingers.rotating {
+        TouchPhase::Moved
+      } else {
+        TouchPhase::Started
+      };
+
+      two_fingers.rotating = true;
+      two_fingers.rotation = rotation;
+      gestures.push(Gesture::Rotate {
+        phase,
+        location: centroid,
+        rotation,
+      });
+    }
+  }
+
+  /// Ends the sequence once all touches lifted, the location is where the
+  /// last one did.
+  fn finish(
+    &mut self,
+    location: PhysicalPosition<f64>,
+    slop: f64,
+    now: Instant,
+    gestures: &mut Vec<Gesture>,
+  ) {
+    let sequence = match self.sequence.take() {
+      Some(sequence) => sequence,
+      None => return,
+    };
+    let phase = if sequence.cancelled {
+      TouchPhase::Cancelled
+    } else {
+      TouchPhase::Ended
+    };
+
+    if let Some(two_fingers) = sequence.two_fingers {
+      end_two_fingers(two_fingers, location, phase, gestures);
+    }
+    if sequence.panning {
+      // The touches may have rested before they lifted, which the velocity
+      // of their last movement does not account for.
+      let rested = now.duration_since(sequence.moved_at).as_secs_f64();
+      let decay = (-rested / VELOCITY_DECAY.as_secs_f64()).exp();
+
+      gestures.push(Gesture::Pan {
+        phase,
+        location,
+        translation: (0.0, 0.0),
+        velocity: (sequence.velocity.0 * decay, sequence.velocity.1 * decay),
+      });
+    }
+
+    if sequence.cancelled
+      || sequence.moved
+      || sequence.long_pressed
+      || sequence.max_touches != 1
+      || now.duration_since(sequence.started) >= LONG_PRESS
+    {
+      return;
+    }
+
+    gestures.push(Gesture::Tap { location });
+    match self.last_tap.take() {
+      Some((tapped, last_location))
+        if now.duration_since(tapped) <= DOUBLE_TAP
+          && distance(location, last_location) <= slop * 2.0 =>
+      {
+        gestures.push(Gesture::DoubleTap { location });
+      }
+      _ => self.last_tap = Some((now, location)),
+    }
+  }
+}
+
+fn end_two_fingers(
+  two_fingers: TwoFingers,
+  location: PhysicalPosition<f64>,
+  phase: TouchPhase,
+  gestures: &mut Vec<Gesture>,
+) {
+  if two_fingers.pinching {
+    gestures.push(Gesture::Pinch {
+      phase,
+      location,
+      scale: two_fingers.scale,
+    });
+  }
+  if two_fingers.rotating {
+    gestures.push(Gesture::Rotate {
+      phase,
+      location,
+      rotation: two_fingers.rotation,
+    });
+  }
+}
+
+fn distance(a: PhysicalPosition<f64>, b: PhysicalPosition<f64>) -> f64 {
+  (a.x - b.x).hypot(a.y - b.y)
+}
+
+fn smooth(velocity: f64, latest: f64) -> f64 {
+  velocity * (1.0 - VELOCITY_SMOOTHING) + latest * VELOCITY_SMOOTHING
+}
+
+/// Wraps an angle to the range from -π to π.
+fn normalize_angle(angle: f64) -> f64 {
+  use std::f64::consts::PI;
+
+  let angle = angle % (2.0 * PI);
+  if angle > PI {
+    angle - 2.0 * PI
+  } else if angle < -PI {
+    angle + 2.0 * PI
+  } else {
+    angle
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use std::f64::consts::PI;
+
+  use winit::event::DeviceId;
+
+  /// Feeds touches to a recognizer at times relative to the first touch.
+  struct Touches {
+    recognizer: GestureRecognizer,
+    start: Instant,
+  }
+
+  impl Touches {
+    fn new() -> Self {
+      Self {
+        recognizer: GestureRecognizer::default(),
+        start: Instant::now(),
+      }
+    }
+
+    fn at(&self, millis: u64) -> Instant {
+      self.start + Duration::from_millis(millis)
+    }
+
+    fn touch(
+      &mut self,
+      millis: u64,
+      id: u64,
+      phase: TouchPhase,
+      (x, y): (f64, f64),
+    ) -> Vec<Gesture> {
+      let touch = Touch {
+        device_id: unsafe { DeviceId::dummy() },
+        phase,
+        location: PhysicalPosition::new(x, y),
+        force: None,
+        id,
+      };
+
+      self.recognizer.handle_touch(&touch, 1.0, self.at(millis))
+    }
+
+    fn poll(&mut self, millis: u64) -> Option<Gesture> {
+      self.recognizer.poll(self.at(millis))
+    }
+  }
+
+  fn assert_close(actual: f64, expected: f64) {
+    assert!(
+      (actual - expected).abs() < 1e-6,
+      "{} is not {}",
+      actual,
+      expected
+    );
+  }
+
+  /// Returns the phase, translation and velocity of a pan.
+  fn pan(gesture: &Gesture) -> (TouchPhase, (f64, f64), (f64, f64)) {
+    match gesture {
+      Gesture::Pan {
+        phase,
+        translation,
+        velocity,
+        ..
+      } => (*phase, *translation, *velocity),
+      gesture => panic!("{:?} is not a pan", gesture),
+    }
+  }
+
+  #[test]
+  fn taps() {
+    let mut touches = Touches::new();
+
+    assert!(touches
+      .touch(0, 1, TouchPhase::Started, (100.0, 100.0))
+      .is_empty());
+    let gestures = touches.touch(100, 1, TouchPhase::Ended, (102.0, 99.0));
+
+    assert!(matches!(
+      gestures.as_slice(),
+      [Gesture::Tap { location }] if *location == PhysicalPosition::new(102.0, 99.0)
+    ));
+  }
+
+  #[test]
+  fn double_taps_close_to_the_last_tap() {
+    let mut touches = Touches::new();
+
+    touches.touch(0, 1, TouchPhase::Started, (100.0, 100.0));
+    touches.touch(50, 1, TouchPhase::Ended, (100.0, 100.0));
+    touches.touch(150, 2, TouchPhase::Started, (105.0, 100.0));
+    let gestures = touches.touch(200, 2, TouchPhase::Ended, (105.0, 100.0));
+
+    assert!(matches!(
+      gestures.as_slice(),
+      [Gesture::Tap { .. }, Gesture::DoubleTap { .. }]
+    ));
+
+    // The double tap used up the first tap, and the next one is too late.
+    touches.touch(250, 3, TouchPhase::Started, (105.0, 100.0));
+    let gestures = touches.touch(300, 3, TouchPhase::Ended, (105.0, 100.0));
+    assert!(matches!(gestures.as_slice(), [Gesture::Tap { .. }]));
+    touches.touch(700, 4, TouchPhase::Started, (105.0, 100.0));
+    let gestures = touches.touch(750, 4, TouchPhase::Ended, (105.0, 100.0));
+    assert!(matches!(gestures.as_slice(), [Gesture::Tap { .. }]));
+  }
+
+  #[test]
+  fn does_not_double_tap_far_from_the_last_tap() {
+    let mut touches = Touches::new();
+
+    touches.touch(0, 1, TouchPhase::Started, (100.0, 100.0));
+    touches.touch(50, 1, TouchPhase::Ended, (100.0, 100.0));
+    touches.touch(100, 2, TouchPhase::Started, (200.0, 100.0));
+    let gestures = touches.touch(150, 2, TouchPhase::Ended, (200.0, 100.0));
+
+    assert!(matches!(gestures.as_slice(), [Gesture::Tap { .. }]));
+  }
+
+  #[test]
+  fn long_presses_instead_of_tapping() {
+    let mut touches = Touches::new();
+
+    touches.touch(0, 1, TouchPhase::Started, (10.0, 20.0));
+    assert!(touches.poll(499).is_none());
+    assert!(matches!(
+      touches.poll(500),
+      Some(Gesture::LongPress { location }) if location == PhysicalPosition::new(10.0, 20.0)
+    ));
+    assert!(touches.poll(600).is_none());
+
+    assert!(touches
+      .touch(700, 1, TouchPhase::Ended, (10.0, 20.0))
+      .is_empty());
+  }
+
+  #[test]
+  fn does_not_long_press_after_moving() {
+    let mut touches = Touches::new();
+
+    touches.touch(0, 1, TouchPhase::Started, (0.0, 0.0));
+    touches.touch(100, 1, TouchPhase::Moved, (30.0, 0.0));
+
+    assert!(touches.poll(600).is_none());
+  }
+
+  #[test]
+  fn does_not_pan_within_the_slop() {
+    let mut touches = Touches::new();
+
+    touches.touch(0, 1, TouchPhase::Started, (0.0, 0.0));
+    assert!(touches
+      .touch(5, 1, TouchPhase::Moved, (6.0, 8.0))
+      .is_empty());
+    let gestures = touches.touch(10, 1, TouchPhase::Ended, (6.0, 8.0));
+
+    assert!(matches!(gestures.as_slice(), [Gesture::Tap { .. }]));
+  }
+
+  #[test]
+  fn pans_with_a_smoothed_velocity() {
+    let mut touches = Touches::new();
+
+    touches.touch(0, 1, TouchPhase::Started, (0.0, 0.0));
+    let gestures = touches.touch(10, 1, TouchPhase::Moved, (20.0, 0.0));
+    let (phase, translation, velocity) = pan(&gestures[0]);
+    assert_eq!(phase, TouchPhase::Started);
+    assert_eq!(translation, (20.0, 0.0));
+    // Half of 20 pixels in 10 milliseconds.
+    assert_close(velocity.0, 1000.0);
+    assert_close(velocity.1, 0.0);
+
+    let gestures = touches.touch(20, 1, TouchPhase::Moved, (40.0, 10.0));
+    let (phase, translation, velocity) = pan(&gestures[0]);
+    assert_eq!(phase, TouchPhase::Moved);
+    assert_eq!(translation, (20.0, 10.0));
+    assert_close(velocity.0, 1500.0);
+    assert_close(velocity.1, 500.0);
+
+    let gestures = touches.touch(20, 1, TouchPhase::Ended, (40.0, 10.0));
+    let (phase, translation, velocity) = pan(&gestures[0]);
+    assert_eq!(gestures.len(), 1, "a pan is not a tap");
+    assert_eq!(phase, TouchPhase::Ended);
+    assert_eq!(translation, (0.0, 0.0));
+    assert_close(velocity.0, 1500.0);
+    assert_close(velocity.1, 500.0);
+  }
+
+  #[test]
+  fn decays_the_velocity_of_pans_that_rested() {
+    let mut touches = Touches::new();
+
+    touches.touch(0, 1, TouchPhase::Started, (0.0, 0.0));
+    touches.touch(10, 1, TouchPhase::Moved, (20.0, 0.0));
+    let gestures = touches.touch(60, 1, TouchPhase::Ended, (20.0, 0.0));
+    let (_, _, velocity) = pan(&gestures[0]);
+    assert_close(velocity.0, 1000.0 / std::f64::consts::E);
+
+    touches.touch(100, 2, TouchPhase::Started, (0.0, 0.0));
+    touches.touch(110, 2, TouchPhase::Moved, (20.0, 0.0));
+    let gestures = touches.touch(2000, 2, TouchPhase::Ended, (20.0, 0.0));
+    let (_, _, velocity) = pan(&gestures[0]);
+    assert!(velocity.0.abs() < 1e-6);
+  }
+
+  #[test]
+  fn pinches() {
+    let mut touches = Touches::new();
+
+    touches.touch(0, 1, TouchPhase::Started, (0.0, 0.0));
+    touches.touch(0, 2, TouchPhase::Started, (100.0, 0.0));
+    // Two fingers are never a tap or long press.
+    assert!(touches.poll(600).is_none());
+
+    let gestures = touches.touch(10, 2, TouchPhase::Moved, (200.0, 0.0));
+    assert!(matches!(
+      gestures.as_slice(),
+      [Gesture::Pinch { phase: TouchPhase::Started, scale, location }]
+        if *scale == 2.0 && *location == PhysicalPosition::new(100.0, 0.0)
+    ));
+
+    let gestures = touches.touch(20, 2, TouchPhase::Moved, (50.0, 0.0));
+    assert!(matches!(
+      gestures.as_slice(),
+      [Gesture::Pinch { phase: TouchPhase::Moved, scale, .. }] if *scale == 0.5
+    ));
+
+    let gestures = touches.touch(30, 2, TouchPhase::Ended, (50.0, 0.0));
+    assert!(matches!(
+      gestures.as_slice(),
+      [Gesture::Pinch { phase: TouchPhase::Ended, scale, .. }] if *scale == 0.5
+    ));
+    assert!(touches
+      .touch(40, 1, TouchPhase::Ended, (0.0, 0.0))
+      .is_empty());
+  }
+
+  #[test]
+  fn rotates() {
+    let mut touches = Touches::new();
+
+    touches.touch(0, 1, TouchPhase::Started, (0.0, 0.0));
+    touches.touch(0, 2, TouchPhase::Started, (100.0, 0.0));
+
+    // Turning less than the slop does not start a rotation.
+    let angle: f64 = 0.05;
+    let gestures = touches.touch(
+      5,
+      2,
+      TouchPhase::Moved,
+      (100.0 * angle.cos(), 100.0 * angle.sin()),
+    );
+    assert!(gestures.is_empty());
+
+    let gestures = touches.touch(10, 2, TouchPhase::Moved, (0.0, 100.0));
+    match gestures.as_slice() {
+      [Gesture::Rotate {
+        phase: TouchPhase::Started,
+        rotation,
+        ..
+      }] => assert_close(*rotation, PI / 2.0),
+      gestures => panic!("{:?} is not a rotation", gestures),
+    }
+
+    // Rotations wrap around instead of jumping past half a turn.
+    let gestures = touches.touch(20, 2, TouchPhase::Moved, (-100.0, -1.0));
+    match gestures.as_slice() {
+      [Gesture::Rotate {
+        phase: TouchPhase::Moved,
+        rotation,
+        ..
+      }] => assert_close(*rotation, (-1.0f64).atan2(-100.0)),
+      gestures => panic!("{:?} is not a rotation", gestures),
+    }
+  }
+
+  #[test]
+  fn cancels_gestures() {
+    let mut touches = Touches::new();
+
+    touches.touch(0, 1, TouchPhase::Started, (0.0, 0.0));
+    touches.touch(10, 1, TouchPhase::Moved, (30.0, 0.0));
+    let gestures = touches.touch(20, 1, TouchPhase::Cancelled, (30.0, 0.0));
+
+    assert_eq!(gestures.len(), 1);
+    assert_eq!(pan(&gestures[0]).0, TouchPhase::Cancelled);
+
+    // A cancelled touch is not a tap.
+    touches.touch(100, 2, TouchPhase::Started, (0.0, 0.0));
+    assert!(touches
+      .touch(150, 2, TouchPhase::Cancelled, (0.0, 0.0))
+      .is_empty());
+  }
+
+  #[test]
+  fn ignores_unknown_touches() {
+    let mut touches = Touches::new();
+
+    assert!(touches
+      .touch(0, 1, TouchPhase::Moved, (0.0, 0.0))
+      .is_empty());
+    assert!(touches
+      .touch(0, 1, TouchPhase::Ended, (0.0, 0.0))
+      .is_empty());
+  }
+}
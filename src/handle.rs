@@ -0,0 +1,119 @@
+use deno_core::serde::Serialize;
+
+use raw_window_handle::RawWindowHandle;
+
+/// A serializable description of a `RawWindowHandle`, with pointers given as
+/// addresses.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type", content = "value")]
+pub enum RawHandle {
+  #[cfg(target_os = "ios")]
+  #[serde(rename_all = "camelCase")]
+  Ios {
+    ui_window: usize,
+    ui_view: usize,
+    ui_view_controller: usize,
+  },
+  #[cfg(target_os = "macos")]
+  #[serde(rename_all = "camelCase")]
+  MacOs { ns_window: usize, ns_view: usize },
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  Xlib {
+    window: std::os::raw::c_ulong,
+    display: usize,
+  },
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  Xcb { window: u32, connection: usize },
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  Wayland { surface: usize, display: usize },
+  #[cfg(target_os = "windows")]
+  Windows { hwnd: usize, hinstance: usize },
+  #[cfg(target_arch = "wasm32")]
+  Web { id: u32 },
+  #[cfg(target_os = "android")]
+  #[serde(rename_all = "camelCase")]
+  Android { a_native_window: usize },
+}
+
+impl RawHandle {
+  /// Returns `None` for handle variants added after `raw-window-handle` 0.3.
+  pub fn new(handle: RawWindowHandle) -> Option<Self> {
+    match handle {
+      #[cfg(target_os = "ios")]
+      RawWindowHandle::IOS(handle) => Some(RawHandle::Ios {
+        ui_window: handle.ui_window as usize,
+        ui_view: handle.ui_view as usize,
+        ui_view_controller: handle.ui_view_controller as usize,
+      }),
+      #[cfg(target_os = "macos")]
+      RawWindowHandle::MacOS(handle) => Some(RawHandle::MacOs {
+        ns_window: handle.ns_window as usize,
+        ns_view: handle.ns_view as usize,
+      }),
+      #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+      ))]
+      RawWindowHandle::Xlib(handle) => Some(RawHandle::Xlib {
+        window: handle.window,
+        display: handle.display as usize,
+      }),
+      #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+      ))]
+      RawWindowHandle::Xcb(handle) => Some(RawHandle::Xcb {
+        window: handle.window,
+        connection: handle.connection as usize,
+      }),
+      #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+      ))]
+      RawWindowHandle::Wayland(handle) => Some(RawHandle::Wayland {
+        surface: handle.surface as usize,
+        display: handle.display as usize,
+      }),
+      #[cfg(target_os = "windows")]
+      RawWindowHandle::Windows(handle) => Some(RawHandle::Windows {
+        hwnd: handle.hwnd as usize,
+        hinstance: handle.hinstance as usize,
+      }),
+      #[cfg(target_arch = "wasm32")]
+      RawWindowHandle::Web(handle) => Some(RawHandle::Web { id: handle.id }),
+      #[cfg(target_os = "android")]
+      RawWindowHandle::Android(handle) => Some(RawHandle::Android {
+        a_native_window: handle.a_native_window as usize,
+      }),
+      #[allow(unreachable_patterns)]
+      _ => None,
+    }
+  }
+}
@@ -7,3 +7,103 @@ pub fn hash<T: Hash>(t: T) -> u32 {
   t.hash(&mut s);
   s.finish() as u32
 }
+
+/// A private D-Bus bus for tests, so they neither need nor disturb a session
+/// bus. The bus is stopped when dropped.
+#[cfg(all(
+  test,
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+pub mod test_bus {
+  use std::io::BufRead;
+  use std::io::BufReader;
+  use std::io::ErrorKind;
+  use std::process::Child;
+  use std::process::Command;
+  use std::process::Stdio;
+  use std::time::Duration;
+  use std::time::Instant;
+
+  use dbus::blocking::Connection;
+  use dbus::blocking::LocalConnection;
+  use dbus::channel::Channel;
+
+  pub struct TestBus {
+    daemon: Child,
+    address: String,
+  }
+
+  impl TestBus {
+    /// Starts a bus, or returns `None` so the test can be skipped if
+    /// dbus-daemon is not installed.
+    pub fn start() -> Option<Self> {
+      let daemon = Command::new("dbus-daemon")
+        .arg("--session")
+        .arg("--nofork")
+        .arg("--print-address")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+      let mut daemon = match daemon {
+        Ok(daemon) => daemon,
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+          eprintln!("dbus-daemon is not installed, skipping the test");
+          return None;
+        }
+        Err(error) => panic!("Could not start dbus-daemon: {}", error),
+      };
+
+      let mut address = String::new();
+      let stdout = daemon.stdout.as_mut().expect("stdout is piped");
+      BufReader::new(stdout).read_line(&mut address).unwrap();
+
+      Some(Self {
+        daemon,
+        address: address.trim().to_string(),
+      })
+    }
+
+    fn channel(&self) -> Channel {
+      let mut channel = Channel::open_private(&self.address).unwrap();
+      channel.register().unwrap();
+      channel
+    }
+
+    pub fn connect(&self) -> LocalConnection {
+      LocalConnection::from(self.channel())
+    }
+
+    /// Connects with a connection that can be moved to another thread, for
+    /// blocking calls to objects served by the test thread.
+    pub fn connect_sync(&self) -> Connection {
+      Connection::from(self.channel())
+    }
+  }
+
+  impl Drop for TestBus {
+    fn drop(&mut self) {
+      let _ = self.daemon.kill();
+      let _ = self.daemon.wait();
+    }
+  }
+
+  /// Polls until `poll` returns a value, panicking after five seconds.
+  pub fn wait_for<T>(mut poll: impl FnMut() -> Option<T>) -> T {
+    let deadline = Instant::now() + Duration::from_secs(5);
+
+    loop {
+      if let Some(value) = poll() {
+        return value;
+      }
+      assert!(Instant::now() < deadline, "timed out");
+      std::thread::sleep(Duration::from_millis(5));
+    }
+  }
+}
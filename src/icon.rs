@@ -0,0 +1,248 @@
+use deno_core::error::custom_error;
+use deno_core::error::AnyError;
+use deno_core::serde::Deserialize;
+
+use winit::window::BadIcon;
+use winit::window::Icon;
+use winit::window::Window;
+
+#[derive(Clone, Copy, Deserialize)]
+pub struct IconSize {
+  width: u32,
+  height: u32,
+}
+
+#[cfg(test)]
+impl IconSize {
+  pub fn new(width: u32, height: u32) -> Self {
+    Self { width, height }
+  }
+}
+
+/// A window icon as 32bpp RGBA pixels, checked against its dimensions.
+pub struct RgbaIcon<'a> {
+  rgba: &'a [u8],
+  width: u32,
+  height: u32,
+}
+
+#[cfg(feature = "image")]
+impl<'a> From<&'a image::RgbaImage> for RgbaIcon<'a> {
+  fn from(image: &'a image::RgbaImage) -> Self {
+    Self {
+      rgba: image.as_raw(),
+      width: image.width(),
+      height: image.height(),
+    }
+  }
+}
+
+impl RgbaIcon<'_> {
+  fn to_icon(&self) -> Result<Icon, AnyError> {
+    Icon::from_rgba(self.rgba.to_vec(), self.width, self.height)
+      .map_err(bad_icon)
+  }
+
+  /// Packs the icon into the `_NET_WM_ICON` format, a width and height
+  /// followed by ARGB pixels.
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  fn to_cardinals(&self) -> Vec<std::os::raw::c_ulong> {
+    let mut data = Vec::with_capacity(2 + self.rgba.len() / 4);
+
+    data.push(self.width as _);
+    data.push(self.height as _);
+    data.extend(self.rgba.chunks_exact(4).map(|pixel| {
+      let [r, g, b, a] = [pixel[0], pixel[1], pixel[2], pixel[3]];
+      u32::from_be_bytes([a, r, g, b]) as std::os::raw::c_ulong
+    }));
+
+    data
+  }
+
+  /// Packs the icon into the StatusNotifierItem `IconPixmap` format, ARGB32
+  /// pixels in network byte order.
+  #[cfg(all(
+    feature = "freedesktop",
+    any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    )
+  ))]
+  pub fn to_pixmap(&self) -> crate::tray::Pixmap {
+    let argb = self
+      .rgba
+      .chunks_exact(4)
+      .flat_map(|pixel| [pixel[3], pixel[0], pixel[1], pixel[2]].to_vec())
+      .collect();
+
+    (self.width as i32, self.height as i32, argb)
+  }
+
+  /// Packs the icon into the `image-data` hint of desktop notifications.
+  #[cfg(all(
+    feature = "freedesktop",
+    any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    )
+  ))]
+  pub fn to_image_data(&self) -> crate::notification::ImageData {
+    let (width, height) = (self.width as i32, self.height as i32);
+
+    (width, height, width * 4, true, 8, 4, self.rgba.to_vec())
+  }
+}
+
+pub fn bad_icon(error: BadIcon) -> AnyError {
+  custom_error("BadIcon", error.to_string())
+}
+
+/// Splits a buffer of concatenated RGBA icons into the given sizes, erroring
+/// if the buffer is not exactly as long as the sizes require.
+pub fn split_rgba<'a>(
+  mut rgba: &'a [u8],
+  sizes: &[IconSize],
+) -> Result<Vec<RgbaIcon<'a>>, AnyError> {
+  let mut icons = Vec::with_capacity(sizes.len());
+
+  for &IconSize { width, height } in sizes {
+    let byte_count = width as usize * height as usize * 4;
+
+    if byte_count > rgba.len() {
+      return Err(custom_error(
+        "BadIcon",
+        format!(
+          "A {}x{} icon needs {} bytes of RGBA data, but only {} remain",
+          width,
+          height,
+          byte_count,
+          rgba.len()
+        ),
+      ));
+    }
+
+    let (icon, rest) = rgba.split_at(byte_count);
+    icons.push(RgbaIcon {
+      rgba: icon,
+      width,
+      height,
+    });
+    rgba = rest;
+  }
+
+  if !rgba.is_empty() {
+    return Err(custom_error(
+      "BadIcon",
+      format!(
+        "{} bytes of RGBA data remain after the last icon",
+        rgba.len()
+      ),
+    ));
+  }
+
+  Ok(icons)
+}
+
+/// Sets the window icon, clearing it if `icons` is empty. Where the platform
+/// supports it every size is handed to the window manager, otherwise only the
+/// largest icon is used.
+pub fn set_window_icons(
+  window: &Window,
+  icons: &[RgbaIcon],
+) -> Result<(), AnyError> {
+  let largest = icons
+    .iter()
+    .max_by_key(|icon| icon.width as u64 * icon.height as u64);
+
+  window.set_window_icon(largest.map(RgbaIcon::to_icon).transpose()?);
+
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  if !icons.is_empty() {
+    if let Some(window) = crate::x11::XWindow::new(window)? {
+      let data: Vec<_> =
+        icons.iter().flat_map(RgbaIcon::to_cardinals).collect();
+      window.set_cardinals("_NET_WM_ICON", &data);
+    }
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use deno_core::error::get_custom_error_class;
+
+  use super::*;
+
+  fn split(
+    rgba: &[u8],
+    sizes: &[(u32, u32)],
+  ) -> Result<Vec<(u32, u32, usize)>, AnyError> {
+    let sizes: Vec<IconSize> = sizes
+      .iter()
+      .map(|&(width, height)| IconSize::new(width, height))
+      .collect();
+
+    Ok(
+      split_rgba(rgba, &sizes)?
+        .iter()
+        .map(|icon| (icon.width, icon.height, icon.rgba.len()))
+        .collect(),
+    )
+  }
+
+  #[test]
+  fn splits_concatenated_icons() {
+    let rgba: Vec<u8> = (0..(16 + 4) * 4).map(|byte| byte as u8).collect();
+    let sizes = [IconSize::new(4, 4), IconSize::new(2, 2)];
+    let icons = split_rgba(&rgba, &sizes).unwrap();
+
+    assert_eq!(icons.len(), 2);
+    assert_eq!(icons[0].rgba, &rgba[..64]);
+    assert_eq!(icons[1].rgba, &rgba[64..]);
+    assert_eq!((icons[1].width, icons[1].height), (2, 2));
+  }
+
+  #[test]
+  fn splits_no_icons() {
+    assert_eq!(split(&[], &[]).unwrap(), []);
+  }
+
+  #[test]
+  fn rejects_short_data() {
+    let error = split(&[0; 16 * 4 + 15], &[(4, 4), (2, 2)]).unwrap_err();
+
+    assert_eq!(get_custom_error_class(&error), Some("BadIcon"));
+    assert!(error
+      .to_string()
+      .contains("A 2x2 icon needs 16 bytes of RGBA data, but only 15 remain"));
+  }
+
+  #[test]
+  fn rejects_trailing_data() {
+    let error = split(&[0; 4 * 4 + 3], &[(2, 2)]).unwrap_err();
+    assert_eq!(get_custom_error_class(&error), Some("BadIcon"));
+    assert!(error.to_string().contains("3 bytes of RGBA data remain"));
+
+    let error = split(&[0; 4], &[]).unwrap_err();
+    assert_eq!(get_custom_error_class(&error), Some("BadIcon"));
+  }
+}
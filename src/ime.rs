@@ -0,0 +1,756 @@
+use std::collections::HashMap;
+use std::env;
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::mem::MaybeUninit;
+use std::os::raw::c_char;
+use std::os::raw::c_int;
+use std::os::raw::c_short;
+use std::os::raw::c_ulong;
+use std::ptr;
+
+use deno_core::error::generic_error;
+use deno_core::error::AnyError;
+use deno_core::OpState;
+
+use raw_window_handle::HasRawWindowHandle;
+use raw_window_handle::RawWindowHandle;
+
+use winit::window::Window;
+
+use x11_dl::xlib;
+use x11_dl::xlib::Xlib;
+
+use crate::event::Event;
+use crate::event::Ime;
+use crate::event::WindowEvent;
+use crate::helpers::hash;
+
+/// Feedback of preedit characters, the converted clause is usually shown
+/// reversed or highlighted.
+const XIM_REVERSE: c_ulong = 1;
+const XIM_HIGHLIGHT: c_ulong = 1 << 2;
+
+/// Points winit at the local input method, which only handles compose keys,
+/// so pane can talk to the input method server itself. Returns the locale
+/// modifiers of the server, which must be restored with `restore_xmodifiers`
+/// once the event loop is created.
+///
+/// Winit reads `XMODIFIERS` from the environment when the event loop is
+/// created, so it is changed for the whole process in the meantime. This is
+/// only done when the event loop is created with `imeEvents`, as other threads
+/// reading the environment at the same time race with it.
+///
+/// Only input methods named by `XMODIFIERS` are taken over, without it winit
+/// uses the local input method anyway.
+pub fn redirect_xmodifiers() -> Option<String> {
+  let modifiers = env::var("XMODIFIERS").ok()?;
+  env::set_var("XMODIFIERS", "@im=local");
+
+  Some(modifiers)
+}
+
+pub fn restore_xmodifiers(modifiers: &str) {
+  env::set_var("XMODIFIERS", modifiers);
+}
+
+/// The composition shown by the input method, updated by the preedit
+/// callbacks of an input context.
+#[derive(Default)]
+struct Preedit {
+  text: Vec<char>,
+  feedback: Vec<c_ulong>,
+  caret: usize,
+  composing: bool,
+  events: Vec<Ime>,
+}
+
+impl Preedit {
+  fn update(&mut self) {
+    let selected = self
+      .feedback
+      .iter()
+      .enumerate()
+      .filter(|(_, feedback)| *feedback & (XIM_REVERSE | XIM_HIGHLIGHT) != 0)
+      .map(|(index, _)| index);
+    let range = match (selected.clone().min(), selected.max()) {
+      (Some(start), Some(end)) => (start, end + 1),
+      _ => (self.caret, self.caret),
+    };
+
+    self.events.push(Ime::Update {
+      text: self.text.iter().collect(),
+      cursor: Some((self.utf16_offset(range.0), self.utf16_offset(range.1))),
+    });
+  }
+
+  /// Converts a character index of the composition to a UTF-16 offset, as
+  /// used by JavaScript strings.
+  fn utf16_offset(&self, index: usize) -> usize {
+    self.text[..index.min(self.text.len())]
+      .iter()
+      .map(|char| char.len_utf16())
+      .sum()
+  }
+}
+
+/// An input context of a window, composing on the spot if the input method
+/// supports it.
+struct InputContext {
+  ic: xlib::XIC,
+  window_id: u32,
+  /// Boxed so the callbacks can keep a pointer to it.
+  preedit: Box<Preedit>,
+  /// Whether the window accepts text input, the context only has focus
+  /// while the window is focused and accepts text input.
+  active: bool,
+  focused: bool,
+}
+
+/// An input method server connection that replaces winit's input method on
+/// X11, reporting compositions as `ime` window events.
+///
+/// Input goes through a second connection to the X server, which receives
+/// its own copy of the key and focus events of windows with IME enabled.
+pub struct InputMethod {
+  xlib: Xlib,
+  display: *mut xlib::Display,
+  im: xlib::XIM,
+  contexts: HashMap<xlib::Window, InputContext>,
+}
+
+impl InputMethod {
+  pub fn open(modifiers: &str) -> Result<Self, AnyError> {
+    let xlib = Xlib::open()?;
+    let modifiers = CString::new(modifiers)?;
+
+    unsafe {
+      let display = (xlib.XOpenDisplay)(ptr::null());
+      if display.is_null() {
+        return Err(generic_error("XOpenDisplay failed"));
+      }
+
+      (xlib.XSetLocaleModifiers)(modifiers.as_ptr());
+      let im = (xlib.XOpenIM)(
+        display,
+        ptr::null_mut(),
+        ptr::null_mut(),
+        ptr::null_mut(),
+      );
+      if im.is_null() {
+        (xlib.XCloseDisplay)(display);
+        return Err(generic_error("XOpenIM failed"));
+      }
+
+      Ok(Self {
+        xlib,
+        display,
+        im,
+        contexts: HashMap::new(),
+      })
+    }
+  }
+
+  fn x_window(window: &Window) -> Option<xlib::Window> {
+    match window.raw_window_handle() {
+      RawWindowHandle::Xlib(handle) => Some(handle.window),
+      _ => None,
+    }
+  }
+
+  /// Whether IME is enabled for the window, in which case its text input is
+  /// reported by the input method rather than winit.
+  pub fn is_enabled(&self, window: &Window) -> bool {
+    matches!(Self::x_window(window), Some(x_window) if self.contexts.contains_key(&x_window))
+  }
+
+  /// Whether the input method is composing text for the window.
+  pub fn is_composing(&self, window: &Window) -> bool {
+    Self::x_window(window)
+      .and_then(|x_window| self.contexts.get(&x_window))
+      .map(|context| context.preedit.composing)
+      .unwrap_or(false)
+  }
+
+  /// Enables or disables IME for a window. Returns `false` if the window is
+  /// not an X11 window.
+  pub fn set_enabled(
+    &mut self,
+    window: &Window,
+    enabled: bool,
+  ) -> Result<bool, AnyError> {
+    let x_window = match Self::x_window(window) {
+      Some(x_window) => x_window,
+      None => return Ok(false),
+    };
+
+    if enabled && !self.contexts.contains_key(&x_window) {
+      let context = self.create_context(x_window, hash(window.id()))?;
+      self.contexts.insert(x_window, context);
+    } else if !enabled {
+      if let Some(context) = self.contexts.remove(&x_window) {
+        self.destroy_context(x_window, context);
+      }
+    }
+
+    unsafe { (self.xlib.XFlush)(self.display) };
+
+    Ok(true)
+  }
+
+  fn create_context(
+    &self,
+    x_window: xlib::Window,
+    window_id: u32,
+  ) -> Result<InputContext, AnyError> {
+    let mut preedit = Box::new(Preedit::default());
+    let client_data = &mut *preedit as *mut Preedit as xlib::XPointer;
+
+    let start = xlib::XICCallback {
+      client_data,
+      callback: Some(preedit_start),
+    };
+    let done = xlib::XIMCallback {
+      client_data,
+      callback: Some(preedit_done),
+    };
+    let draw = xlib::XIMCallback {
+      client_data,
+      callback: Some(preedit_draw),
+    };
+    let caret = xlib::XIMCallback {
+      client_data,
+      callback: Some(preedit_caret),
+    };
+
+    unsafe {
+      let callbacks = (self.xlib.XVaCreateNestedList)(
+        0,
+        xlib::XNPreeditStartCallback_0.as_ptr(),
+        &start,
+        xlib::XNPreeditDoneCallback_0.as_ptr(),
+        &done,
+        xlib::XNPreeditDrawCallback_0.as_ptr(),
+        &draw,
+        xlib::XNPreeditCaretCallback_0.as_ptr(),
+        &caret,
+        ptr::null_mut::<()>(),
+      );
+      let mut ic = (self.xlib.XCreateIC)(
+        self.im,
+        xlib::XNInputStyle_0.as_ptr(),
+        (xlib::XIMPreeditCallbacks | xlib::XIMStatusNothing) as c_ulong,
+        xlib::XNClientWindow_0.as_ptr(),
+        x_window,
+        xlib::XNFocusWindow_0.as_ptr(),
+        x_window,
+        xlib::XNPreeditAttributes_0.as_ptr(),
+        callbacks,
+        ptr::null_mut::<()>(),
+      );
+      (self.xlib.XFree)(callbacks);
+
+      // Input methods that can not compose on the spot show the composition
+      // in a window of their own.
+      if ic.is_null() {
+        ic = (self.xlib.XCreateIC)(
+          self.im,
+          xlib::XNInputStyle_0.as_ptr(),
+          (xlib::XIMPreeditNothing | xlib::XIMStatusNothing) as c_ulong,
+          xlib::XNClientWindow_0.as_ptr(),
+          x_window,
+          xlib::XNFocusWindow_0.as_ptr(),
+          x_window,
+          ptr::null_mut::<()>(),
+        );
+      }
+      if ic.is_null() {
+        return Err(generic_error("XCreateIC failed"));
+      }
+
+      (self.xlib.XSelectInput)(
+        self.display,
+        x_window,
+        xlib::KeyPressMask
+          | xlib::KeyReleaseMask
+          | xlib::FocusChangeMask
+          | xlib::StructureNotifyMask,
+      );
+
+      let mut focus = 0;
+      let mut revert_to = 0;
+      (self.xlib.XGetInputFocus)(self.display, &mut focus, &mut revert_to);
+      let focused = focus == x_window;
+      if focused {
+        (self.xlib.XSetICFocus)(ic);
+      }
+
+      Ok(InputContext {
+        ic,
+        window_id,
+        preedit,
+        active: true,
+        focused,
+      })
+    }
+  }
+
+  fn destroy_context(&self, x_window: xlib::Window, context: InputContext) {
+    unsafe {
+      (self.xlib.XSelectInput)(self.display, x_window, 0);
+      (self.xlib.XDestroyIC)(context.ic);
+    }
+  }
+
+  /// Sets whether a window accepts text input. Compositions in progress are
+  /// cancelled when text input ends.
+  pub fn set_text_input(&mut self, window: &Window, active: bool) {
+    let contexts = &mut self.contexts;
+    let context = match Self::x_window(window)
+      .and_then(|x_window| contexts.get_mut(&x_window))
+    {
+      Some(context) if context.active != active => context,
+      _ => return,
+    };
+    context.active = active;
+    if !context.focused {
+      return;
+    }
+
+    unsafe {
+      if active {
+        (self.xlib.XSetICFocus)(context.ic);
+      } else {
+        let text = (self.xlib.Xutf8ResetIC)(context.ic);
+        if !text.is_null() {
+          (self.xlib.XFree)(text as *mut _);
+        }
+        (self.xlib.XUnsetICFocus)(context.ic);
+      }
+      (self.xlib.XFlush)(self.display);
+    }
+
+    // Not every input method ends the composition when it is reset.
+    let preedit = &mut context.preedit;
+    if !active && preedit.composing {
+      preedit.text.clear();
+      preedit.feedback.clear();
+      preedit.composing = false;
+      preedit.events.push(Ime::End);
+    }
+  }
+
+  /// Moves the candidate window of the input method, relative to the client
+  /// area of the window in physical pixels.
+  pub fn set_spot(&self, window: &Window, x: i32, y: i32) {
+    let context = match Self::x_window(window)
+      .and_then(|x_window| self.contexts.get(&x_window))
+    {
+      Some(context) => context,
+      None => return,
+    };
+    let spot = xlib::XPoint {
+      x: x.clamp(c_short::MIN as i32, c_short::MAX as i32) as c_short,
+      y: y.clamp(c_short::MIN as i32, c_short::MAX as i32) as c_short,
+    };
+
+    unsafe {
+      let attributes = (self.xlib.XVaCreateNestedList)(
+        0,
+        xlib::XNSpotLocation_0.as_ptr(),
+        &spot,
+        ptr::null_mut::<()>(),
+      );
+      (self.xlib.XSetICValues)(
+        context.ic,
+        xlib::XNPreeditAttributes_0.as_ptr(),
+        attributes,
+        ptr::null_mut::<()>(),
+      );
+      (self.xlib.XFree)(attributes);
+      (self.xlib.XFlush)(self.display);
+    }
+  }
+
+  /// Handles pending events of the input method connection, returning the
+  /// resulting window events.
+  fn process(&mut self) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    unsafe {
+      while (self.xlib.XPending)(self.display) > 0 {
+        let mut event = MaybeUninit::<xlib::XEvent>::uninit();
+        (self.xlib.XNextEvent)(self.display, event.as_mut_ptr());
+        let mut event = event.assume_init();
+
+        // Key events of a composition are consumed by the input method, and
+        // the callbacks are called while filtering.
+        let filtered = (self.xlib.XFilterEvent)(&mut event, 0) != 0;
+        self.take_preedit_events(&mut events);
+        if filtered {
+          continue;
+        }
+
+        match event.get_type() {
+          xlib::KeyPress => events.extend(self.lookup(&mut event.key)),
+          xlib::FocusIn | xlib::FocusOut => {
+            let focus_change = event.focus_change;
+            let context = match self.contexts.get_mut(&focus_change.window) {
+              Some(context) if focus_change.detail != xlib::NotifyPointer => {
+                context
+              }
+              _ => continue,
+            };
+
+            context.focused = focus_change.type_ == xlib::FocusIn;
+            if !context.active {
+              continue;
+            }
+            if context.focused {
+              (self.xlib.XSetICFocus)(context.ic);
+            } else {
+              (self.xlib.XUnsetICFocus)(context.ic);
+            }
+          }
+          xlib::DestroyNotify => {
+            let x_window = event.destroy_window.window;
+            if let Some(context) = self.contexts.remove(&x_window) {
+              (self.xlib.XDestroyIC)(context.ic);
+            }
+          }
+          _ => {}
+        }
+      }
+
+      (self.xlib.XFlush)(self.display);
+    }
+
+    events
+  }
+
+  fn take_preedit_events(&mut self, events: &mut Vec<Event>) {
+    for context in self.contexts.values_mut() {
+      let window_id = context.window_id;
+
+      events.extend(context.preedit.events.drain(..).map(|ime| {
+        Event::WindowEvent {
+          window_id,
+          event: WindowEvent::Ime(ime),
+        }
+      }));
+    }
+  }
+
+  /// Looks up the text of a key press. Text committed by the input method
+  /// arrives as a key press without a keycode.
+  unsafe fn lookup(&self, key: &mut xlib::XKeyEvent) -> Vec<Event> {
+    let context = match self.contexts.get(&key.window) {
+      Some(context) if context.active => context,
+      _ => return Vec::new(),
+    };
+
+    let lookup = |key: &mut xlib::XKeyEvent, buffer: &mut Vec<u8>| {
+      let mut keysym = 0;
+      let mut status = 0;
+      let count = (self.xlib.Xutf8LookupString)(
+        context.ic,
+        key,
+        buffer.as_mut_ptr() as *mut c_char,
+        buffer.len() as c_int,
+        &mut keysym,
+        &mut status,
+      );
+
+      (count, status)
+    };
+
+    let mut buffer = vec![0u8; 64];
+    let (mut count, mut status) = lookup(key, &mut buffer);
+    if status == xlib::XBufferOverflow {
+      buffer.resize(count as usize, 0);
+      let (retry_count, retry_status) = lookup(key, &mut buffer);
+      count = retry_count;
+      status = retry_status;
+    }
+    if status != xlib::XLookupChars && status != xlib::XLookupBoth {
+      return Vec::new();
+    }
+
+    buffer.truncate(count as usize);
+    let text = String::from_utf8_lossy(&buffer).into_owned();
+    let window_id = context.window_id;
+
+    if key.keycode == 0 {
+      vec![Event::WindowEvent {
+        window_id,
+        event: WindowEvent::Ime(Ime::Commit(text)),
+      }]
+    } else {
+      text
+        .chars()
+        .map(|char| Event::WindowEvent {
+          window_id,
+          event: WindowEvent::ReceivedCharacter(char.to_string()),
+        })
+        .collect()
+    }
+  }
+}
+
+impl Drop for InputMethod {
+  fn drop(&mut self) {
+    unsafe {
+      for (_, context) in self.contexts.drain() {
+        (self.xlib.XDestroyIC)(context.ic);
+      }
+      (self.xlib.XCloseIM)(self.im);
+      (self.xlib.XCloseDisplay)(self.display);
+    }
+  }
+}
+
+/// Processes the input method connection if there is one, adding the events
+/// of its compositions.
+pub fn process_input_method(state: &mut OpState, events: &mut Vec<Event>) {
+  if let Some(input_method) = state.try_borrow_mut::<InputMethod>() {
+    events.extend(input_method.process());
+  }
+}
+
+unsafe fn preedit<'a>(client_data: xlib::XPointer) -> &'a mut Preedit {
+  &mut *(client_data as *mut Preedit)
+}
+
+unsafe extern "C" fn preedit_start(
+  _ic: xlib::XIC,
+  client_data: xlib::XPointer,
+  _call_data: xlib::XPointer,
+) -> c_int {
+  let preedit = preedit(client_data);
+  preedit.text.clear();
+  preedit.feedback.clear();
+  preedit.caret = 0;
+  preedit.composing = true;
+  preedit.events.push(Ime::Start);
+
+  // The composition can be of any length.
+  -1
+}
+
+unsafe extern "C" fn preedit_done(
+  _im: xlib::XIM,
+  client_data: xlib::XPointer,
+  _call_data: xlib::XPointer,
+) {
+  let preedit = preedit(client_data);
+  preedit.text.clear();
+  preedit.feedback.clear();
+  preedit.composing = false;
+  preedit.events.push(Ime::End);
+}
+
+unsafe extern "C" fn preedit_draw(
+  _im: xlib::XIM,
+  client_data: xlib::XPointer,
+  call_data: xlib::XPointer,
+) {
+  let preedit = preedit(client_data);
+  let draw = &*(call_data as *const xlib::XIMPreeditDrawCallbackStruct);
+
+  let (text, feedback) = match draw.text.as_ref() {
+    Some(text) if text.encoding_is_wchar == 0 => {
+      let chars: Vec<char> = if text.string.multi_byte.is_null() {
+        Vec::new()
+      } else {
+        CStr::from_ptr(text.string.multi_byte)
+          .to_string_lossy()
+          .chars()
+          .collect()
+      };
+      let feedback = if text.feedback.is_null() {
+        vec![0; chars.len()]
+      } else {
+        std::slice::from_raw_parts(text.feedback, text.length as usize)
+          .iter()
+          .copied()
+          .chain(std::iter::repeat(0))
+          .take(chars.len())
+          .collect()
+      };
+
+      (chars, feedback)
+    }
+    _ => (Vec::new(), Vec::new()),
+  };
+
+  let start = (draw.chg_first.max(0) as usize).min(preedit.text.len());
+  let end = (start + draw.chg_length.max(0) as usize).min(preedit.text.len());
+  preedit.text.splice(start..end, text);
+  preedit.feedback.splice(start..end, feedback);
+  preedit.caret = (draw.caret.max(0) as usize).min(preedit.text.len());
+  preedit.update();
+}
+
+unsafe extern "C" fn preedit_caret(
+  _im: xlib::XIM,
+  client_data: xlib::XPointer,
+  call_data: xlib::XPointer,
+) {
+  let preedit = preedit(client_data);
+  let caret = &mut *(call_data as *mut xlib::XIMPreeditCaretCallbackStruct);
+
+  if caret.direction == xlib::XIMCaretDirection::XIMAbsolutePosition {
+    preedit.caret = (caret.position.max(0) as usize).min(preedit.text.len());
+    preedit.update();
+  }
+  caret.position = preedit.caret as c_int;
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns the text and cursor of the last update of the composition.
+  fn last_update(preedit: &Preedit) -> (String, Option<(usize, usize)>) {
+    match preedit.events.last() {
+      Some(Ime::Update { text, cursor }) => (text.clone(), *cursor),
+      _ => panic!("the composition was not updated"),
+    }
+  }
+
+  /// Calls the draw callback as the input method would, replacing
+  /// `chg_length` characters from `chg_first` with `text`.
+  fn draw(
+    preedit: &mut Preedit,
+    chg_first: c_int,
+    chg_length: c_int,
+    text: Option<(&str, &[c_ulong])>,
+    caret: c_int,
+  ) {
+    let string = text.map(|(text, _)| CString::new(text).unwrap());
+    let mut feedback = text
+      .map(|(_, feedback)| feedback.to_vec())
+      .unwrap_or_default();
+    let mut text = string.as_ref().map(|string| xlib::XIMText {
+      length: feedback.len() as _,
+      feedback: if feedback.is_empty() {
+        ptr::null_mut()
+      } else {
+        feedback.as_mut_ptr()
+      },
+      encoding_is_wchar: 0,
+      string: xlib::XIMTextString {
+        multi_byte: string.as_ptr() as *mut c_char,
+      },
+    });
+    let mut call_data = xlib::XIMPreeditDrawCallbackStruct {
+      caret,
+      chg_first,
+      chg_length,
+      text: text
+        .as_mut()
+        .map_or(ptr::null_mut(), |text| text as *mut xlib::XIMText),
+    };
+
+    unsafe {
+      preedit_draw(
+        ptr::null_mut(),
+        preedit as *mut Preedit as xlib::XPointer,
+        &mut call_data as *mut _ as xlib::XPointer,
+      );
+    }
+  }
+
+  #[test]
+  fn reports_the_caret_without_a_converted_clause() {
+    let mut preedit = Preedit {
+      text: "かな".chars().collect(),
+      feedback: vec![0, 0],
+      caret: 1,
+      ..Preedit::default()
+    };
+    preedit.update();
+
+    assert_eq!(last_update(&preedit), ("かな".to_string(), Some((1, 1))));
+  }
+
+  #[test]
+  fn reports_the_converted_clause() {
+    let mut preedit = Preedit {
+      text: "漢字かな".chars().collect(),
+      feedback: vec![XIM_REVERSE, XIM_HIGHLIGHT, 0, 0],
+      caret: 4,
+      ..Preedit::default()
+    };
+    preedit.update();
+
+    assert_eq!(last_update(&preedit).1, Some((0, 2)));
+  }
+
+  #[test]
+  fn converts_offsets_to_utf16() {
+    let preedit = Preedit {
+      text: "a😀b".chars().collect(),
+      ..Preedit::default()
+    };
+
+    assert_eq!(preedit.utf16_offset(0), 0);
+    assert_eq!(preedit.utf16_offset(1), 1);
+    assert_eq!(preedit.utf16_offset(2), 3);
+    assert_eq!(preedit.utf16_offset(3), 4);
+    // Indices past the end are clamped to the end of the composition.
+    assert_eq!(preedit.utf16_offset(10), 4);
+  }
+
+  #[test]
+  fn inserts_replaces_and_deletes_drawn_text() {
+    let mut preedit = Preedit::default();
+
+    draw(&mut preedit, 0, 0, Some(("nihon", &[0; 5])), 5);
+    assert_eq!(last_update(&preedit), ("nihon".to_string(), Some((5, 5))));
+
+    draw(
+      &mut preedit,
+      0,
+      5,
+      Some(("日本", &[XIM_REVERSE, XIM_REVERSE])),
+      2,
+    );
+    assert_eq!(last_update(&preedit), ("日本".to_string(), Some((0, 2))));
+    assert_eq!(preedit.feedback, [XIM_REVERSE, XIM_REVERSE]);
+
+    draw(&mut preedit, 2, 0, Some(("語😀", &[0, 0])), 4);
+    assert_eq!(
+      last_update(&preedit),
+      ("日本語😀".to_string(), Some((0, 2)))
+    );
+
+    // Without text the changed characters are deleted.
+    draw(&mut preedit, 0, 2, None, 1);
+    assert_eq!(last_update(&preedit), ("語😀".to_string(), Some((1, 1))));
+    assert_eq!(preedit.feedback, [0, 0]);
+  }
+
+  #[test]
+  fn clamps_changes_to_the_composition() {
+    let mut preedit = Preedit::default();
+    draw(&mut preedit, 0, 0, Some(("abc", &[0; 3])), 3);
+
+    draw(&mut preedit, 2, 10, Some(("d", &[0])), 10);
+    assert_eq!(last_update(&preedit), ("abd".to_string(), Some((3, 3))));
+
+    draw(&mut preedit, 10, 1, Some(("e", &[0])), -1);
+    assert_eq!(last_update(&preedit), ("abde".to_string(), Some((0, 0))));
+  }
+
+  #[test]
+  fn pads_missing_feedback() {
+    let mut preedit = Preedit::default();
+    draw(&mut preedit, 0, 0, Some(("abc", &[])), 0);
+    assert_eq!(preedit.feedback, [0, 0, 0]);
+
+    draw(&mut preedit, 0, 3, Some(("xy", &[XIM_REVERSE])), 0);
+    assert_eq!(preedit.feedback, [XIM_REVERSE, 0]);
+    assert_eq!(last_update(&preedit).1, Some((0, 1)));
+  }
+}
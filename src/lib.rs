@@ -1,8 +1,15 @@
 use std::borrow::Cow;
+use std::cell::Cell;
 use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Instant;
 
 use deno_core::error::bad_resource_id;
+use deno_core::error::not_supported;
+use deno_core::error::null_opbuf;
+use deno_core::error::type_error;
 use deno_core::error::AnyError;
+use deno_core::op_async;
 use deno_core::op_sync;
 use deno_core::serde::Deserialize;
 use deno_core::Extension;
@@ -11,23 +18,122 @@ use deno_core::Resource;
 use deno_core::ResourceId;
 use deno_core::ZeroCopyBuf;
 
+use attributes::WindowAttributes;
+use dpi::Unit;
+use dpi::UnitPosition;
+use dpi::UnitSize;
+use framebuffer::PixelFormat;
+use geometry::NormalBounds;
+use geometry::WindowGeometry;
+use gesture::GestureRecognizer;
+use handle::RawHandle;
 use helpers::hash;
+use icon::IconSize;
+use menu::MenuItem;
+use menu::MenuResource;
+use pointer_lock::PointerLock;
+use state::WindowState;
+use surface::ListenerId;
+use surface::SurfaceEvent;
+use surface::SurfaceListeners;
 use winit::dpi::PhysicalPosition;
 use winit::dpi::PhysicalSize;
 use winit::dpi::Position;
 use winit::dpi::Size;
+use winit::event::ModifiersState;
 use winit::event_loop::ControlFlow;
 use winit::event_loop::EventLoop;
 use winit::platform::run_return::EventLoopExtRunReturn;
 use winit::window::CursorIcon;
-use winit::window::Icon;
 use winit::window::Window;
+use winit::window::WindowId;
 
 use raw_window_handle::HasRawWindowHandle;
 use raw_window_handle::RawWindowHandle;
 
+pub mod api;
+mod attributes;
+mod canvas;
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+mod context_menu;
+#[cfg(feature = "image")]
+mod decode;
+mod dpi;
 mod event;
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+mod file_dialog;
+mod framebuffer;
+mod geometry;
+mod gesture;
+mod handle;
 mod helpers;
+mod icon;
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+mod ime;
+mod menu;
+mod message_box;
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+mod notification;
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+mod pen;
+mod pointer_lock;
+mod state;
+mod surface;
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+mod tray;
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+mod x11;
 
 use event::Event;
 
@@ -49,6 +155,171 @@ impl From<UserAttentionType> for winit::window::UserAttentionType {
   }
 }
 
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EventLoopOptions {
+  unit: Option<Unit>,
+  /// Whether pane talks to the input method server itself on X11, reporting
+  /// compositions as `ime` events.
+  #[serde(default)]
+  ime_events: bool,
+}
+
+#[derive(Deserialize)]
+struct WindowNewArgs {
+  rid: ResourceId,
+  #[serde(default)]
+  attributes: WindowAttributes,
+}
+
+#[derive(Deserialize)]
+struct WindowOpacityArgs {
+  rid: ResourceId,
+  opacity: f64,
+}
+
+#[derive(Deserialize)]
+struct MenuItemCheckedArgs {
+  rid: ResourceId,
+  id: String,
+  checked: bool,
+}
+
+#[derive(Deserialize)]
+struct MenuItemEnabledArgs {
+  rid: ResourceId,
+  id: String,
+  enabled: bool,
+}
+
+#[derive(Deserialize)]
+struct MenuPopupArgs {
+  rid: ResourceId,
+  window: ResourceId,
+  position: Position,
+}
+
+#[derive(Deserialize)]
+struct MessageBoxArgs {
+  rid: ResourceId,
+  #[serde(flatten)]
+  options: message_box::MessageBoxOptions,
+}
+
+#[derive(Deserialize)]
+struct WindowAcceleratorsArgs {
+  rid: ResourceId,
+  menu: Option<ResourceId>,
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+#[derive(Deserialize)]
+struct TrayArgs {
+  #[serde(default)]
+  title: String,
+  #[serde(default)]
+  tooltip: String,
+  sizes: Vec<IconSize>,
+  menu: Option<ResourceId>,
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+#[derive(Deserialize)]
+struct TrayIconArgs {
+  rid: ResourceId,
+  sizes: Vec<IconSize>,
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+#[derive(Deserialize)]
+struct TrayTooltipArgs {
+  rid: ResourceId,
+  tooltip: String,
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+#[derive(Deserialize)]
+struct TrayMenuArgs {
+  rid: ResourceId,
+  menu: Option<ResourceId>,
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+#[derive(Deserialize)]
+struct NotificationArgs {
+  #[serde(flatten)]
+  notification: notification::Notification,
+  icon: Option<IconSize>,
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+#[derive(Deserialize)]
+struct FileDialogArgs {
+  rid: ResourceId,
+  #[serde(flatten)]
+  options: file_dialog::FileDialogOptions,
+}
+
+#[derive(Deserialize)]
+struct WindowUnitArgs {
+  rid: ResourceId,
+  unit: Option<Unit>,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct WindowPositionArgs {
@@ -116,9 +387,15 @@ struct WindowAlwaysOnTopArgs {
 #[derive(Deserialize)]
 struct WindowIconArgs {
   rid: ResourceId,
-  rgba: Vec<u8>,
-  width: u32,
-  height: u32,
+  sizes: Vec<IconSize>,
+}
+
+#[cfg(feature = "image")]
+#[derive(Deserialize)]
+struct WindowIconImageArgs {
+  rid: ResourceId,
+  lengths: Vec<usize>,
+  format: Option<decode::ImageFormat>,
 }
 
 #[derive(Deserialize)]
@@ -128,6 +405,34 @@ struct WindowUserAttentionArgs {
   request_type: Option<UserAttentionType>,
 }
 
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type", content = "value")]
+enum ScaleFactorPolicy {
+  KeepLogicalSize,
+  KeepPhysicalSize,
+  Size(Size),
+}
+
+#[derive(Deserialize)]
+struct WindowScaleFactorPolicyArgs {
+  rid: ResourceId,
+  policy: ScaleFactorPolicy,
+}
+
+#[derive(Deserialize)]
+struct WindowGeometryArgs {
+  rid: ResourceId,
+  geometry: WindowGeometry,
+}
+
+#[derive(Deserialize)]
+struct WindowPresentArgs {
+  rid: ResourceId,
+  width: u32,
+  height: u32,
+  format: Option<PixelFormat>,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct WindowCursorIconArgs {
@@ -135,13 +440,49 @@ struct WindowCursorIconArgs {
   cursor: CursorIcon,
 }
 
+#[derive(Deserialize)]
+struct WindowImeAllowedArgs {
+  rid: ResourceId,
+  allowed: bool,
+}
+
+/// The field or caret text is entered in, relative to the top left of the
+/// client area.
+#[derive(Deserialize)]
+struct TextInputArea {
+  position: Position,
+  size: Size,
+}
+
+#[derive(Deserialize)]
+struct WindowTextInputArgs {
+  rid: ResourceId,
+  active: bool,
+  area: Option<TextInputArea>,
+}
+
+#[derive(Deserialize)]
+struct WindowGesturesArgs {
+  rid: ResourceId,
+  enabled: bool,
+}
+
+#[derive(Deserialize)]
+struct WindowPointerLockArgs {
+  rid: ResourceId,
+  locked: bool,
+}
+
 #[derive(Deserialize)]
 struct WindowCursorGrabArgs {
   rid: ResourceId,
   grab: bool,
 }
 
-pub struct EventLoopResource(pub RefCell<EventLoop<()>>);
+pub struct EventLoopResource {
+  pub event_loop: RefCell<EventLoop<()>>,
+  unit: Unit,
+}
 
 impl Resource for EventLoopResource {
   fn name(&self) -> Cow<str> {
@@ -149,15 +490,189 @@ impl Resource for EventLoopResource {
   }
 }
 
-pub struct WindowResource(pub Window);
+/// A pane window, see the `api` module for using it from other extensions.
+pub struct WindowResource {
+  window: Window,
+  surface_listeners: SurfaceListeners,
+  normal_bounds: NormalBounds,
+  scale_factor_policy: Cell<ScaleFactorPolicy>,
+  state: RefCell<WindowState>,
+  popup_parent: Option<WindowId>,
+  /// The menu whose accelerators are activated while the window is focused.
+  accelerators: RefCell<Option<(ResourceId, Rc<MenuResource>)>>,
+  modifiers: Cell<ModifiersState>,
+  /// Whether the window accepts text input, `ReceivedCharacter` events are
+  /// only emitted while it does.
+  text_input: Cell<bool>,
+  gestures: RefCell<Option<GestureRecognizer>>,
+  pointer_lock: PointerLock,
+}
 
 impl WindowResource {
   pub fn new(event_loop: &EventLoop<()>) -> Result<Self, AnyError> {
-    Ok(Self(winit::window::Window::new(event_loop)?))
+    Self::with_attributes(event_loop, &WindowAttributes::default(), None)
+  }
+
+  fn with_attributes(
+    event_loop: &EventLoop<()>,
+    attributes: &WindowAttributes,
+    owner: Option<&WindowResource>,
+  ) -> Result<Self, AnyError> {
+    let owner = owner.map(WindowResource::window);
+    let window = attributes.builder(event_loop, owner)?.build(event_loop)?;
+    attributes.apply(&window, owner)?;
+
+    Ok(Self {
+      popup_parent: owner.filter(|_| attributes.is_popup()).map(Window::id),
+      window,
+      surface_listeners: SurfaceListeners::default(),
+      normal_bounds: NormalBounds::default(),
+      scale_factor_policy: Cell::new(ScaleFactorPolicy::KeepLogicalSize),
+      state: RefCell::new(attributes.state()),
+      accelerators: RefCell::new(None),
+      modifiers: Cell::new(ModifiersState::empty()),
+      text_input: Cell::new(true),
+      gestures: RefCell::new(None),
+      pointer_lock: PointerLock::default(),
+    })
   }
 
+  /// The id of the window as seen by JavaScript, in events and `PaneWindow.id`.
   pub fn id(&self) -> u32 {
-    hash(self.0.id())
+    hash(self.window.id())
+  }
+
+  pub fn window(&self) -> &Window {
+    &self.window
+  }
+
+  /// Adds a listener that is called whenever surfaces created for this window
+  /// need to be reconfigured, and before the window is destroyed. Listeners are
+  /// called synchronously from `pane_event_loop_step`, before the event is
+  /// passed on to JavaScript, and must not add or remove listeners themselves.
+  pub fn add_surface_listener(
+    &self,
+    listener: impl Fn(&SurfaceEvent) + 'static,
+  ) -> ListenerId {
+    self.surface_listeners.add(Rc::new(listener))
+  }
+
+  /// Removes a listener, returning whether it was still registered.
+  pub fn remove_surface_listener(&self, id: ListenerId) -> bool {
+    self.surface_listeners.remove(id)
+  }
+
+  /// Handles a window event before it is passed on to JavaScript.
+  fn handle_event(&self, event: &mut winit::event::WindowEvent) {
+    if let winit::event::WindowEvent::ScaleFactorChanged {
+      scale_factor,
+      new_inner_size,
+    } = event
+    {
+      match self.scale_factor_policy.get() {
+        ScaleFactorPolicy::KeepLogicalSize => {}
+        ScaleFactorPolicy::KeepPhysicalSize => {
+          **new_inner_size = self.window.inner_size();
+        }
+        ScaleFactorPolicy::Size(size) => {
+          **new_inner_size = size.to_physical(*scale_factor);
+        }
+      }
+    }
+
+    if let winit::event::WindowEvent::ModifiersChanged(modifiers) = event {
+      self.modifiers.set(*modifiers);
+    }
+
+    self.normal_bounds.handle_event(&self.window, event);
+
+    self.surface_listeners.dispatch_window_event(
+      event,
+      || self.is_minimized(),
+      || self.window.inner_size(),
+    );
+  }
+
+  /// Whether the window is minimized, where the platform reveals it other than
+  /// by resizing the window to zero, which is only the case on X11.
+  fn is_minimized(&self) -> bool {
+    #[cfg(any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    ))]
+    if let Ok(Some(_)) = x11::XWindow::new(&self.window) {
+      let state = self.state.borrow().query(&self.window);
+      return state.map(|state| state.minimized).unwrap_or(false);
+    }
+
+    false
+  }
+
+  /// Activates the item of the accelerator table whose accelerator was
+  /// pressed.
+  fn activate_accelerator(
+    &self,
+    event: &winit::event::WindowEvent,
+  ) -> Option<Event> {
+    if let winit::event::WindowEvent::KeyboardInput {
+      input:
+        winit::event::KeyboardInput {
+          state: winit::event::ElementState::Pressed,
+          virtual_keycode: Some(key),
+          ..
+        },
+      is_synthetic: false,
+      ..
+    } = event
+    {
+      let accelerators = self.accelerators.borrow();
+      let (menu_rid, menu) = accelerators.as_ref()?;
+      let activation = menu.activate_accelerator(self.modifiers.get(), *key)?;
+
+      return Some(activation.into_event(self.id(), *menu_rid));
+    }
+
+    None
+  }
+
+  /// Returns the gestures recognized from a touch, if gestures are enabled.
+  fn recognize_gestures(
+    &self,
+    event: &winit::event::WindowEvent,
+  ) -> Vec<Event> {
+    let mut gestures = self.gestures.borrow_mut();
+
+    match (gestures.as_mut(), event) {
+      (Some(gestures), winit::event::WindowEvent::Touch(touch)) => gestures
+        .handle_touch(touch, self.window.scale_factor(), Instant::now())
+        .into_iter()
+        .map(|gesture| self.gesture_event(gesture))
+        .collect(),
+      _ => Vec::new(),
+    }
+  }
+
+  /// Returns a long press once one has been held long enough.
+  fn poll_gestures(&self, now: Instant) -> Option<Event> {
+    let gesture = self.gestures.borrow_mut().as_mut()?.poll(now)?;
+
+    Some(self.gesture_event(gesture))
+  }
+
+  fn gesture_event(&self, gesture: gesture::Gesture) -> Event {
+    Event::WindowEvent {
+      window_id: self.id(),
+      event: event::WindowEvent::Gesture(gesture),
+    }
+  }
+}
+
+impl Drop for WindowResource {
+  fn drop(&mut self) {
+    self.surface_listeners.dispatch(&SurfaceEvent::Destroying);
   }
 }
 
@@ -171,95 +686,295 @@ impl Resource for WindowResource {
 // `raw_window_handle` function
 unsafe impl HasRawWindowHandle for WindowResource {
   fn raw_window_handle(&self) -> RawWindowHandle {
-    self.0.raw_window_handle()
+    self.window.raw_window_handle()
+  }
+}
+
+/// Finds the window resource with the given winit id.
+fn find_window(state: &OpState, id: WindowId) -> Option<Rc<WindowResource>> {
+  state
+    .resource_table
+    .names()
+    .filter_map(|(rid, _)| state.resource_table.get::<WindowResource>(rid))
+    .find(|window| window.window.id() == id)
+}
+
+/// Finds the window the pointer is locked to, if any.
+fn locked_window(state: &OpState) -> Option<Rc<WindowResource>> {
+  state
+    .resource_table
+    .names()
+    .filter_map(|(rid, _)| state.resource_table.get::<WindowResource>(rid))
+    .find(|window| window.pointer_lock.is_engaged())
+}
+
+/// Returns the event loop, there can only be one per process.
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+fn find_event_loop(state: &OpState) -> Option<Rc<EventLoopResource>> {
+  state
+    .resource_table
+    .names()
+    .find_map(|(rid, _)| state.resource_table.get::<EventLoopResource>(rid))
+}
+
+/// Returns the window a popup was opened from, following the parents of
+/// nested popups.
+fn popup_root(state: &OpState, id: WindowId) -> WindowId {
+  match find_window(state, id).and_then(|window| window.popup_parent) {
+    Some(parent) => popup_root(state, parent),
+    None => id,
+  }
+}
+
+/// Closes the popups of a window, and the popups of those popups, emitting a
+/// `PopupClosed` event for each of them.
+fn close_popups(
+  state: &mut OpState,
+  parent: WindowId,
+  events: &mut Vec<Event>,
+) {
+  let popups: Vec<_> = state
+    .resource_table
+    .names()
+    .filter_map(|(rid, _)| {
+      let window = state.resource_table.get::<WindowResource>(rid)?;
+      (window.popup_parent == Some(parent)).then(|| (rid, window.window.id()))
+    })
+    .collect();
+
+  for (rid, id) in popups {
+    close_popups(state, id, events);
+    events.push(Event::WindowEvent {
+      window_id: hash(id),
+      event: event::WindowEvent::PopupClosed,
+    });
+    state.resource_table.close(rid);
   }
 }
 
 #[cfg(feature = "init")]
 #[no_mangle]
 fn init() -> Extension {
-  Extension::builder()
-    .ops(vec![
-      ("pane_event_loop_new", op_sync(event_loop_new)),
-      ("pane_event_loop_step", op_sync(event_loop_step)),
-      ("pane_window_new", op_sync(window_new)),
-      ("pane_window_id", op_sync(window_id)),
-      ("pane_window_scale_factor", op_sync(window_scale_factor)),
-      ("pane_window_request_redraw", op_sync(window_request_redraw)),
-      ("pane_window_inner_position", op_sync(window_inner_position)),
-      ("pane_window_outer_position", op_sync(window_outer_position)),
-      (
-        "pane_window_set_outer_position",
-        op_sync(window_set_outer_position),
-      ),
-      ("pane_window_inner_size", op_sync(window_inner_size)),
-      ("pane_window_set_inner_size", op_sync(window_set_inner_size)),
-      ("pane_window_outer_size", op_sync(window_outer_size)),
-      (
-        "pane_window_set_min_inner_size",
-        op_sync(window_set_min_inner_size),
-      ),
-      (
-        "pane_window_set_max_inner_size",
-        op_sync(window_set_max_inner_size),
-      ),
-      ("pane_window_set_title", op_sync(window_set_title)),
-      ("pane_window_set_visible", op_sync(window_set_visible)),
-      ("pane_window_set_resizable", op_sync(window_set_resizable)),
-      ("pane_window_set_minimized", op_sync(window_set_minimized)),
-      ("pane_window_set_maximized", op_sync(window_set_maximized)),
-      ("pane_window_is_maximized", op_sync(window_is_maximized)),
-      (
-        "pane_window_set_decorations",
-        op_sync(window_set_decorations),
-      ),
-      (
-        "pane_window_set_always_on_top",
-        op_sync(window_set_always_on_top),
-      ),
-      (
-        "pane_window_set_window_icon",
-        op_sync(window_set_window_icon),
-      ),
-      (
-        "pane_window_set_ime_position",
-        op_sync(window_set_ime_position),
-      ),
-      (
-        "pane_window_request_user_attention",
-        op_sync(window_request_user_attention),
-      ),
-      (
-        "pane_window_set_cursor_icon",
-        op_sync(window_set_cursor_icon),
-      ),
-      (
-        "pane_window_set_cursor_position",
-        op_sync(window_set_cursor_position),
-      ),
-      (
-        "pane_window_set_cursor_grab",
-        op_sync(window_set_cursor_grab),
-      ),
-      (
-        "pane_window_set_cursor_visible",
-        op_sync(window_set_cursor_visible),
-      ),
-      ("pane_window_drag_window", op_sync(window_drag_window)),
-    ])
-    .build()
+  let mut extension = Extension::builder();
+
+  extension.ops(vec![
+    ("pane_event_loop_new", op_sync(event_loop_new)),
+    ("pane_event_loop_step", op_sync(event_loop_step)),
+    ("pane_window_new", op_sync(window_new)),
+    ("pane_window_id", op_sync(window_id)),
+    ("pane_window_scale_factor", op_sync(window_scale_factor)),
+    ("pane_window_request_redraw", op_sync(window_request_redraw)),
+    ("pane_window_inner_position", op_sync(window_inner_position)),
+    ("pane_window_outer_position", op_sync(window_outer_position)),
+    (
+      "pane_window_set_outer_position",
+      op_sync(window_set_outer_position),
+    ),
+    ("pane_window_inner_size", op_sync(window_inner_size)),
+    ("pane_window_set_inner_size", op_sync(window_set_inner_size)),
+    ("pane_window_outer_size", op_sync(window_outer_size)),
+    (
+      "pane_window_set_min_inner_size",
+      op_sync(window_set_min_inner_size),
+    ),
+    (
+      "pane_window_set_max_inner_size",
+      op_sync(window_set_max_inner_size),
+    ),
+    ("pane_window_set_title", op_sync(window_set_title)),
+    ("pane_window_set_visible", op_sync(window_set_visible)),
+    ("pane_window_set_resizable", op_sync(window_set_resizable)),
+    ("pane_window_set_minimized", op_sync(window_set_minimized)),
+    ("pane_window_set_maximized", op_sync(window_set_maximized)),
+    ("pane_window_is_maximized", op_sync(window_is_maximized)),
+    ("pane_window_state", op_sync(window_state)),
+    ("pane_window_geometry", op_sync(window_geometry)),
+    (
+      "pane_window_restore_geometry",
+      op_sync(window_restore_geometry),
+    ),
+    (
+      "pane_window_set_scale_factor_policy",
+      op_sync(window_set_scale_factor_policy),
+    ),
+    (
+      "pane_window_set_decorations",
+      op_sync(window_set_decorations),
+    ),
+    (
+      "pane_window_set_always_on_top",
+      op_sync(window_set_always_on_top),
+    ),
+    ("pane_window_set_opacity", op_sync(window_set_opacity)),
+    (
+      "pane_window_set_window_icon",
+      op_sync(window_set_window_icon),
+    ),
+    (
+      "pane_window_set_ime_position",
+      op_sync(window_set_ime_position),
+    ),
+    (
+      "pane_window_set_ime_allowed",
+      op_sync(window_set_ime_allowed),
+    ),
+    ("pane_window_set_text_input", op_sync(window_set_text_input)),
+    ("pane_window_set_gestures", op_sync(window_set_gestures)),
+    (
+      "pane_window_set_pointer_lock",
+      op_sync(window_set_pointer_lock),
+    ),
+    (
+      "pane_window_request_user_attention",
+      op_sync(window_request_user_attention),
+    ),
+    (
+      "pane_window_set_cursor_icon",
+      op_sync(window_set_cursor_icon),
+    ),
+    (
+      "pane_window_set_cursor_position",
+      op_sync(window_set_cursor_position),
+    ),
+    (
+      "pane_window_set_cursor_grab",
+      op_sync(window_set_cursor_grab),
+    ),
+    (
+      "pane_window_set_cursor_visible",
+      op_sync(window_set_cursor_visible),
+    ),
+    ("pane_window_drag_window", op_sync(window_drag_window)),
+    ("pane_window_present", op_sync(window_present)),
+    ("pane_window_capture", op_sync(window_capture)),
+    ("pane_window_raw_handle", op_sync(window_raw_handle)),
+    (
+      "pane_window_set_accelerators",
+      op_sync(window_set_accelerators),
+    ),
+    ("pane_menu_new", op_sync(menu_new)),
+    ("pane_menu_set_checked", op_sync(menu_set_checked)),
+    ("pane_menu_set_enabled", op_sync(menu_set_enabled)),
+    ("pane_menu_popup", op_sync(menu_popup)),
+    ("pane_window_message_box", op_async(window_message_box)),
+  ]);
+
+  #[cfg(all(
+    feature = "freedesktop",
+    any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    )
+  ))]
+  extension.ops(vec![
+    ("pane_tray_new", op_sync(tray_new)),
+    ("pane_tray_set_icon", op_sync(tray_set_icon)),
+    ("pane_tray_set_tooltip", op_sync(tray_set_tooltip)),
+    ("pane_tray_set_menu", op_sync(tray_set_menu)),
+    ("pane_notification_show", op_sync(notification_show)),
+    ("pane_notification_close", op_sync(notification_close)),
+    (
+      "pane_window_open_file_dialog",
+      op_async(window_open_file_dialog),
+    ),
+    (
+      "pane_window_open_files_dialog",
+      op_async(window_open_files_dialog),
+    ),
+    (
+      "pane_window_open_directory_dialog",
+      op_async(window_open_directory_dialog),
+    ),
+    (
+      "pane_window_save_file_dialog",
+      op_async(window_save_file_dialog),
+    ),
+  ]);
+
+  #[cfg(feature = "image")]
+  extension.ops(vec![(
+    "pane_window_set_window_icon_image",
+    op_sync(window_set_window_icon_image),
+  )]);
+
+  extension.build()
 }
 
 fn event_loop_new(
   state: &mut OpState,
-  _args: (),
+  args: Option<EventLoopOptions>,
   _zero_copy: Option<ZeroCopyBuf>,
 ) -> Result<ResourceId, AnyError> {
-  Ok(
-    state
-      .resource_table
-      .add(EventLoopResource(RefCell::new(EventLoop::new()))),
-  )
+  let args = args.unwrap_or_default();
+
+  // Pane takes over the input method server on X11 if asked to, to report
+  // compositions.
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  let modifiers = if args.ime_events {
+    ime::redirect_xmodifiers()
+  } else {
+    None
+  };
+
+  let event_loop = EventLoop::new();
+
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  if let Some(modifiers) = modifiers {
+    use winit::platform::unix::EventLoopWindowTargetExtUnix;
+
+    ime::restore_xmodifiers(&modifiers);
+    // Winit falls back to the local input method as well when the server
+    // can not be reached.
+    if event_loop.is_x11() {
+      if let Ok(input_method) = ime::InputMethod::open(&modifiers) {
+        state.put(input_method);
+      }
+    }
+  }
+
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  {
+    use winit::platform::unix::EventLoopWindowTargetExtUnix;
+
+    // Tablets are optional, without XInput2 there are no pen events.
+    if event_loop.is_x11() {
+      if let Ok(pen_input) = pen::PenInput::open() {
+        state.put(pen_input);
+      }
+    }
+  }
+
+  Ok(state.resource_table.add(EventLoopResource {
+    event_loop: RefCell::new(event_loop),
+    unit: args.unit.unwrap_or(Unit::Physical),
+  }))
 }
 
 fn event_loop_step(
@@ -273,30 +988,255 @@ fn event_loop_step(
     .ok_or_else(bad_resource_id)?;
   let mut events = Vec::new();
 
-  event_loop
-    .0
-    .borrow_mut()
-    .run_return(|event, _, control_flow| {
+  #[cfg(all(
+    feature = "freedesktop",
+    any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    )
+  ))]
+  tray::process_trays(state, &mut events);
+  #[cfg(all(
+    feature = "freedesktop",
+    any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    )
+  ))]
+  notification::process_notifications(state, &mut events);
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  ime::process_input_method(state, &mut events);
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  pen::process_pen_input(state, &mut events);
+
+  // Long presses are recognized by time, rather than by a touch event.
+  let now = Instant::now();
+  events.extend(
+    state
+      .resource_table
+      .names()
+      .filter_map(|(rid, _)| state.resource_table.get::<WindowResource>(rid))
+      .filter_map(|window| window.poll_gestures(now)),
+  );
+
+  let mut result = Ok(());
+  let mut unfocused = Vec::new();
+  let mut focused = None;
+
+  event_loop.event_loop.borrow_mut().run_return(
+    |mut event, target, control_flow| {
       *control_flow = ControlFlow::Exit;
-      events.push(Event::from(event));
-    });
 
-  Ok(events)
+      #[cfg(any(
+        target_os = "linux",
+        target_os = "dragonfly",
+        target_os = "freebsd",
+        target_os = "netbsd",
+        target_os = "openbsd"
+      ))]
+      if let Some(context_menus) =
+        state.try_borrow_mut::<context_menu::ContextMenus>()
+      {
+        match context_menus.handle_event(target, &event, &mut events) {
+          Ok(true) => return,
+          Ok(false) => {}
+          Err(error) => {
+            result = Err(error);
+            return;
+          }
+        }
+      }
+
+      if let Some(message_boxes) =
+        state.try_borrow_mut::<message_box::MessageBoxes>()
+      {
+        match message_boxes.handle_event(&event) {
+          Ok(true) => return,
+          Ok(false) => {}
+          Err(error) => {
+            result = Err(error);
+            return;
+          }
+        }
+      }
+
+      let mut window = None;
+      let mut menu_event = None;
+      let mut gesture_events = Vec::new();
+      let mut pointer_motion = None;
+      if let winit::event::Event::WindowEvent {
+        window_id,
+        event: ref mut window_event,
+      } = event
+      {
+        window = find_window(state, window_id);
+
+        if let Some(window) = &window {
+          if is_unwanted_text(window.text_input.get(), window_event) {
+            return;
+          }
+        }
+
+        // The input method reports the text of windows with IME enabled, and
+        // consumes the keys of compositions.
+        #[cfg(any(
+          target_os = "linux",
+          target_os = "dragonfly",
+          target_os = "freebsd",
+          target_os = "netbsd",
+          target_os = "openbsd"
+        ))]
+        if let (Some(input_method), Some(window)) =
+          (state.try_borrow::<ime::InputMethod>(), &window)
+        {
+          let ignored = match window_event {
+            winit::event::WindowEvent::ReceivedCharacter(_) => {
+              input_method.is_enabled(window.window())
+            }
+            winit::event::WindowEvent::KeyboardInput { .. } => {
+              input_method.is_composing(window.window())
+            }
+            _ => false,
+          };
+          if ignored {
+            return;
+          }
+        }
+
+        if let Some(window) = &window {
+          if window.pointer_lock.handle_event(
+            &window.window,
+            &window.state.borrow(),
+            window_event,
+          ) {
+            return;
+          }
+
+          window.handle_event(window_event);
+          menu_event = window.activate_accelerator(window_event);
+          gesture_events = window.recognize_gestures(window_event);
+        }
+        match window_event {
+          winit::event::WindowEvent::Focused(false) => {
+            unfocused.push(window_id)
+          }
+          winit::event::WindowEvent::Focused(true) => focused = Some(window_id),
+          _ => {}
+        }
+      }
+
+      if let winit::event::Event::DeviceEvent {
+        event: winit::event::DeviceEvent::MouseMotion { delta },
+        ..
+      } = event
+      {
+        pointer_motion =
+          locked_window(state).map(|window| Event::WindowEvent {
+            window_id: window.id(),
+            event: event::WindowEvent::PointerMotion { delta },
+          });
+      }
+
+      let event = match (Event::from(event), window) {
+        (Event::WindowEvent { window_id, event }, Some(window)) => {
+          Event::WindowEvent {
+            window_id,
+            event: event
+              .into_unit(event_loop.unit, window.window.scale_factor()),
+          }
+        }
+        (event, _) => event,
+      };
+
+      events.push(event);
+      events.extend(menu_event);
+      events.extend(gesture_events);
+      events.extend(pointer_motion);
+    },
+  );
+
+  // Popups are closed once focus moves away from them and their parent. On
+  // most platforms popups take focus, so it is only known where focus went
+  // once all events of the step are handled.
+  let focused_root = focused.map(|id| popup_root(state, id));
+  for id in unfocused {
+    let root = popup_root(state, id);
+    if focused_root != Some(root) {
+      close_popups(state, root, &mut events);
+    }
+  }
+
+  result.map(|_| events)
 }
 
 fn window_new(
   state: &mut OpState,
-  rid: ResourceId,
+  args: WindowNewArgs,
   _zero_copy: Option<ZeroCopyBuf>,
 ) -> Result<ResourceId, AnyError> {
   let event_loop = state
     .resource_table
-    .get::<EventLoopResource>(rid)
+    .get::<EventLoopResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  let event_loop = event_loop.0.borrow_mut();
+  let owner = args
+    .attributes
+    .owner()
+    .map(|rid| {
+      state
+        .resource_table
+        .get::<WindowResource>(rid)
+        .ok_or_else(bad_resource_id)
+    })
+    .transpose()?;
+
+  let event_loop = event_loop.event_loop.borrow_mut();
+  let window = WindowResource::with_attributes(
+    &event_loop,
+    &args.attributes,
+    owner.as_deref(),
+  )?;
+
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  if let Some(input_method) = state.try_borrow_mut::<ime::InputMethod>() {
+    input_method.set_enabled(window.window(), true)?;
+  }
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  if let Some(pen_input) = state.try_borrow_mut::<pen::PenInput>() {
+    pen_input.add_window(window.window());
+  }
 
-  Ok(state.resource_table.add(WindowResource::new(&event_loop)?))
+  Ok(state.resource_table.add(window))
 }
 
 fn window_id(
@@ -322,7 +1262,7 @@ fn window_scale_factor(
     .get::<WindowResource>(rid)
     .ok_or_else(bad_resource_id)?;
 
-  Ok(window.0.scale_factor())
+  Ok(window.window().scale_factor())
 }
 
 fn window_request_redraw(
@@ -335,35 +1275,43 @@ fn window_request_redraw(
     .get::<WindowResource>(rid)
     .ok_or_else(bad_resource_id)?;
 
-  window.0.request_redraw();
+  window.window().request_redraw();
 
   Ok(())
 }
 
 fn window_inner_position(
   state: &mut OpState,
-  rid: ResourceId,
+  args: WindowUnitArgs,
   _zero_copy: Option<ZeroCopyBuf>,
-) -> Result<PhysicalPosition<i32>, AnyError> {
+) -> Result<UnitPosition<i32>, AnyError> {
   let window = state
     .resource_table
-    .get::<WindowResource>(rid)
+    .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  Ok(window.0.inner_position()?)
+  Ok(UnitPosition::new(
+    window.window().inner_position()?,
+    args.unit.unwrap_or(Unit::Physical),
+    window.window().scale_factor(),
+  ))
 }
 
 fn window_outer_position(
   state: &mut OpState,
-  rid: ResourceId,
+  args: WindowUnitArgs,
   _zero_copy: Option<ZeroCopyBuf>,
-) -> Result<PhysicalPosition<i32>, AnyError> {
+) -> Result<UnitPosition<i32>, AnyError> {
   let window = state
     .resource_table
-    .get::<WindowResource>(rid)
+    .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  Ok(window.0.outer_position()?)
+  Ok(UnitPosition::new(
+    window.window().outer_position()?,
+    args.unit.unwrap_or(Unit::Physical),
+    window.window().scale_factor(),
+  ))
 }
 
 fn window_set_outer_position(
@@ -376,22 +1324,26 @@ fn window_set_outer_position(
     .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  window.0.set_outer_position(args.position);
+  window.window().set_outer_position(args.position);
 
   Ok(())
 }
 
 fn window_inner_size(
   state: &mut OpState,
-  rid: ResourceId,
+  args: WindowUnitArgs,
   _zero_copy: Option<ZeroCopyBuf>,
-) -> Result<PhysicalSize<u32>, AnyError> {
+) -> Result<UnitSize<u32>, AnyError> {
   let window = state
     .resource_table
-    .get::<WindowResource>(rid)
+    .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  Ok(window.0.inner_size())
+  Ok(UnitSize::new(
+    window.window().inner_size(),
+    args.unit.unwrap_or(Unit::Physical),
+    window.window().scale_factor(),
+  ))
 }
 
 fn window_set_inner_size(
@@ -404,22 +1356,26 @@ fn window_set_inner_size(
     .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  window.0.set_inner_size(args.size);
+  window.window().set_inner_size(args.size);
 
   Ok(())
 }
 
 fn window_outer_size(
   state: &mut OpState,
-  rid: ResourceId,
+  args: WindowUnitArgs,
   _zero_copy: Option<ZeroCopyBuf>,
-) -> Result<PhysicalSize<u32>, AnyError> {
+) -> Result<UnitSize<u32>, AnyError> {
   let window = state
     .resource_table
-    .get::<WindowResource>(rid)
+    .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  Ok(window.0.outer_size())
+  Ok(UnitSize::new(
+    window.window().outer_size(),
+    args.unit.unwrap_or(Unit::Physical),
+    window.window().scale_factor(),
+  ))
 }
 
 fn window_set_min_inner_size(
@@ -432,7 +1388,8 @@ fn window_set_min_inner_size(
     .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  window.0.set_min_inner_size(args.size);
+  window.window().set_min_inner_size(args.size);
+  window.state.borrow_mut().min_inner_size = args.size;
 
   Ok(())
 }
@@ -447,7 +1404,8 @@ fn window_set_max_inner_size(
     .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  window.0.set_max_inner_size(args.size);
+  window.window().set_max_inner_size(args.size);
+  window.state.borrow_mut().max_inner_size = args.size;
 
   Ok(())
 }
@@ -462,7 +1420,8 @@ fn window_set_title(
     .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  window.0.set_title(&args.title);
+  window.window().set_title(&args.title);
+  window.state.borrow_mut().title = args.title;
 
   Ok(())
 }
@@ -477,7 +1436,8 @@ fn window_set_visible(
     .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  window.0.set_visible(args.visible);
+  window.window().set_visible(args.visible);
+  window.state.borrow_mut().visible = args.visible;
 
   Ok(())
 }
@@ -492,7 +1452,8 @@ fn window_set_resizable(
     .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  window.0.set_resizable(args.resizable);
+  window.window().set_resizable(args.resizable);
+  window.state.borrow_mut().resizable = args.resizable;
 
   Ok(())
 }
@@ -507,7 +1468,8 @@ fn window_set_minimized(
     .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  window.0.set_minimized(args.minimized);
+  window.window().set_minimized(args.minimized);
+  window.state.borrow_mut().minimized = args.minimized;
 
   Ok(())
 }
@@ -522,7 +1484,8 @@ fn window_set_maximized(
     .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  window.0.set_maximized(args.maximized);
+  window.window().set_maximized(args.maximized);
+  window.state.borrow_mut().maximized = args.maximized;
 
   Ok(())
 }
@@ -537,42 +1500,39 @@ fn window_is_maximized(
     .get::<WindowResource>(rid)
     .ok_or_else(bad_resource_id)?;
 
-  Ok(window.0.is_maximized())
+  Ok(window.window().is_maximized())
 }
 
-fn window_set_decorations(
+fn window_state(
   state: &mut OpState,
-  args: WindowDecorationsArgs,
+  rid: ResourceId,
   _zero_copy: Option<ZeroCopyBuf>,
-) -> Result<(), AnyError> {
+) -> Result<WindowState, AnyError> {
   let window = state
     .resource_table
-    .get::<WindowResource>(args.rid)
+    .get::<WindowResource>(rid)
     .ok_or_else(bad_resource_id)?;
 
-  window.0.set_decorations(args.decorations);
-
-  Ok(())
+  let window_state = window.state.borrow().query(window.window());
+  window_state
 }
 
-fn window_set_always_on_top(
+fn window_geometry(
   state: &mut OpState,
-  args: WindowAlwaysOnTopArgs,
+  rid: ResourceId,
   _zero_copy: Option<ZeroCopyBuf>,
-) -> Result<(), AnyError> {
+) -> Result<WindowGeometry, AnyError> {
   let window = state
     .resource_table
-    .get::<WindowResource>(args.rid)
+    .get::<WindowResource>(rid)
     .ok_or_else(bad_resource_id)?;
 
-  window.0.set_always_on_top(args.always_on_top);
-
-  Ok(())
+  Ok(WindowGeometry::new(window.window(), &window.normal_bounds))
 }
 
-fn window_set_window_icon(
+fn window_restore_geometry(
   state: &mut OpState,
-  args: WindowIconArgs,
+  args: WindowGeometryArgs,
   _zero_copy: Option<ZeroCopyBuf>,
 ) -> Result<(), AnyError> {
   let window = state
@@ -580,15 +1540,18 @@ fn window_set_window_icon(
     .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  window
-    .0
-    .set_window_icon(Icon::from_rgba(args.rgba, args.width, args.height).ok());
+  args.geometry.restore(window.window());
+
+  let mut window_state = window.state.borrow_mut();
+  window_state.maximized = args.geometry.maximized;
+  window_state.fullscreen = args.geometry.fullscreen;
+
   Ok(())
 }
 
-fn window_set_ime_position(
+fn window_set_scale_factor_policy(
   state: &mut OpState,
-  args: WindowPositionArgs,
+  args: WindowScaleFactorPolicyArgs,
   _zero_copy: Option<ZeroCopyBuf>,
 ) -> Result<(), AnyError> {
   let window = state
@@ -596,14 +1559,14 @@ fn window_set_ime_position(
     .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  window.0.set_ime_position(args.position);
+  window.scale_factor_policy.set(args.policy);
 
   Ok(())
 }
 
-fn window_request_user_attention(
+fn window_set_decorations(
   state: &mut OpState,
-  args: WindowUserAttentionArgs,
+  args: WindowDecorationsArgs,
   _zero_copy: Option<ZeroCopyBuf>,
 ) -> Result<(), AnyError> {
   let window = state
@@ -611,18 +1574,15 @@ fn window_request_user_attention(
     .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  window.0.request_user_attention(
-    args
-      .request_type
-      .map(winit::window::UserAttentionType::from),
-  );
+  window.window().set_decorations(args.decorations);
+  window.state.borrow_mut().decorations = args.decorations;
 
   Ok(())
 }
 
-fn window_set_cursor_icon(
+fn window_set_always_on_top(
   state: &mut OpState,
-  args: WindowCursorIconArgs,
+  args: WindowAlwaysOnTopArgs,
   _zero_copy: Option<ZeroCopyBuf>,
 ) -> Result<(), AnyError> {
   let window = state
@@ -630,22 +1590,287 @@ fn window_set_cursor_icon(
     .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  window.0.set_cursor_icon(args.cursor);
+  window.window().set_always_on_top(args.always_on_top);
+  window.state.borrow_mut().always_on_top = args.always_on_top;
 
   Ok(())
 }
 
-fn window_set_cursor_position(
+#[allow(unused_variables)]
+fn window_set_opacity(
   state: &mut OpState,
-  args: WindowPositionArgs,
+  args: WindowOpacityArgs,
   _zero_copy: Option<ZeroCopyBuf>,
 ) -> Result<(), AnyError> {
+  // NaN would pass through the clamp and hide the window.
+  if !args.opacity.is_finite() {
+    return Err(type_error("The opacity must be a finite number"));
+  }
+
   let window = state
     .resource_table
     .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  Ok(window.0.set_cursor_position(args.position)?)
+  let opacity = args.opacity.clamp(0.0, 1.0);
+
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  if let Some(x_window) = x11::XWindow::new(window.window())? {
+    // Compositors read the opacity as a fraction of the largest 32 bit value.
+    let opacity_cardinal = (opacity * u32::MAX as f64).round() as u32;
+    x_window.set_cardinals("_NET_WM_WINDOW_OPACITY", &[opacity_cardinal as _]);
+    window.state.borrow_mut().opacity = opacity;
+
+    return Ok(());
+  }
+
+  Err(not_supported())
+}
+
+fn window_set_window_icon(
+  state: &mut OpState,
+  args: WindowIconArgs,
+  zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let window = state
+    .resource_table
+    .get::<WindowResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  let icons = if args.sizes.is_empty() {
+    Vec::new()
+  } else {
+    let zero_copy = zero_copy.as_deref().ok_or_else(null_opbuf)?;
+    icon::split_rgba(zero_copy, &args.sizes)?
+  };
+
+  icon::set_window_icons(window.window(), &icons)
+}
+
+#[cfg(feature = "image")]
+fn window_set_window_icon_image(
+  state: &mut OpState,
+  args: WindowIconImageArgs,
+  zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let window = state
+    .resource_table
+    .get::<WindowResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  let images = if args.lengths.is_empty() {
+    Vec::new()
+  } else {
+    let zero_copy = zero_copy.as_deref().ok_or_else(null_opbuf)?;
+    decode::decode_all(zero_copy, &args.lengths, args.format)?
+  };
+  let icons: Vec<_> = images.iter().map(icon::RgbaIcon::from).collect();
+
+  icon::set_window_icons(window.window(), &icons)
+}
+
+fn window_set_ime_position(
+  state: &mut OpState,
+  args: WindowPositionArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let window = state
+    .resource_table
+    .get::<WindowResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  let position = args
+    .position
+    .to_physical::<i32>(window.window().scale_factor());
+  set_ime_spot(state, &window, position);
+
+  Ok(())
+}
+
+/// Moves the candidate window of the input method, for winit and the input
+/// method server pane talks to on X11.
+#[allow(unused_variables)]
+fn set_ime_spot(
+  state: &OpState,
+  window: &WindowResource,
+  position: PhysicalPosition<i32>,
+) {
+  window.window().set_ime_position(position);
+
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  if let Some(input_method) = state.try_borrow::<ime::InputMethod>() {
+    input_method.set_spot(window.window(), position.x, position.y);
+  }
+}
+
+/// Turns IME on or off for a window, for example off for game canvases which
+/// want raw key presses. IME is on by default.
+#[allow(unused_variables)]
+fn window_set_ime_allowed(
+  state: &mut OpState,
+  args: WindowImeAllowedArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let window = state
+    .resource_table
+    .get::<WindowResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  {
+    if let Some(input_method) = state.try_borrow_mut::<ime::InputMethod>() {
+      if input_method.set_enabled(window.window(), args.allowed)? {
+        input_method.set_text_input(window.window(), window.text_input.get());
+        return Ok(());
+      }
+    }
+
+    // Without an input method server there is nothing to turn off.
+    if x11::XWindow::new(window.window())?.is_some() {
+      return Ok(());
+    }
+  }
+
+  Err(not_supported())
+}
+
+/// Whether an event is text entered into a window that is not accepting text
+/// input, which is not reported.
+fn is_unwanted_text(
+  text_input: bool,
+  event: &winit::event::WindowEvent,
+) -> bool {
+  !text_input
+    && matches!(event, winit::event::WindowEvent::ReceivedCharacter(_))
+}
+
+/// Starts or ends text input for a window, optionally moving the candidate
+/// window of the input method below the area text is entered in.
+fn window_set_text_input(
+  state: &mut OpState,
+  args: WindowTextInputArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let window = state
+    .resource_table
+    .get::<WindowResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  window.text_input.set(args.active);
+
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  if let Some(input_method) = state.try_borrow_mut::<ime::InputMethod>() {
+    input_method.set_text_input(window.window(), args.active);
+  }
+
+  // Input methods place their candidate window below the spot, so it is put
+  // at the bottom left of the area to keep the area uncovered.
+  if let Some(area) = args.area {
+    let scale_factor = window.window().scale_factor();
+    let position = area.position.to_physical::<i32>(scale_factor);
+    let size = area.size.to_physical::<i32>(scale_factor);
+
+    set_ime_spot(
+      state,
+      &window,
+      PhysicalPosition::new(position.x, position.y + size.height),
+    );
+  }
+
+  Ok(())
+}
+
+/// Enables or disables gesture recognition for a window, gestures are
+/// reported next to the touches they are recognized from.
+fn window_set_gestures(
+  state: &mut OpState,
+  args: WindowGesturesArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let window = state
+    .resource_table
+    .get::<WindowResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  let mut gestures = window.gestures.borrow_mut();
+  if !args.enabled {
+    *gestures = None;
+  } else if gestures.is_none() {
+    *gestures = Some(GestureRecognizer::default());
+  }
+
+  Ok(())
+}
+
+fn window_request_user_attention(
+  state: &mut OpState,
+  args: WindowUserAttentionArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let window = state
+    .resource_table
+    .get::<WindowResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  window.window().request_user_attention(
+    args
+      .request_type
+      .map(winit::window::UserAttentionType::from),
+  );
+
+  Ok(())
+}
+
+fn window_set_cursor_icon(
+  state: &mut OpState,
+  args: WindowCursorIconArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let window = state
+    .resource_table
+    .get::<WindowResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  window.window().set_cursor_icon(args.cursor);
+
+  Ok(())
+}
+
+fn window_set_cursor_position(
+  state: &mut OpState,
+  args: WindowPositionArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let window = state
+    .resource_table
+    .get::<WindowResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  Ok(window.window().set_cursor_position(args.position)?)
 }
 
 fn window_set_cursor_grab(
@@ -658,7 +1883,13 @@ fn window_set_cursor_grab(
     .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  Ok(window.0.set_cursor_grab(args.grab)?)
+  // The cursor is restored once the pointer lock is released.
+  if !window.pointer_lock.is_engaged() {
+    window.window().set_cursor_grab(args.grab)?;
+  }
+  window.state.borrow_mut().cursor_grab = args.grab;
+
+  Ok(())
 }
 
 fn window_set_cursor_visible(
@@ -671,11 +1902,33 @@ fn window_set_cursor_visible(
     .get::<WindowResource>(args.rid)
     .ok_or_else(bad_resource_id)?;
 
-  window.0.set_cursor_visible(args.visible);
+  if !window.pointer_lock.is_engaged() {
+    window.window().set_cursor_visible(args.visible);
+  }
+  window.state.borrow_mut().cursor_visible = args.visible;
 
   Ok(())
 }
 
+/// Locks the pointer to the window, or unlocks it. While locked the cursor is
+/// hidden and kept in the middle of the window, and only its relative motion
+/// is reported.
+fn window_set_pointer_lock(
+  state: &mut OpState,
+  args: WindowPointerLockArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let window = state
+    .resource_table
+    .get::<WindowResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  let window_state = window.state.borrow();
+  window
+    .pointer_lock
+    .set(window.window(), &window_state, args.locked)
+}
+
 fn window_drag_window(
   state: &mut OpState,
   rid: ResourceId,
@@ -686,7 +1939,622 @@ fn window_drag_window(
     .get::<WindowResource>(rid)
     .ok_or_else(bad_resource_id)?;
 
-  window.0.drag_window()?;
+  window.window().drag_window()?;
 
   Ok(())
 }
+
+fn window_present(
+  state: &mut OpState,
+  args: WindowPresentArgs,
+  zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let window = state
+    .resource_table
+    .get::<WindowResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+  let zero_copy = zero_copy.ok_or_else(null_opbuf)?;
+
+  framebuffer::check_len(&zero_copy, args.width, args.height)?;
+  let bgra =
+    framebuffer::to_bgra(&zero_copy, args.format.unwrap_or(PixelFormat::Rgba));
+
+  framebuffer::present(window.window(), &bgra, args.width, args.height)
+}
+
+fn window_capture(
+  state: &mut OpState,
+  rid: ResourceId,
+  zero_copy: Option<ZeroCopyBuf>,
+) -> Result<PhysicalSize<u32>, AnyError> {
+  let window = state
+    .resource_table
+    .get::<WindowResource>(rid)
+    .ok_or_else(bad_resource_id)?;
+  let mut zero_copy = zero_copy.ok_or_else(null_opbuf)?;
+
+  framebuffer::capture(window.window(), &mut zero_copy)
+}
+
+fn window_raw_handle(
+  state: &mut OpState,
+  rid: ResourceId,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<RawHandle, AnyError> {
+  let window = state
+    .resource_table
+    .get::<WindowResource>(rid)
+    .ok_or_else(bad_resource_id)?;
+
+  RawHandle::new(window.raw_window_handle()).ok_or_else(not_supported)
+}
+
+fn window_set_accelerators(
+  state: &mut OpState,
+  args: WindowAcceleratorsArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let window = state
+    .resource_table
+    .get::<WindowResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  let menu = args
+    .menu
+    .map(|rid| {
+      state
+        .resource_table
+        .get::<MenuResource>(rid)
+        .ok_or_else(bad_resource_id)
+        .map(|menu| (rid, menu))
+    })
+    .transpose()?;
+
+  window.accelerators.replace(menu);
+
+  Ok(())
+}
+
+fn menu_new(
+  state: &mut OpState,
+  items: Vec<MenuItem>,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<ResourceId, AnyError> {
+  Ok(state.resource_table.add(MenuResource::new(items)))
+}
+
+fn menu_set_checked(
+  state: &mut OpState,
+  args: MenuItemCheckedArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let menu = state
+    .resource_table
+    .get::<MenuResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  menu.set_checked(&args.id, args.checked)
+}
+
+fn menu_set_enabled(
+  state: &mut OpState,
+  args: MenuItemEnabledArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let menu = state
+    .resource_table
+    .get::<MenuResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  menu.set_enabled(&args.id, args.enabled)
+}
+
+/// Pops up a menu as a context menu of a window, replacing any open context
+/// menu. Currently only X11 is supported.
+#[allow(unused_variables)]
+fn menu_popup(
+  state: &mut OpState,
+  args: MenuPopupArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let menu = state
+    .resource_table
+    .get::<MenuResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+  let window = state
+    .resource_table
+    .get::<WindowResource>(args.window)
+    .ok_or_else(bad_resource_id)?;
+
+  #[cfg(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  ))]
+  {
+    let event_loop = find_event_loop(state).ok_or_else(bad_resource_id)?;
+    let context_menu = context_menu::ContextMenu::open(
+      &event_loop.event_loop.borrow(),
+      window.window(),
+      args.position,
+      window.window().id(),
+      menu,
+      args.rid,
+      Vec::new(),
+    )?;
+
+    if let Some(context_menu) = context_menu {
+      if !state.has::<context_menu::ContextMenus>() {
+        state.put(context_menu::ContextMenus::default());
+      }
+
+      let context_menus = state.borrow_mut::<context_menu::ContextMenus>();
+      context_menus.close();
+      context_menus.0.push(context_menu);
+
+      return Ok(());
+    }
+  }
+
+  Err(not_supported())
+}
+
+async fn window_message_box(
+  state: Rc<RefCell<OpState>>,
+  args: MessageBoxArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<String, AnyError> {
+  let receiver = {
+    let mut state = state.borrow_mut();
+    let window = state
+      .resource_table
+      .get::<WindowResource>(args.rid)
+      .ok_or_else(bad_resource_id)?;
+    let event_loop = find_event_loop(&state).ok_or_else(bad_resource_id)?;
+    let message_box = message_box::MessageBox::open(
+      &event_loop.event_loop.borrow(),
+      window.window(),
+      args.options,
+    )?;
+
+    match message_box {
+      Some((message_box, receiver)) => {
+        if !state.has::<message_box::MessageBoxes>() {
+          state.put(message_box::MessageBoxes::default());
+        }
+        state
+          .borrow_mut::<message_box::MessageBoxes>()
+          .0
+          .push(message_box);
+
+        receiver
+      }
+      None => return Err(not_supported()),
+    }
+  };
+
+  let button = receiver.await.map_err(|_| {
+    deno_core::error::generic_error("The message box was closed")
+  })?;
+
+  Ok(button.name().to_string())
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+fn get_menu(
+  state: &OpState,
+  rid: Option<ResourceId>,
+) -> Result<Option<(ResourceId, Rc<MenuResource>)>, AnyError> {
+  rid
+    .map(|rid| {
+      state
+        .resource_table
+        .get::<MenuResource>(rid)
+        .ok_or_else(bad_resource_id)
+        .map(|menu| (rid, menu))
+    })
+    .transpose()
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+fn tray_new(
+  state: &mut OpState,
+  args: TrayArgs,
+  zero_copy: Option<ZeroCopyBuf>,
+) -> Result<ResourceId, AnyError> {
+  let icons = if args.sizes.is_empty() {
+    Vec::new()
+  } else {
+    let zero_copy = zero_copy.as_deref().ok_or_else(null_opbuf)?;
+    icon::split_rgba(zero_copy, &args.sizes)?
+  };
+  let menu = get_menu(state, args.menu)?;
+
+  let tray = tray::TrayResource::new(
+    args.title,
+    args.tooltip,
+    icons.iter().map(icon::RgbaIcon::to_pixmap).collect(),
+  )?;
+  tray.set_menu(menu)?;
+
+  Ok(state.resource_table.add(tray))
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+fn tray_set_icon(
+  state: &mut OpState,
+  args: TrayIconArgs,
+  zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let tray = state
+    .resource_table
+    .get::<tray::TrayResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+  let icons = if args.sizes.is_empty() {
+    Vec::new()
+  } else {
+    let zero_copy = zero_copy.as_deref().ok_or_else(null_opbuf)?;
+    icon::split_rgba(zero_copy, &args.sizes)?
+  };
+
+  tray.set_icons(icons.iter().map(icon::RgbaIcon::to_pixmap).collect())?;
+
+  Ok(())
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+fn tray_set_tooltip(
+  state: &mut OpState,
+  args: TrayTooltipArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let tray = state
+    .resource_table
+    .get::<tray::TrayResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  tray.set_tooltip(args.tooltip)?;
+
+  Ok(())
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+fn tray_set_menu(
+  state: &mut OpState,
+  args: TrayMenuArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  let tray = state
+    .resource_table
+    .get::<tray::TrayResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+
+  tray.set_menu(get_menu(state, args.menu)?)?;
+
+  Ok(())
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+fn notification_show(
+  state: &mut OpState,
+  args: NotificationArgs,
+  zero_copy: Option<ZeroCopyBuf>,
+) -> Result<u32, AnyError> {
+  let icon = match args.icon {
+    Some(size) => {
+      let zero_copy = zero_copy.as_deref().ok_or_else(null_opbuf)?;
+      icon::split_rgba(zero_copy, &[size])?.pop()
+    }
+    None => None,
+  };
+
+  if !state.has::<notification::Notifications>() {
+    state.put(notification::Notifications::new()?);
+  }
+
+  state
+    .borrow::<notification::Notifications>()
+    .show(args.notification, icon.as_ref())
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+fn notification_close(
+  state: &mut OpState,
+  id: u32,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<(), AnyError> {
+  match state.try_borrow::<notification::Notifications>() {
+    Some(notifications) => notifications.close(id),
+    None => Ok(()),
+  }
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+fn show_file_dialog(
+  state: &RefCell<OpState>,
+  kind: file_dialog::FileDialogKind,
+  args: FileDialogArgs,
+) -> Result<
+  impl std::future::Future<Output = Result<Vec<String>, AnyError>>,
+  AnyError,
+> {
+  let window = state
+    .borrow()
+    .resource_table
+    .get::<WindowResource>(args.rid)
+    .ok_or_else(bad_resource_id)?;
+  let parent_window = file_dialog::parent_window(window.window());
+
+  Ok(file_dialog::show(kind, parent_window, args.options))
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+async fn window_open_file_dialog(
+  state: Rc<RefCell<OpState>>,
+  args: FileDialogArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<Option<String>, AnyError> {
+  let kind = file_dialog::FileDialogKind::OpenFile;
+
+  Ok(show_file_dialog(&state, kind, args)?.await?.pop())
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+async fn window_open_files_dialog(
+  state: Rc<RefCell<OpState>>,
+  args: FileDialogArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<Vec<String>, AnyError> {
+  let kind = file_dialog::FileDialogKind::OpenFiles;
+
+  show_file_dialog(&state, kind, args)?.await
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+async fn window_open_directory_dialog(
+  state: Rc<RefCell<OpState>>,
+  args: FileDialogArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<Option<String>, AnyError> {
+  let kind = file_dialog::FileDialogKind::OpenDirectory;
+
+  Ok(show_file_dialog(&state, kind, args)?.await?.pop())
+}
+
+#[cfg(all(
+  feature = "freedesktop",
+  any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd"
+  )
+))]
+async fn window_save_file_dialog(
+  state: Rc<RefCell<OpState>>,
+  args: FileDialogArgs,
+  _zero_copy: Option<ZeroCopyBuf>,
+) -> Result<Option<String>, AnyError> {
+  let kind = file_dialog::FileDialogKind::SaveFile;
+
+  Ok(show_file_dialog(&state, kind, args)?.await?.pop())
+}
+
+#[cfg(test)]
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+mod tests {
+  use std::os::raw::c_ulong;
+
+  use deno_core::error::get_custom_error_class;
+  use deno_core::JsRuntime;
+  use deno_core::RuntimeOptions;
+
+  use winit::dpi::LogicalPosition;
+  use winit::dpi::LogicalSize;
+  use winit::platform::unix::EventLoopExtUnix;
+
+  use super::*;
+
+  fn x_window(window: &WindowResource) -> x11::XWindow {
+    x11::XWindow::new(window.window())
+      .unwrap()
+      .expect("an X11 window")
+  }
+
+  #[test]
+  fn rejects_non_finite_opacities() {
+    let mut runtime = JsRuntime::new(RuntimeOptions::default());
+    let state = runtime.op_state();
+
+    for &opacity in &[f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+      let args = WindowOpacityArgs { rid: 0, opacity };
+      let error =
+        window_set_opacity(&mut state.borrow_mut(), args, None).unwrap_err();
+      assert_eq!(get_custom_error_class(&error), Some("TypeError"));
+    }
+  }
+
+  #[test]
+  #[ignore = "needs an X server, run with xvfb-run cargo test -- --ignored"]
+  fn sets_the_opacity_property() {
+    let event_loop = EventLoop::new_any_thread();
+    let mut runtime = JsRuntime::new(RuntimeOptions::default());
+    let state = runtime.op_state();
+    let mut state = state.borrow_mut();
+    let rid = state
+      .resource_table
+      .add(WindowResource::new(&event_loop).unwrap());
+    let opacity = |state: &OpState| {
+      let window = state.resource_table.get::<WindowResource>(rid).unwrap();
+      x_window(&window)
+        .get_longs("_NET_WM_WINDOW_OPACITY", x11_dl::xlib::XA_CARDINAL)
+        .unwrap()
+    };
+
+    let args = WindowOpacityArgs { rid, opacity: 0.5 };
+    window_set_opacity(&mut state, args, None).unwrap();
+    assert_eq!(
+      opacity(&state),
+      [(u32::MAX as f64 / 2.0).round() as c_ulong]
+    );
+
+    // Opacities out of range are clamped.
+    let args = WindowOpacityArgs { rid, opacity: 2.0 };
+    window_set_opacity(&mut state, args, None).unwrap();
+    assert_eq!(opacity(&state), [u32::MAX as c_ulong]);
+  }
+
+  #[test]
+  fn drops_text_while_text_input_is_inactive() {
+    let character = winit::event::WindowEvent::ReceivedCharacter('a');
+    let focused = winit::event::WindowEvent::Focused(true);
+
+    assert!(is_unwanted_text(false, &character));
+    assert!(!is_unwanted_text(true, &character));
+    assert!(!is_unwanted_text(false, &focused));
+  }
+
+  #[test]
+  #[ignore = "needs an X server, run with xvfb-run cargo test -- --ignored"]
+  fn sets_text_input() {
+    let event_loop = EventLoop::new_any_thread();
+    let mut runtime = JsRuntime::new(RuntimeOptions::default());
+    let state = runtime.op_state();
+    let mut state = state.borrow_mut();
+    let rid = state
+      .resource_table
+      .add(WindowResource::new(&event_loop).unwrap());
+    let text_input = |state: &OpState| {
+      let window = state.resource_table.get::<WindowResource>(rid).unwrap();
+      window.text_input.get()
+    };
+    assert!(text_input(&state));
+
+    let args = WindowTextInputArgs {
+      rid,
+      active: false,
+      area: None,
+    };
+    window_set_text_input(&mut state, args, None).unwrap();
+    assert!(!text_input(&state));
+
+    let args = WindowTextInputArgs {
+      rid,
+      active: true,
+      area: Some(TextInputArea {
+        position: Position::Logical(LogicalPosition::new(10.0, 20.0)),
+        size: Size::Logical(LogicalSize::new(100.0, 20.0)),
+      }),
+    };
+    window_set_text_input(&mut state, args, None).unwrap();
+    assert!(text_input(&state));
+  }
+}
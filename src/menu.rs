@@ -0,0 +1,507 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::convert::TryFrom;
+
+use deno_core::error::custom_error;
+use deno_core::error::AnyError;
+use deno_core::serde::de::value::StringDeserializer;
+use deno_core::serde::de::IntoDeserializer;
+use deno_core::serde::Deserialize;
+use deno_core::Resource;
+use deno_core::ResourceId;
+
+use winit::event::ModifiersState;
+use winit::event::VirtualKeyCode;
+
+use crate::event::Event;
+
+/// A key combination that activates a menu item, such as `"Ctrl+Shift+S"`.
+/// Keys are named like winit's `VirtualKeyCode`, letters and digits can also
+/// be written as is.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Accelerator {
+  label: String,
+  modifiers: ModifiersState,
+  key: VirtualKeyCode,
+}
+
+impl TryFrom<String> for Accelerator {
+  type Error = String;
+
+  fn try_from(label: String) -> Result<Self, Self::Error> {
+    let mut parts: Vec<&str> = label.split('+').map(str::trim).collect();
+    let key = parts.pop().unwrap_or_default();
+    let mut modifiers = ModifiersState::empty();
+
+    for part in parts {
+      modifiers |= match part.to_ascii_lowercase().as_str() {
+        "ctrl" | "control" => ModifiersState::CTRL,
+        "shift" => ModifiersState::SHIFT,
+        "alt" | "option" => ModifiersState::ALT,
+        "super" | "logo" | "meta" | "cmd" => ModifiersState::LOGO,
+        _ => {
+          return Err(format!(
+            "Unknown modifier \"{}\" in the accelerator \"{}\"",
+            part, label
+          ))
+        }
+      };
+    }
+
+    let key = parse_key(key).ok_or_else(|| {
+      format!("Unknown key \"{}\" in the accelerator \"{}\"", key, label)
+    })?;
+
+    Ok(Self {
+      label,
+      modifiers,
+      key,
+    })
+  }
+}
+
+fn parse_key(key: &str) -> Option<VirtualKeyCode> {
+  let name = match key.as_bytes() {
+    [digit] if digit.is_ascii_digit() => format!("Key{}", key),
+    [_] => key.to_ascii_uppercase(),
+    _ => key.to_string(),
+  };
+  let deserializer: StringDeserializer<deno_core::serde::de::value::Error> =
+    name.into_deserializer();
+
+  VirtualKeyCode::deserialize(deserializer).ok()
+}
+
+impl Accelerator {
+  pub fn label(&self) -> &str {
+    &self.label
+  }
+
+  fn matches(&self, modifiers: ModifiersState, key: VirtualKeyCode) -> bool {
+    self.key == key && self.modifiers == modifiers
+  }
+}
+
+fn enabled() -> bool {
+  true
+}
+
+/// An entry of a menu, as described in JSON.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase", tag = "type")]
+pub enum MenuItem {
+  #[serde(rename_all = "camelCase")]
+  Item {
+    id: String,
+    label: String,
+    accelerator: Option<Accelerator>,
+    #[serde(default = "enabled")]
+    enabled: bool,
+  },
+  #[serde(rename_all = "camelCase")]
+  Checkbox {
+    id: String,
+    label: String,
+    #[serde(default)]
+    checked: bool,
+    accelerator: Option<Accelerator>,
+    #[serde(default = "enabled")]
+    enabled: bool,
+  },
+  Separator,
+  #[serde(rename_all = "camelCase")]
+  Submenu {
+    label: String,
+    items: Vec<MenuItem>,
+    #[serde(default = "enabled")]
+    enabled: bool,
+  },
+}
+
+impl MenuItem {
+  pub fn label(&self) -> Option<&str> {
+    match self {
+      MenuItem::Item { label, .. }
+      | MenuItem::Checkbox { label, .. }
+      | MenuItem::Submenu { label, .. } => Some(label),
+      MenuItem::Separator => None,
+    }
+  }
+
+  pub fn accelerator(&self) -> Option<&Accelerator> {
+    match self {
+      MenuItem::Item { accelerator, .. }
+      | MenuItem::Checkbox { accelerator, .. } => accelerator.as_ref(),
+      MenuItem::Separator | MenuItem::Submenu { .. } => None,
+    }
+  }
+
+  pub fn is_enabled(&self) -> bool {
+    match self {
+      MenuItem::Item { enabled, .. }
+      | MenuItem::Checkbox { enabled, .. }
+      | MenuItem::Submenu { enabled, .. } => *enabled,
+      MenuItem::Separator => false,
+    }
+  }
+
+  fn id(&self) -> Option<&str> {
+    match self {
+      MenuItem::Item { id, .. } | MenuItem::Checkbox { id, .. } => Some(id),
+      MenuItem::Separator | MenuItem::Submenu { .. } => None,
+    }
+  }
+
+  /// Activates the item, toggling it if it is a checkbox. Returns `None` for
+  /// items that can not be activated.
+  fn activate(&mut self) -> Option<MenuActivation> {
+    match self {
+      MenuItem::Item { id, enabled, .. } if *enabled => Some(MenuActivation {
+        id: id.clone(),
+        checked: None,
+      }),
+      MenuItem::Checkbox {
+        id,
+        checked,
+        enabled,
+        ..
+      } if *enabled => {
+        *checked = !*checked;
+
+        Some(MenuActivation {
+          id: id.clone(),
+          checked: Some(*checked),
+        })
+      }
+      _ => None,
+    }
+  }
+}
+
+/// An activated menu item, with the new state of checkboxes.
+pub struct MenuActivation {
+  pub id: String,
+  pub checked: Option<bool>,
+}
+
+impl MenuActivation {
+  pub fn into_event(self, window_id: u32, menu_id: ResourceId) -> Event {
+    Event::Menu {
+      window_id,
+      menu_id,
+      item_id: self.id,
+      checked: self.checked,
+    }
+  }
+}
+
+fn find_item<'a>(
+  items: &'a mut [MenuItem],
+  id: &str,
+) -> Option<&'a mut MenuItem> {
+  for item in items {
+    if item.id() == Some(id) {
+      return Some(item);
+    }
+    if let MenuItem::Submenu { items, .. } = item {
+      if let Some(item) = find_item(items, id) {
+        return Some(item);
+      }
+    }
+  }
+
+  None
+}
+
+/// Finds the item an accelerator activates, skipping disabled submenus.
+fn find_accelerator(
+  items: &mut [MenuItem],
+  modifiers: ModifiersState,
+  key: VirtualKeyCode,
+) -> Option<&mut MenuItem> {
+  for item in items {
+    if let MenuItem::Submenu {
+      items,
+      enabled: true,
+      ..
+    } = item
+    {
+      if let Some(item) = find_accelerator(items, modifiers, key) {
+        return Some(item);
+      }
+    } else if matches!(
+      item.accelerator(),
+      Some(accelerator) if accelerator.matches(modifiers, key)
+    ) {
+      return Some(item);
+    }
+  }
+
+  None
+}
+
+/// Returns the items of the submenu at `path`, a list of item indices.
+pub fn submenu_items<'a>(
+  mut items: &'a [MenuItem],
+  path: &[usize],
+) -> &'a [MenuItem] {
+  for &index in path {
+    items = match items.get(index) {
+      Some(MenuItem::Submenu { items, .. }) => items,
+      _ => return &[],
+    };
+  }
+
+  items
+}
+
+/// A menu tree that can be attached to windows for its accelerators, or be
+/// popped up as a context menu.
+pub struct MenuResource {
+  items: RefCell<Vec<MenuItem>>,
+  revision: Cell<u32>,
+}
+
+impl Resource for MenuResource {
+  fn name(&self) -> Cow<str> {
+    "menu".into()
+  }
+}
+
+impl MenuResource {
+  pub fn new(items: Vec<MenuItem>) -> Self {
+    Self {
+      items: RefCell::new(items),
+      revision: Cell::new(0),
+    }
+  }
+
+  /// Increases whenever an item is changed, checked or enabled.
+  #[cfg(all(
+    feature = "freedesktop",
+    any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    )
+  ))]
+  pub fn revision(&self) -> u32 {
+    self.revision.get()
+  }
+
+  fn changed(&self) {
+    self.revision.set(self.revision.get().wrapping_add(1));
+  }
+
+  /// Returns the items of the submenu at `path`, a list of item indices.
+  pub fn items(&self, path: &[usize]) -> Vec<MenuItem> {
+    submenu_items(&self.items.borrow(), path).to_vec()
+  }
+
+  /// Activates the item at `index` of the submenu at `path`.
+  pub fn activate(
+    &self,
+    path: &[usize],
+    index: usize,
+  ) -> Option<MenuActivation> {
+    let mut items = self.items.borrow_mut();
+    let mut items = &mut items[..];
+
+    for &index in path {
+      items = match items.get_mut(index) {
+        Some(MenuItem::Submenu { items, .. }) => items,
+        _ => return None,
+      };
+    }
+
+    let activation = items.get_mut(index)?.activate()?;
+    self.changed();
+
+    Some(activation)
+  }
+
+  /// Activates the item with a matching accelerator, if there is one.
+  pub fn activate_accelerator(
+    &self,
+    modifiers: ModifiersState,
+    key: VirtualKeyCode,
+  ) -> Option<MenuActivation> {
+    let activation =
+      find_accelerator(&mut self.items.borrow_mut(), modifiers, key)?
+        .activate()?;
+    self.changed();
+
+    Some(activation)
+  }
+
+  pub fn set_checked(&self, id: &str, checked: bool) -> Result<(), AnyError> {
+    match find_item(&mut self.items.borrow_mut(), id) {
+      Some(MenuItem::Checkbox { checked: item, .. }) => {
+        *item = checked;
+        self.changed();
+        Ok(())
+      }
+      _ => Err(item_not_found(id)),
+    }
+  }
+
+  pub fn set_enabled(&self, id: &str, enabled: bool) -> Result<(), AnyError> {
+    match find_item(&mut self.items.borrow_mut(), id) {
+      Some(MenuItem::Item { enabled: item, .. })
+      | Some(MenuItem::Checkbox { enabled: item, .. }) => {
+        *item = enabled;
+        self.changed();
+        Ok(())
+      }
+      _ => Err(item_not_found(id)),
+    }
+  }
+}
+
+fn item_not_found(id: &str) -> AnyError {
+  custom_error("NotFound", format!("No menu item with the id \"{}\"", id))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn accelerator(label: &str) -> Result<Accelerator, String> {
+    Accelerator::try_from(label.to_string())
+  }
+
+  fn item(id: &str, label: &str) -> MenuItem {
+    MenuItem::Item {
+      id: id.into(),
+      label: id.into(),
+      accelerator: Some(accelerator(label).unwrap()),
+      enabled: true,
+    }
+  }
+
+  fn submenu(items: Vec<MenuItem>, enabled: bool) -> MenuItem {
+    MenuItem::Submenu {
+      label: "Submenu".into(),
+      items,
+      enabled,
+    }
+  }
+
+  fn find(
+    items: &mut [MenuItem],
+    modifiers: ModifiersState,
+    key: VirtualKeyCode,
+  ) -> Option<&str> {
+    find_accelerator(items, modifiers, key).and_then(|item| item.id())
+  }
+
+  #[test]
+  fn parses_modifiers() {
+    let ctrl_shift_s = accelerator("Ctrl+Shift+S").unwrap();
+    assert_eq!(ctrl_shift_s.label(), "Ctrl+Shift+S");
+    assert_eq!(
+      ctrl_shift_s.modifiers,
+      ModifiersState::CTRL | ModifiersState::SHIFT
+    );
+    assert_eq!(ctrl_shift_s.key, VirtualKeyCode::S);
+
+    // Modifiers have aliases, are case insensitive and may be surrounded by
+    // spaces.
+    let cmd_option_1 = accelerator(" cmd + OPTION + 1 ").unwrap();
+    assert_eq!(
+      cmd_option_1.modifiers,
+      ModifiersState::LOGO | ModifiersState::ALT
+    );
+    assert_eq!(cmd_option_1.key, VirtualKeyCode::Key1);
+
+    let f5 = accelerator("F5").unwrap();
+    assert_eq!(f5.modifiers, ModifiersState::empty());
+    assert_eq!(f5.key, VirtualKeyCode::F5);
+  }
+
+  #[test]
+  fn rejects_unknown_modifiers() {
+    let error = accelerator("Hyper+A").unwrap_err();
+    assert_eq!(
+      error,
+      "Unknown modifier \"Hyper\" in the accelerator \"Hyper+A\""
+    );
+  }
+
+  #[test]
+  fn rejects_unknown_and_missing_keys() {
+    let error = accelerator("Ctrl+Foo").unwrap_err();
+    assert_eq!(error, "Unknown key \"Foo\" in the accelerator \"Ctrl+Foo\"");
+    let error = accelerator("Ctrl+").unwrap_err();
+    assert_eq!(error, "Unknown key \"\" in the accelerator \"Ctrl+\"");
+    assert!(accelerator("").is_err());
+  }
+
+  #[test]
+  fn parses_keys() {
+    assert_eq!(parse_key("a"), Some(VirtualKeyCode::A));
+    assert_eq!(parse_key("Z"), Some(VirtualKeyCode::Z));
+    assert_eq!(parse_key("0"), Some(VirtualKeyCode::Key0));
+    assert_eq!(parse_key("Key0"), Some(VirtualKeyCode::Key0));
+    assert_eq!(parse_key("Escape"), Some(VirtualKeyCode::Escape));
+    assert_eq!(parse_key("Numpad1"), Some(VirtualKeyCode::Numpad1));
+    // Only single letters are case insensitive.
+    assert_eq!(parse_key("escape"), None);
+    assert_eq!(parse_key("+"), None);
+    assert_eq!(parse_key(""), None);
+  }
+
+  #[test]
+  fn finds_accelerators_in_submenus() {
+    let mut items = vec![
+      item("save", "Ctrl+S"),
+      MenuItem::Separator,
+      submenu(vec![item("save_as", "Ctrl+Shift+S")], true),
+    ];
+
+    assert_eq!(
+      find(&mut items, ModifiersState::CTRL, VirtualKeyCode::S),
+      Some("save")
+    );
+    assert_eq!(
+      find(
+        &mut items,
+        ModifiersState::CTRL | ModifiersState::SHIFT,
+        VirtualKeyCode::S
+      ),
+      Some("save_as")
+    );
+  }
+
+  #[test]
+  fn matches_modifiers_exactly() {
+    let mut items = vec![item("save", "Ctrl+S")];
+
+    assert_eq!(
+      find(
+        &mut items,
+        ModifiersState::CTRL | ModifiersState::SHIFT,
+        VirtualKeyCode::S
+      ),
+      None
+    );
+    assert_eq!(
+      find(&mut items, ModifiersState::empty(), VirtualKeyCode::S),
+      None
+    );
+  }
+
+  #[test]
+  fn skips_disabled_submenus() {
+    let mut items = vec![submenu(vec![item("save", "Ctrl+S")], false)];
+
+    assert_eq!(
+      find(&mut items, ModifiersState::CTRL, VirtualKeyCode::S),
+      None
+    );
+  }
+}
@@ -0,0 +1,706 @@
+use std::cell::Cell;
+use std::cell::RefCell;
+
+use deno_core::error::AnyError;
+use deno_core::futures::channel::oneshot;
+use deno_core::serde::Deserialize;
+
+use winit::dpi::PhysicalPosition;
+use winit::event::ElementState;
+use winit::event::KeyboardInput;
+use winit::event::MouseButton;
+use winit::event::VirtualKeyCode;
+use winit::event::WindowEvent;
+use winit::event_loop::EventLoopWindowTarget;
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+use winit::platform::unix::WindowBuilderExtUnix;
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+use winit::platform::unix::XWindowType;
+use winit::window::Window;
+use winit::window::WindowBuilder;
+use winit::window::WindowId;
+
+use crate::canvas::text_width;
+use crate::canvas::Canvas;
+use crate::canvas::GLYPH_HEIGHT;
+use crate::canvas::GLYPH_WIDTH;
+use crate::framebuffer;
+
+const BACKGROUND: u32 = 0xf6f5f4;
+const BORDER: u32 = 0xc0bfbc;
+const TEXT: u32 = 0x241f31;
+const BUTTON: u32 = 0xffffff;
+const HOVERED_BUTTON: u32 = 0xe8e8e7;
+const FOCUS: u32 = 0x3584e4;
+const ICON_TEXT: u32 = 0xffffff;
+
+/// Sizes are in canvas units, which are scaled to the scale factor of the
+/// owner.
+const PADDING: i32 = 12;
+const ICON_SIZE: i32 = 24;
+const MAX_TEXT_WIDTH: i32 = 320;
+const LINE_HEIGHT: i32 = GLYPH_HEIGHT + 4;
+const BUTTON_PADDING: i32 = 6;
+const BUTTON_MIN_WIDTH: i32 = 64;
+const BUTTON_SPACING: i32 = 8;
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MessageBoxIcon {
+  Info,
+  Warning,
+  Error,
+  Question,
+}
+
+impl MessageBoxIcon {
+  fn glyph(self) -> (&'static str, u32) {
+    match self {
+      MessageBoxIcon::Info => ("i", 0x3584e4),
+      MessageBoxIcon::Warning => ("!", 0xe5a50a),
+      MessageBoxIcon::Error => ("x", 0xc01c28),
+      MessageBoxIcon::Question => ("?", 0x3584e4),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MessageBoxButtons {
+  Ok,
+  OkCancel,
+  YesNo,
+  YesNoCancel,
+}
+
+impl MessageBoxButtons {
+  /// The buttons in the order they are shown, the first is the default.
+  fn buttons(self) -> &'static [MessageBoxButton] {
+    match self {
+      MessageBoxButtons::Ok => &[MessageBoxButton::Ok],
+      MessageBoxButtons::OkCancel => {
+        &[MessageBoxButton::Ok, MessageBoxButton::Cancel]
+      }
+      MessageBoxButtons::YesNo => {
+        &[MessageBoxButton::Yes, MessageBoxButton::No]
+      }
+      MessageBoxButtons::YesNoCancel => &[
+        MessageBoxButton::Yes,
+        MessageBoxButton::No,
+        MessageBoxButton::Cancel,
+      ],
+    }
+  }
+
+  /// The button chosen when the message box is closed or Escape is pressed.
+  fn dismiss(self) -> MessageBoxButton {
+    match self {
+      MessageBoxButtons::Ok => MessageBoxButton::Ok,
+      MessageBoxButtons::YesNo => MessageBoxButton::No,
+      MessageBoxButtons::OkCancel | MessageBoxButtons::YesNoCancel => {
+        MessageBoxButton::Cancel
+      }
+    }
+  }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum MessageBoxButton {
+  Ok,
+  Cancel,
+  Yes,
+  No,
+}
+
+impl MessageBoxButton {
+  /// The name the button is returned to JavaScript as.
+  pub fn name(self) -> &'static str {
+    match self {
+      MessageBoxButton::Ok => "ok",
+      MessageBoxButton::Cancel => "cancel",
+      MessageBoxButton::Yes => "yes",
+      MessageBoxButton::No => "no",
+    }
+  }
+
+  fn label(self) -> &'static str {
+    match self {
+      MessageBoxButton::Ok => "OK",
+      MessageBoxButton::Cancel => "Cancel",
+      MessageBoxButton::Yes => "Yes",
+      MessageBoxButton::No => "No",
+    }
+  }
+}
+
+fn default_icon() -> MessageBoxIcon {
+  MessageBoxIcon::Info
+}
+
+fn default_buttons() -> MessageBoxButtons {
+  MessageBoxButtons::Ok
+}
+
+/// A message box, as described in JSON.
+#[derive(Deserialize)]
+pub struct MessageBoxOptions {
+  #[serde(default)]
+  title: String,
+  text: String,
+  #[serde(default = "default_icon")]
+  icon: MessageBoxIcon,
+  #[serde(default = "default_buttons")]
+  buttons: MessageBoxButtons,
+}
+
+#[derive(Debug, PartialEq)]
+struct Rect {
+  x: i32,
+  y: i32,
+  width: i32,
+  height: i32,
+}
+
+impl Rect {
+  fn contains(&self, (x, y): (i32, i32)) -> bool {
+    x >= self.x
+      && x < self.x + self.width
+      && y >= self.y
+      && y < self.y + self.height
+  }
+}
+
+/// A modal message box in a window owned by another, drawn in software with
+/// the built-in font. Like `framebuffer::present`, this currently only works
+/// on X11.
+pub struct MessageBox {
+  window: Window,
+  owner: WindowId,
+  options: MessageBoxOptions,
+  /// The text wrapped to lines.
+  lines: Vec<String>,
+  buttons: Vec<(MessageBoxButton, Rect)>,
+  hovered: Cell<Option<usize>>,
+  focused: Cell<usize>,
+  /// The message box as last drawn, presented again on every redraw.
+  canvas: RefCell<Canvas>,
+  sender: oneshot::Sender<MessageBoxButton>,
+}
+
+/// The open message boxes of the event loop.
+#[derive(Default)]
+pub struct MessageBoxes(pub Vec<MessageBox>);
+
+impl MessageBox {
+  /// Opens a message box centered over `owner`, the chosen button is sent
+  /// through the returned receiver. Returns `None` if windows of the owner's
+  /// backend can not be drawn to.
+  pub fn open(
+    target: &EventLoopWindowTarget<()>,
+    owner: &Window,
+    options: MessageBoxOptions,
+  ) -> Result<Option<(Self, oneshot::Receiver<MessageBoxButton>)>, AnyError> {
+    if !framebuffer::is_supported(owner) {
+      return Ok(None);
+    }
+
+    let Layout {
+      lines,
+      buttons,
+      width,
+      height,
+    } = layout(&options);
+    let scale = owner.scale_factor().round() as u32;
+    let canvas = Canvas::new(width as u32, height as u32, scale);
+    let size = canvas.size();
+
+    let mut builder = WindowBuilder::new()
+      .with_title(&options.title)
+      .with_inner_size(size)
+      .with_resizable(false)
+      .with_visible(false);
+    // Wayland does not expose window positions, the compositor places the
+    // message box there.
+    if let Ok(owner_position) = owner.outer_position() {
+      let owner_size = owner.outer_size();
+      builder = builder.with_position(PhysicalPosition::new(
+        owner_position.x + (owner_size.width as i32 - size.width as i32) / 2,
+        owner_position.y + (owner_size.height as i32 - size.height as i32) / 2,
+      ));
+    }
+    #[cfg(any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    ))]
+    let builder = builder.with_x11_window_type(vec![XWindowType::Dialog]);
+    let window = builder.build(target)?;
+
+    #[cfg(any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    ))]
+    set_x11_hints(&window, owner)?;
+    window.set_visible(true);
+
+    let (sender, receiver) = oneshot::channel();
+    let message_box = Self {
+      window,
+      owner: owner.id(),
+      options,
+      lines,
+      buttons,
+      hovered: Cell::new(None),
+      focused: Cell::new(0),
+      canvas: RefCell::new(canvas),
+      sender,
+    };
+    message_box.draw();
+
+    Ok(Some((message_box, receiver)))
+  }
+
+  fn button_at(&self, position: PhysicalPosition<f64>) -> Option<usize> {
+    let position = self.canvas.borrow().to_canvas(position.x, position.y);
+
+    self
+      .buttons
+      .iter()
+      .position(|(_, rect)| rect.contains(position))
+  }
+
+  fn focus(&self, offset: usize) {
+    let count = self.buttons.len();
+
+    self.focused.set((self.focused.get() + offset) % count);
+    self.draw();
+  }
+
+  fn hover(&self, hovered: Option<usize>) {
+    if self.hovered.replace(hovered) != hovered {
+      self.draw();
+    }
+  }
+
+  /// Draws the message box into its canvas and requests it to be presented.
+  fn draw(&self) {
+    let mut canvas = self.canvas.borrow_mut();
+    canvas.clear(BACKGROUND);
+
+    let (glyph, color) = self.options.icon.glyph();
+    canvas.fill_rect(color, PADDING, PADDING, ICON_SIZE, ICON_SIZE);
+    canvas.draw_text(
+      ICON_TEXT,
+      PADDING + (ICON_SIZE - GLYPH_WIDTH) / 2,
+      PADDING + (ICON_SIZE - GLYPH_HEIGHT) / 2,
+      glyph,
+    );
+
+    let text_x = PADDING + ICON_SIZE + PADDING;
+    for (index, line) in self.lines.iter().enumerate() {
+      let y =
+        PADDING + index as i32 * LINE_HEIGHT + (LINE_HEIGHT - GLYPH_HEIGHT) / 2;
+      canvas.draw_text(TEXT, text_x, y, line);
+    }
+
+    for (index, (button, rect)) in self.buttons.iter().enumerate() {
+      let background = if self.hovered.get() == Some(index) {
+        HOVERED_BUTTON
+      } else {
+        BUTTON
+      };
+      let border = if self.focused.get() == index {
+        FOCUS
+      } else {
+        BORDER
+      };
+
+      canvas.fill_rect(background, rect.x, rect.y, rect.width, rect.height);
+      canvas.stroke_rect(border, rect.x, rect.y, rect.width, rect.height);
+
+      let label = button.label();
+      canvas.draw_text(
+        TEXT,
+        rect.x + (rect.width - text_width(label)) / 2,
+        rect.y + (rect.height - GLYPH_HEIGHT) / 2,
+        label,
+      );
+    }
+
+    self.window.request_redraw();
+  }
+}
+
+/// Marks the message box as a modal dialog of its owner where both are X11
+/// windows. The hints are set before the window is shown, as window managers
+/// only read them when a window is mapped.
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+fn set_x11_hints(window: &Window, owner: &Window) -> Result<(), AnyError> {
+  use crate::x11::XWindow;
+
+  if let (Some(x_window), Some(owner_window)) =
+    (XWindow::new(window)?, XWindow::new(owner)?)
+  {
+    x_window.set_transient_for(&owner_window);
+    x_window.set_atoms("_NET_WM_STATE", &["_NET_WM_STATE_MODAL"]);
+  }
+
+  Ok(())
+}
+
+/// The wrapped text and buttons of a message box, and the size of its canvas.
+struct Layout {
+  lines: Vec<String>,
+  buttons: Vec<(MessageBoxButton, Rect)>,
+  width: i32,
+  height: i32,
+}
+
+/// Wraps the text of a message box and lays out its buttons.
+fn layout(options: &MessageBoxOptions) -> Layout {
+  let lines = wrap(&options.text);
+  let lines_width =
+    lines.iter().map(|line| text_width(line)).max().unwrap_or(0);
+  let text_height = lines.len() as i32 * LINE_HEIGHT;
+
+  let button_height = GLYPH_HEIGHT + 2 * BUTTON_PADDING;
+  let button_widths: Vec<i32> = options
+    .buttons
+    .buttons()
+    .iter()
+    .map(|button| {
+      (text_width(button.label()) + 2 * BUTTON_PADDING).max(BUTTON_MIN_WIDTH)
+    })
+    .collect();
+  let buttons_width = button_widths.iter().sum::<i32>()
+    + BUTTON_SPACING * (button_widths.len() as i32 - 1);
+
+  let width = (PADDING + ICON_SIZE + PADDING + lines_width + PADDING)
+    .max(PADDING + buttons_width + PADDING);
+  let buttons_y = PADDING + text_height.max(ICON_SIZE) + PADDING;
+  let height = buttons_y + button_height + PADDING;
+
+  let mut x = width - PADDING - buttons_width;
+  let buttons = options
+    .buttons
+    .buttons()
+    .iter()
+    .zip(button_widths)
+    .map(|(&button, button_width)| {
+      let rect = Rect {
+        x,
+        y: buttons_y,
+        width: button_width,
+        height: button_height,
+      };
+      x += button_width + BUTTON_SPACING;
+
+      (button, rect)
+    })
+    .collect();
+
+  Layout {
+    lines,
+    buttons,
+    width,
+    height,
+  }
+}
+
+/// Wraps text to lines no wider than `MAX_TEXT_WIDTH`, breaking at spaces and
+/// at line breaks.
+fn wrap(text: &str) -> Vec<String> {
+  let mut lines = Vec::new();
+
+  for paragraph in text.lines() {
+    let mut line = String::new();
+
+    for word in paragraph.split(' ') {
+      let candidate = if line.is_empty() {
+        word.to_string()
+      } else {
+        format!("{} {}", line, word)
+      };
+
+      if !line.is_empty() && text_width(&candidate) > MAX_TEXT_WIDTH {
+        lines.push(std::mem::replace(&mut line, word.to_string()));
+      } else {
+        line = candidate;
+      }
+    }
+
+    lines.push(line);
+  }
+
+  lines
+}
+
+impl MessageBoxes {
+  /// Handles the events of message box windows, and blocks the input of the
+  /// windows they are modal to. Returns whether the event was handled, in
+  /// which case it should not be passed on.
+  pub fn handle_event(
+    &mut self,
+    event: &winit::event::Event<()>,
+  ) -> Result<bool, AnyError> {
+    let (window_id, window_event) = match event {
+      winit::event::Event::WindowEvent { window_id, event } => {
+        (*window_id, Some(event))
+      }
+      winit::event::Event::RedrawRequested(window_id) => (*window_id, None),
+      _ => return Ok(false),
+    };
+
+    let index = match self
+      .0
+      .iter()
+      .position(|message_box| message_box.window.id() == window_id)
+    {
+      Some(index) => index,
+      None => return Ok(self.handle_owner_event(window_id, window_event)),
+    };
+
+    let message_box = &self.0[index];
+    let chosen = match window_event {
+      None => {
+        message_box.canvas.borrow().present(&message_box.window)?;
+        None
+      }
+      Some(WindowEvent::CursorMoved { position, .. }) => {
+        message_box.hover(message_box.button_at(*position));
+        None
+      }
+      Some(WindowEvent::CursorLeft { .. }) => {
+        message_box.hover(None);
+        None
+      }
+      Some(WindowEvent::MouseInput {
+        state: ElementState::Released,
+        button: MouseButton::Left,
+        ..
+      }) => message_box
+        .hovered
+        .get()
+        .map(|index| message_box.buttons[index].0),
+      Some(WindowEvent::KeyboardInput {
+        input:
+          KeyboardInput {
+            state: ElementState::Pressed,
+            virtual_keycode: Some(key),
+            ..
+          },
+        ..
+      }) => match key {
+        VirtualKeyCode::Return
+        | VirtualKeyCode::NumpadEnter
+        | VirtualKeyCode::Space => {
+          Some(message_box.buttons[message_box.focused.get()].0)
+        }
+        VirtualKeyCode::Escape => Some(message_box.options.buttons.dismiss()),
+        VirtualKeyCode::Tab | VirtualKeyCode::Right => {
+          message_box.focus(1);
+          None
+        }
+        VirtualKeyCode::Left => {
+          message_box.focus(message_box.buttons.len() - 1);
+          None
+        }
+        _ => None,
+      },
+      Some(WindowEvent::CloseRequested) => {
+        Some(message_box.options.buttons.dismiss())
+      }
+      _ => None,
+    };
+
+    if let Some(button) = chosen {
+      let _ = self.0.remove(index).sender.send(button);
+    }
+
+    Ok(true)
+  }
+
+  /// Blocks input to windows with an open message box, and dismisses their
+  /// message boxes when they are destroyed.
+  fn handle_owner_event(
+    &mut self,
+    window_id: WindowId,
+    event: Option<&WindowEvent>,
+  ) -> bool {
+    if !self
+      .0
+      .iter()
+      .any(|message_box| message_box.owner == window_id)
+    {
+      return false;
+    }
+
+    match event {
+      Some(WindowEvent::Destroyed) => {
+        let (dismissed, open): (Vec<_>, Vec<_>) = std::mem::take(&mut self.0)
+          .into_iter()
+          .partition(|message_box| message_box.owner == window_id);
+        self.0 = open;
+
+        for message_box in dismissed {
+          let button = message_box.options.buttons.dismiss();
+          let _ = message_box.sender.send(button);
+        }
+
+        false
+      }
+      Some(event) => is_input(event),
+      None => false,
+    }
+  }
+}
+
+fn is_input(event: &WindowEvent) -> bool {
+  matches!(
+    event,
+    WindowEvent::KeyboardInput { .. }
+      | WindowEvent::ReceivedCharacter(_)
+      | WindowEvent::MouseInput { .. }
+      | WindowEvent::MouseWheel { .. }
+      | WindowEvent::Touch(_)
+      | WindowEvent::TouchpadPressure { .. }
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn options(text: &str, buttons: MessageBoxButtons) -> MessageBoxOptions {
+    MessageBoxOptions {
+      title: String::new(),
+      text: text.to_string(),
+      icon: MessageBoxIcon::Info,
+      buttons,
+    }
+  }
+
+  #[test]
+  fn wraps_text_at_spaces_and_line_breaks() {
+    let word = "x".repeat(15);
+    let text = format!("{0} {0} {0}\nshort\n\nend", word);
+
+    assert_eq!(
+      wrap(&text),
+      vec![
+        format!("{0} {0}", word),
+        word,
+        "short".to_string(),
+        String::new(),
+        "end".to_string(),
+      ]
+    );
+  }
+
+  #[test]
+  fn keeps_long_words_on_their_own_line() {
+    let word = "y".repeat(50);
+
+    assert_eq!(wrap(&format!("a {} b", word)), vec!["a", &word, "b"]);
+  }
+
+  #[test]
+  fn lays_out_buttons_from_the_right() {
+    let layout =
+      layout(&options("Save changes?", MessageBoxButtons::YesNoCancel));
+    let labels: Vec<_> = layout
+      .buttons
+      .iter()
+      .map(|(button, _)| button.name())
+      .collect();
+    let rects: Vec<_> = layout.buttons.iter().map(|(_, rect)| rect).collect();
+    let buttons_y = PADDING + ICON_SIZE + PADDING;
+    let button_height = GLYPH_HEIGHT + 2 * BUTTON_PADDING;
+
+    assert_eq!(labels, ["yes", "no", "cancel"]);
+    assert_eq!(layout.width, 3 * BUTTON_MIN_WIDTH + 2 * BUTTON_SPACING + 24);
+    assert_eq!(layout.height, buttons_y + button_height + PADDING);
+    assert_eq!(
+      rects[0],
+      &Rect {
+        x: PADDING,
+        y: buttons_y,
+        width: BUTTON_MIN_WIDTH,
+        height: button_height,
+      }
+    );
+    assert_eq!(
+      rects[2].x + rects[2].width + PADDING,
+      layout.width,
+      "the last button is aligned to the right padding"
+    );
+  }
+
+  #[test]
+  fn grows_to_fit_the_text() {
+    let layout =
+      layout(&options("z ".repeat(100).trim_end(), MessageBoxButtons::Ok));
+
+    assert_eq!(layout.lines.len(), 5);
+    assert_eq!(
+      layout.width,
+      PADDING + ICON_SIZE + PADDING + MAX_TEXT_WIDTH - GLYPH_WIDTH + PADDING
+    );
+    assert_eq!(
+      layout.height,
+      PADDING
+        + 5 * LINE_HEIGHT
+        + PADDING
+        + GLYPH_HEIGHT
+        + 2 * BUTTON_PADDING
+        + PADDING
+    );
+  }
+
+  #[test]
+  fn hits_buttons_inside_their_bounds() {
+    let rect = Rect {
+      x: 10,
+      y: 20,
+      width: 30,
+      height: 5,
+    };
+
+    assert!(rect.contains((10, 20)));
+    assert!(rect.contains((39, 24)));
+    assert!(!rect.contains((40, 24)));
+    assert!(!rect.contains((10, 25)));
+    assert!(!rect.contains((9, 20)));
+  }
+
+  #[test]
+  fn dismisses_with_the_safest_button() {
+    assert_eq!(MessageBoxButtons::Ok.dismiss().name(), "ok");
+    assert_eq!(MessageBoxButtons::OkCancel.dismiss().name(), "cancel");
+    assert_eq!(MessageBoxButtons::YesNo.dismiss().name(), "no");
+    assert_eq!(MessageBoxButtons::YesNoCancel.dismiss().name(), "cancel");
+  }
+}
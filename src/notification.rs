@@ -0,0 +1,447 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use deno_core::error::AnyError;
+use deno_core::serde::Deserialize;
+use deno_core::serde::Serialize;
+use deno_core::OpState;
+
+use dbus::arg::PropMap;
+use dbus::arg::Variant;
+use dbus::blocking::LocalConnection;
+use dbus::message::MatchRule;
+
+use crate::event::Event;
+use crate::icon::RgbaIcon;
+
+const NAME: &str = "org.freedesktop.Notifications";
+const PATH: &str = "/org/freedesktop/Notifications";
+const INTERFACE: &str = "org.freedesktop.Notifications";
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// An icon in the `image-data` hint format, a width, height, row stride,
+/// whether there is alpha, bits per sample, channels and the pixels.
+pub type ImageData = (i32, i32, i32, bool, i32, i32, Vec<u8>);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type", content = "value")]
+pub enum NotificationEvent {
+  /// An action of the notification was invoked, `"default"` when the
+  /// notification itself was clicked.
+  #[serde(rename_all = "camelCase")]
+  ActionInvoked { action_id: String },
+  /// The notification was closed, it will not emit any further events.
+  Closed { reason: CloseReason },
+  /// The connection to the notification server failed, the notification
+  /// may not report further events.
+  Error { message: String },
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CloseReason {
+  /// The notification timed out.
+  Expired,
+  /// The notification was dismissed by the user.
+  Dismissed,
+  /// The notification was closed with `pane_notification_close`.
+  Closed,
+  Undefined,
+}
+
+impl From<u32> for CloseReason {
+  fn from(reason: u32) -> Self {
+    match reason {
+      1 => CloseReason::Expired,
+      2 => CloseReason::Dismissed,
+      3 => CloseReason::Closed,
+      _ => CloseReason::Undefined,
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Urgency {
+  Low,
+  Normal,
+  Critical,
+}
+
+#[derive(Deserialize)]
+pub struct NotificationAction {
+  id: String,
+  label: String,
+}
+
+/// A notification to show, as described in JSON.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Notification {
+  #[serde(default)]
+  pub app_name: String,
+  pub title: String,
+  #[serde(default)]
+  pub body: String,
+  #[serde(default)]
+  pub actions: Vec<NotificationAction>,
+  /// Milliseconds until the notification expires, `0` to never expire. The
+  /// server decides when it is not given.
+  pub timeout: Option<i32>,
+  pub urgency: Option<Urgency>,
+}
+
+/// The connection to the notification server of the session bus, created
+/// when the first notification is shown and kept in the `OpState`.
+pub struct Notifications {
+  connection: LocalConnection,
+  signals: mpsc::Receiver<(u32, NotificationEvent)>,
+  /// The notifications shown by this connection that have not been closed.
+  open: RefCell<HashSet<u32>>,
+}
+
+impl Notifications {
+  pub fn new() -> Result<Self, AnyError> {
+    Self::with_connection(LocalConnection::new_session()?)
+  }
+
+  fn with_connection(connection: LocalConnection) -> Result<Self, AnyError> {
+    let (sender, signals) = mpsc::channel();
+
+    let action_sender = sender.clone();
+    connection.add_match(
+      MatchRule::new_signal(INTERFACE, "ActionInvoked").with_path(PATH),
+      move |(id, action_id): (u32, String), _, _| {
+        let event = NotificationEvent::ActionInvoked { action_id };
+        action_sender.send((id, event)).is_ok()
+      },
+    )?;
+    connection.add_match(
+      MatchRule::new_signal(INTERFACE, "NotificationClosed").with_path(PATH),
+      move |(id, reason): (u32, u32), _, _| {
+        let event = NotificationEvent::Closed {
+          reason: reason.into(),
+        };
+        sender.send((id, event)).is_ok()
+      },
+    )?;
+
+    Ok(Self {
+      connection,
+      signals,
+      open: RefCell::new(HashSet::new()),
+    })
+  }
+
+  /// Shows a notification, returning the id the server assigned to it.
+  pub fn show(
+    &self,
+    notification: Notification,
+    icon: Option<&RgbaIcon>,
+  ) -> Result<u32, AnyError> {
+    let actions: Vec<String> = notification
+      .actions
+      .into_iter()
+      .flat_map(|action| vec![action.id, action.label])
+      .collect();
+
+    let mut hints = PropMap::new();
+    if let Some(icon) = icon {
+      hints.insert(
+        "image-data".to_string(),
+        Variant(Box::new(icon.to_image_data())),
+      );
+    }
+    if let Some(urgency) = notification.urgency {
+      hints.insert("urgency".to_string(), Variant(Box::new(urgency as u8)));
+    }
+
+    let (id,): (u32,) = self
+      .connection
+      .with_proxy(NAME, PATH, TIMEOUT)
+      .method_call(
+        INTERFACE,
+        "Notify",
+        (
+          notification.app_name,
+          0u32,
+          "",
+          notification.title,
+          notification.body,
+          actions,
+          hints,
+          notification.timeout.unwrap_or(-1),
+        ),
+      )?;
+    self.open.borrow_mut().insert(id);
+
+    Ok(id)
+  }
+
+  /// Closes a notification, it is reported as closed once the server has
+  /// removed it.
+  pub fn close(&self, id: u32) -> Result<(), AnyError> {
+    let () = self
+      .connection
+      .with_proxy(NAME, PATH, TIMEOUT)
+      .method_call(INTERFACE, "CloseNotification", (id,))?;
+
+    Ok(())
+  }
+
+  /// Handles pending signals of the server, returning the events of the
+  /// notifications shown by this connection.
+  fn process(&self) -> Result<Vec<(u32, NotificationEvent)>, AnyError> {
+    while self.connection.process(Duration::from_millis(0))? {}
+
+    let mut open = self.open.borrow_mut();
+    let events = self
+      .signals
+      .try_iter()
+      .filter(|(id, event)| match event {
+        NotificationEvent::ActionInvoked { .. } => open.contains(id),
+        NotificationEvent::Closed { .. } => open.remove(id),
+        NotificationEvent::Error { .. } => true,
+      })
+      .collect();
+
+    Ok(events)
+  }
+}
+
+/// Processes the notification connection if one was created, adding the
+/// events of its notifications. A failing connection is reported as an error
+/// event of every open notification, rather than failing every following
+/// event loop step.
+pub fn process_notifications(state: &OpState, events: &mut Vec<Event>) {
+  if let Some(notifications) = state.try_borrow::<Notifications>() {
+    let notification_events = notifications.process().unwrap_or_else(|error| {
+      let message = error.to_string();
+      notifications
+        .open
+        .borrow()
+        .iter()
+        .map(|&id| {
+          let message = message.clone();
+          (id, NotificationEvent::Error { message })
+        })
+        .collect()
+    });
+
+    events.extend(notification_events.into_iter().map(
+      |(notification_id, event)| Event::NotificationEvent {
+        notification_id,
+        event,
+      },
+    ));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::sync::mpsc;
+  use std::thread;
+
+  use dbus::arg::RefArg;
+  use dbus::blocking::Connection;
+  use dbus::channel::MatchingReceiver;
+  use dbus::channel::Sender;
+  use dbus::Message;
+
+  use deno_core::JsRuntime;
+  use deno_core::RuntimeOptions;
+
+  use crate::helpers::test_bus::wait_for;
+  use crate::helpers::test_bus::TestBus;
+  use crate::icon::split_rgba;
+  use crate::icon::IconSize;
+
+  use super::*;
+
+  /// The arguments of a `Notify` call, as received by the server.
+  #[derive(Debug)]
+  struct Notify {
+    app_name: String,
+    title: String,
+    body: String,
+    actions: Vec<String>,
+    timeout: i32,
+    urgency: Option<u64>,
+    /// The signature and fields of the `image-data` hint, without pixels.
+    image: Option<(String, Vec<i64>, usize)>,
+  }
+
+  fn signal(member: &str, id: u32, arg: impl dbus::arg::Append) -> Message {
+    Message::new_signal(PATH, INTERFACE, member)
+      .unwrap()
+      .append2(id, arg)
+  }
+
+  fn read_notify(message: &Message) -> Notify {
+    let (app_name, _, _, title, body, actions, hints, timeout): (
+      String,
+      u32,
+      String,
+      String,
+      String,
+      Vec<String>,
+      PropMap,
+      i32,
+    ) = message.read_all().unwrap();
+
+    let image = hints.get("image-data").map(|image| {
+      let fields: Vec<_> = image.0.as_iter().unwrap().collect();
+      let numbers = fields[..6]
+        .iter()
+        .map(|field| {
+          field.as_i64().or_else(|| field.as_u64().map(|n| n as i64))
+        })
+        .map(Option::unwrap)
+        .collect();
+
+      (
+        image.0.signature().to_string(),
+        numbers,
+        fields[6].as_iter().unwrap().count(),
+      )
+    });
+
+    Notify {
+      app_name,
+      title,
+      body,
+      actions,
+      timeout,
+      urgency: hints.get("urgency").and_then(|urgency| urgency.0.as_u64()),
+      image,
+    }
+  }
+
+  /// Stands in for the notification server. Every notification is assigned
+  /// the id 41 and answered with signals, some of them for notifications of
+  /// other clients.
+  fn serve(connection: Connection, calls: mpsc::Sender<Notify>) {
+    connection.start_receive(
+      MatchRule::new_method_call(),
+      Box::new(move |message, connection| {
+        if message.member().as_deref() == Some("Notify") {
+          let _ = calls.send(read_notify(&message));
+          let _ = connection.send(message.method_return().append1(41u32));
+
+          let _ = connection.send(signal("ActionInvoked", 7, "reply"));
+          let _ = connection.send(signal("ActionInvoked", 41, "reply"));
+          let _ = connection.send(signal("NotificationClosed", 7, 1u32));
+          let _ = connection.send(signal("NotificationClosed", 41, 2u32));
+          let _ = connection.send(signal("ActionInvoked", 41, "late"));
+        }
+        true
+      }),
+    );
+
+    while connection.process(Duration::from_millis(100)).is_ok() {}
+  }
+
+  #[test]
+  fn shows_notifications_and_reports_their_events() {
+    let bus = match TestBus::start() {
+      Some(bus) => bus,
+      None => return,
+    };
+    let server = bus.connect_sync();
+    server.request_name(NAME, false, true, true).unwrap();
+    let (sender, calls) = mpsc::channel();
+    thread::spawn(move || serve(server, sender));
+    let notifications = Notifications::with_connection(bus.connect()).unwrap();
+
+    let rgba = [255u8; 2 * 3 * 4];
+    let icons = split_rgba(&rgba, &[IconSize::new(2, 3)]).unwrap();
+    let notification = Notification {
+      app_name: "pane".into(),
+      title: "Hello".into(),
+      body: "World".into(),
+      actions: vec![
+        NotificationAction {
+          id: "default".into(),
+          label: "Open".into(),
+        },
+        NotificationAction {
+          id: "reply".into(),
+          label: "Reply".into(),
+        },
+      ],
+      timeout: None,
+      urgency: Some(Urgency::Critical),
+    };
+    let id = notifications.show(notification, Some(&icons[0])).unwrap();
+    assert_eq!(id, 41);
+
+    let notify = calls.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(notify.app_name, "pane");
+    assert_eq!(notify.title, "Hello");
+    assert_eq!(notify.body, "World");
+    assert_eq!(notify.actions, ["default", "Open", "reply", "Reply"]);
+    assert_eq!(notify.timeout, -1);
+    assert_eq!(notify.urgency, Some(2));
+    assert_eq!(
+      notify.image,
+      Some(("(iiibiiay)".to_string(), vec![2, 3, 8, 1, 8, 4], 24))
+    );
+
+    let mut events = Vec::new();
+    wait_for(|| {
+      events.extend(notifications.process().unwrap());
+      if events.len() >= 2 {
+        Some(())
+      } else {
+        None
+      }
+    });
+    // Give stray events a chance to arrive.
+    thread::sleep(Duration::from_millis(50));
+    events.extend(notifications.process().unwrap());
+
+    assert_eq!(events.len(), 2, "{:?}", events);
+    assert!(matches!(
+      &events[0],
+      (41, NotificationEvent::ActionInvoked { action_id }) if action_id == "reply"
+    ));
+    assert!(matches!(
+      events[1],
+      (
+        41,
+        NotificationEvent::Closed {
+          reason: CloseReason::Dismissed
+        }
+      )
+    ));
+  }
+
+  #[test]
+  fn reports_connection_errors_as_events() {
+    let bus = match TestBus::start() {
+      Some(bus) => bus,
+      None => return,
+    };
+    let notifications = Notifications::with_connection(bus.connect()).unwrap();
+    notifications.open.borrow_mut().insert(7);
+    let mut runtime = JsRuntime::new(RuntimeOptions::default());
+    let state = runtime.op_state();
+    state.borrow_mut().put(notifications);
+    drop(bus);
+
+    // Every step reports the error, rather than failing.
+    for _ in 0..2 {
+      let mut events = Vec::new();
+      process_notifications(&state.borrow(), &mut events);
+      assert!(matches!(
+        events.as_slice(),
+        [Event::NotificationEvent {
+          notification_id: 7,
+          event: NotificationEvent::Error { .. },
+        }]
+      ));
+    }
+  }
+}
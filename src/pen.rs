@@ -0,0 +1,630 @@
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::mem::MaybeUninit;
+use std::os::raw::c_int;
+use std::ptr;
+use std::slice;
+
+use deno_core::error::generic_error;
+use deno_core::error::AnyError;
+use deno_core::OpState;
+
+use raw_window_handle::HasRawWindowHandle;
+use raw_window_handle::RawWindowHandle;
+
+use winit::dpi::PhysicalPosition;
+use winit::window::Window;
+
+use x11_dl::xinput2;
+use x11_dl::xinput2::XInput2;
+use x11_dl::xlib;
+use x11_dl::xlib::Xlib;
+
+use crate::event::Event;
+use crate::event::PenEvent;
+use crate::event::PenTool;
+use crate::event::WindowEvent;
+use crate::helpers::hash;
+
+/// The buttons of a pen, as numbered by the tablet drivers.
+const TIP_BUTTON: usize = 1;
+const BARREL_BUTTON: usize = 2;
+const SECONDARY_BARREL_BUTTON: usize = 3;
+
+/// An axis of a tablet, the range of its values and their resolution.
+#[derive(Debug, Clone, Copy)]
+pub struct Axis {
+  pub number: usize,
+  pub min: f64,
+  pub max: f64,
+  pub resolution: f64,
+}
+
+impl Axis {
+  /// Returns the value of the axis scaled from 0 to 1.
+  fn normalize(&self, values: &[f64]) -> Option<f64> {
+    let value = *values.get(self.number)?;
+
+    if self.max > self.min {
+      Some(((value - self.min) / (self.max - self.min)).clamp(0.0, 1.0))
+    } else {
+      None
+    }
+  }
+
+  /// Returns the value of a tilt axis in degrees. Tablet drivers give the
+  /// resolution of tilt axes in units per radian, without one the values are
+  /// taken to be degrees already.
+  fn degrees(&self, values: &[f64]) -> Option<f64> {
+    let value = *values.get(self.number)?;
+    let degrees = if self.resolution > 0.0 {
+      (value / self.resolution).to_degrees()
+    } else {
+      value
+    };
+
+    Some(degrees.clamp(-90.0, 90.0))
+  }
+}
+
+/// A pen or eraser of a tablet, and the last known values of its axes and
+/// buttons.
+///
+/// Devices are described by the labels of their axes, so recorded device
+/// sequences can be replayed without an X server.
+pub struct PenDevice {
+  tool: PenTool,
+  pressure: Axis,
+  tilt: Option<(Axis, Axis)>,
+  twist: Option<Axis>,
+  values: Vec<f64>,
+  buttons: Vec<bool>,
+}
+
+impl PenDevice {
+  /// Creates a device from its name and labelled axes, or returns `None` if
+  /// it is not a pen.
+  pub fn new(name: &str, axes: &[(String, Axis)]) -> Option<Self> {
+    let name = name.to_lowercase();
+    if name.contains("touch") || name.contains("finger") || name.contains("pad")
+    {
+      return None;
+    }
+
+    let axis = |label: &str| {
+      axes
+        .iter()
+        .find(|(axis_label, _)| axis_label == label)
+        .map(|(_, axis)| *axis)
+    };
+    let tilt = match (axis("Abs Tilt X"), axis("Abs Tilt Y")) {
+      (Some(x), Some(y)) => Some((x, y)),
+      _ => None,
+    };
+    let values = axes
+      .iter()
+      .map(|(_, axis)| axis.number + 1)
+      .max()
+      .unwrap_or(0);
+
+    Some(Self {
+      tool: if name.contains("eraser") {
+        PenTool::Eraser
+      } else {
+        PenTool::Pen
+      },
+      pressure: axis("Abs Pressure")?,
+      tilt,
+      // The art pen reports its rotation on the wheel axis of the wacom
+      // driver.
+      twist: axis("Abs Rotary Z").or_else(|| axis("Abs Wheel")),
+      values: vec![0.0; values],
+      buttons: Vec::new(),
+    })
+  }
+
+  /// Updates the axes that changed, given by number.
+  pub fn set_values(&mut self, values: impl IntoIterator<Item = (usize, f64)>) {
+    for (number, value) in values {
+      if let Some(known) = self.values.get_mut(number) {
+        *known = value;
+      }
+    }
+  }
+
+  /// Sets the state of all buttons, indexed by their number.
+  pub fn set_buttons(&mut self, buttons: Vec<bool>) {
+    self.buttons = buttons;
+  }
+
+  /// Sets the state of a single button, as it is pressed or released.
+  pub fn set_button(&mut self, button: usize, pressed: bool) {
+    if self.buttons.len() <= button {
+      self.buttons.resize(button + 1, false);
+    }
+    self.buttons[button] = pressed;
+  }
+
+  fn is_pressed(&self, button: usize) -> bool {
+    self.buttons.get(button).copied().unwrap_or(false)
+  }
+
+  /// Returns the current state of the pen at a location.
+  pub fn event(
+    &self,
+    device_id: u32,
+    location: PhysicalPosition<f64>,
+  ) -> PenEvent {
+    PenEvent {
+      device_id,
+      tool: self.tool,
+      location,
+      contact: self.is_pressed(TIP_BUTTON),
+      pressure: self.pressure.normalize(&self.values).unwrap_or(0.0),
+      tilt: self.tilt.and_then(|(x, y)| {
+        Some((x.degrees(&self.values)?, y.degrees(&self.values)?))
+      }),
+      twist: self
+        .twist
+        .and_then(|twist| twist.normalize(&self.values))
+        .map(|twist| twist * 360.0),
+      barrel_button: self.is_pressed(BARREL_BUTTON),
+      secondary_barrel_button: self.is_pressed(SECONDARY_BARREL_BUTTON),
+    }
+  }
+}
+
+/// Reads tablets through XInput2 on a connection of its own, reporting pens
+/// over pane windows as `pen` window events.
+///
+/// Motion events are not exclusive to a client, so they can be selected next
+/// to winit. Button events are, but winit only selects them for the master
+/// pointer, so they are selected for the tablets themselves. Each event
+/// carries the axes and buttons of the device.
+pub struct PenInput {
+  xlib: Xlib,
+  xinput2: XInput2,
+  display: *mut xlib::Display,
+  opcode: c_int,
+  devices: HashMap<c_int, PenDevice>,
+  windows: HashMap<xlib::Window, u32>,
+}
+
+impl PenInput {
+  pub fn open() -> Result<Self, AnyError> {
+    let xlib = Xlib::open()?;
+    let xinput2 = XInput2::open()?;
+
+    unsafe {
+      let display = (xlib.XOpenDisplay)(ptr::null());
+      if display.is_null() {
+        return Err(generic_error("XOpenDisplay failed"));
+      }
+
+      let mut opcode = 0;
+      let mut event = 0;
+      let mut error = 0;
+      let mut major = 2;
+      let mut minor = 2;
+      if (xlib.XQueryExtension)(
+        display,
+        b"XInputExtension\0".as_ptr() as *const _,
+        &mut opcode,
+        &mut event,
+        &mut error,
+      ) == 0
+        || (xinput2.XIQueryVersion)(display, &mut major, &mut minor)
+          != xlib::Success as c_int
+      {
+        (xlib.XCloseDisplay)(display);
+        return Err(generic_error("XInput2 is not available"));
+      }
+
+      let mut pen_input = Self {
+        xlib,
+        xinput2,
+        display,
+        opcode,
+        devices: HashMap::new(),
+        windows: HashMap::new(),
+      };
+
+      // Tablets are queried again when devices are plugged in or removed.
+      let root = (pen_input.xlib.XDefaultRootWindow)(display);
+      pen_input.select_events(
+        root,
+        xinput2::XIAllDevices,
+        &[xinput2::XI_HierarchyChanged],
+      );
+      pen_input.query_devices();
+
+      Ok(pen_input)
+    }
+  }
+
+  /// Starts reporting pens over a window. Returns `false` if the window is
+  /// not an X11 window.
+  pub fn add_window(&mut self, window: &Window) -> bool {
+    let x_window = match window.raw_window_handle() {
+      RawWindowHandle::Xlib(handle) => handle.window,
+      _ => return false,
+    };
+
+    self.windows.insert(x_window, hash(window.id()));
+    unsafe {
+      self.select_events(
+        x_window,
+        xinput2::XIAllDevices,
+        &[xinput2::XI_Motion],
+      );
+      self.select_buttons(x_window);
+      (self.xlib.XSelectInput)(
+        self.display,
+        x_window,
+        xlib::StructureNotifyMask,
+      );
+      (self.xlib.XFlush)(self.display);
+    }
+
+    true
+  }
+
+  unsafe fn select_events(
+    &self,
+    x_window: xlib::Window,
+    deviceid: c_int,
+    events: &[c_int],
+  ) {
+    let mut mask = [0; (xinput2::XI_LASTEVENT as usize >> 3) + 1];
+    for event in events {
+      xinput2::XISetMask(&mut mask, *event);
+    }
+    let mut event_mask = xinput2::XIEventMask {
+      deviceid,
+      mask_len: mask.len() as c_int,
+      mask: mask.as_mut_ptr(),
+    };
+
+    (self.xinput2.XISelectEvents)(self.display, x_window, &mut event_mask, 1);
+  }
+
+  /// Selects the button events of every pen over a window.
+  unsafe fn select_buttons(&self, x_window: xlib::Window) {
+    for deviceid in self.devices.keys() {
+      self.select_events(
+        x_window,
+        *deviceid,
+        &[xinput2::XI_ButtonPress, xinput2::XI_ButtonRelease],
+      );
+    }
+  }
+
+  unsafe fn query_devices(&mut self) {
+    let mut count = 0;
+    let infos = (self.xinput2.XIQueryDevice)(
+      self.display,
+      xinput2::XIAllDevices,
+      &mut count,
+    );
+    if infos.is_null() {
+      return;
+    }
+
+    self.devices.clear();
+    for info in slice::from_raw_parts(infos, count as usize) {
+      if info._use != xinput2::XISlavePointer {
+        continue;
+      }
+
+      let name = CStr::from_ptr(info.name).to_string_lossy();
+      let axes: Vec<_> =
+        slice::from_raw_parts(info.classes, info.num_classes as usize)
+          .iter()
+          .filter(|class| (***class)._type == xinput2::XIValuatorClass)
+          .map(|class| &*(*class as *const xinput2::XIValuatorClassInfo))
+          .map(|valuator| {
+            let axis = Axis {
+              number: valuator.number as usize,
+              min: valuator.min,
+              max: valuator.max,
+              resolution: valuator.resolution as f64,
+            };
+            (self.atom_name(valuator.label), axis)
+          })
+          .collect();
+
+      if let Some(device) = PenDevice::new(&name, &axes) {
+        self.devices.insert(info.deviceid, device);
+      }
+    }
+
+    (self.xinput2.XIFreeDeviceInfo)(infos);
+
+    for x_window in self.windows.keys() {
+      self.select_buttons(*x_window);
+    }
+    (self.xlib.XFlush)(self.display);
+  }
+
+  unsafe fn atom_name(&self, atom: xlib::Atom) -> String {
+    if atom == 0 {
+      return String::new();
+    }
+
+    let name = (self.xlib.XGetAtomName)(self.display, atom);
+    if name.is_null() {
+      return String::new();
+    }
+    let label = CStr::from_ptr(name).to_string_lossy().into_owned();
+    (self.xlib.XFree)(name as *mut _);
+
+    label
+  }
+
+  /// Handles pending events of the connection, returning the pen events of
+  /// pane windows.
+  fn process(&mut self) -> Vec<Event> {
+    let mut events = Vec::new();
+
+    unsafe {
+      while (self.xlib.XPending)(self.display) > 0 {
+        let mut event = MaybeUninit::<xlib::XEvent>::uninit();
+        (self.xlib.XNextEvent)(self.display, event.as_mut_ptr());
+        let event = event.assume_init();
+
+        match event.get_type() {
+          xlib::GenericEvent => {
+            let mut cookie = event.generic_event_cookie;
+            if cookie.extension != self.opcode
+              || (self.xlib.XGetEventData)(self.display, &mut cookie) == 0
+            {
+              continue;
+            }
+
+            events.extend(self.handle_event(&cookie));
+            (self.xlib.XFreeEventData)(self.display, &mut cookie);
+          }
+          xlib::DestroyNotify => {
+            self.windows.remove(&event.destroy_window.window);
+          }
+          _ => {}
+        }
+      }
+    }
+
+    events
+  }
+
+  unsafe fn handle_event(
+    &mut self,
+    cookie: &xlib::XGenericEventCookie,
+  ) -> Option<Event> {
+    match cookie.evtype {
+      xinput2::XI_HierarchyChanged => {
+        self.query_devices();
+        None
+      }
+      xinput2::XI_Motion
+      | xinput2::XI_ButtonPress
+      | xinput2::XI_ButtonRelease => {
+        let event = &*(cookie.data as *const xinput2::XIDeviceEvent);
+        // The master pointer reports the same motion, without the axes of
+        // the tablet.
+        if event.deviceid != event.sourceid {
+          return None;
+        }
+        let window_id = *self.windows.get(&event.event)?;
+        let device = self.devices.get_mut(&event.deviceid)?;
+
+        device.set_values(valuator_values(&event.valuators));
+        device.set_buttons(button_states(&event.buttons));
+        // The buttons of an event are those held before it.
+        match cookie.evtype {
+          xinput2::XI_ButtonPress => {
+            device.set_button(event.detail as usize, true)
+          }
+          xinput2::XI_ButtonRelease => {
+            device.set_button(event.detail as usize, false)
+          }
+          _ => {}
+        }
+
+        Some(Event::WindowEvent {
+          window_id,
+          event: WindowEvent::Pen(device.event(
+            event.deviceid as u32,
+            PhysicalPosition::new(event.event_x, event.event_y),
+          )),
+        })
+      }
+      _ => None,
+    }
+  }
+}
+
+impl Drop for PenInput {
+  fn drop(&mut self) {
+    unsafe { (self.xlib.XCloseDisplay)(self.display) };
+  }
+}
+
+/// Returns the axes that are set in an event, the values only contain those
+/// axes set in the mask.
+unsafe fn valuator_values(
+  valuators: &xinput2::XIValuatorState,
+) -> Vec<(usize, f64)> {
+  let mask = slice::from_raw_parts(valuators.mask, valuators.mask_len as usize);
+  let numbers: Vec<usize> = (0..mask.len() * 8)
+    .filter(|number| xinput2::XIMaskIsSet(mask, *number as c_int))
+    .collect();
+  let values = slice::from_raw_parts(valuators.values, numbers.len());
+
+  numbers.into_iter().zip(values.iter().copied()).collect()
+}
+
+unsafe fn button_states(buttons: &xinput2::XIButtonState) -> Vec<bool> {
+  let mask = slice::from_raw_parts(buttons.mask, buttons.mask_len as usize);
+
+  (0..mask.len() * 8)
+    .map(|button| xinput2::XIMaskIsSet(mask, button as c_int))
+    .collect()
+}
+
+/// Processes the tablet connection if there is one, adding the events of
+/// pens over pane windows.
+pub fn process_pen_input(state: &mut OpState, events: &mut Vec<Event>) {
+  if let Some(pen_input) = state.try_borrow_mut::<PenInput>() {
+    events.extend(pen_input.process());
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn axis(number: usize, min: f64, max: f64, resolution: f64) -> Axis {
+    Axis {
+      number,
+      min,
+      max,
+      resolution,
+    }
+  }
+
+  /// The axes of a Wacom Intuos pen as reported by the wacom driver, with
+  /// tilt in units of about a degree.
+  fn intuos_axes() -> Vec<(String, Axis)> {
+    vec![
+      ("Abs X".to_string(), axis(0, 0.0, 44704.0, 200000.0)),
+      ("Abs Y".to_string(), axis(1, 0.0, 27940.0, 200000.0)),
+      ("Abs Pressure".to_string(), axis(2, 0.0, 65535.0, 0.0)),
+      ("Abs Tilt X".to_string(), axis(3, -64.0, 63.0, 57.0)),
+      ("Abs Tilt Y".to_string(), axis(4, -64.0, 63.0, 57.0)),
+      ("Abs Wheel".to_string(), axis(5, 0.0, 1023.0, 0.0)),
+    ]
+  }
+
+  fn location() -> PhysicalPosition<f64> {
+    PhysicalPosition::new(12.5, 40.0)
+  }
+
+  #[test]
+  fn only_accepts_pens_with_pressure() {
+    let axes = intuos_axes();
+
+    assert!(PenDevice::new("Wacom Intuos Pro M Pen stylus", &axes).is_some());
+    assert!(PenDevice::new("Wacom Intuos Pro M Finger touch", &axes).is_none());
+    assert!(PenDevice::new("Wacom Intuos Pro M Pad pad", &axes).is_none());
+    assert!(PenDevice::new("Logitech USB Mouse", &axes[..2]).is_none());
+  }
+
+  #[test]
+  fn tells_erasers_from_pens() {
+    let axes = intuos_axes();
+    let pen = PenDevice::new("Wacom Intuos Pro M Pen stylus", &axes).unwrap();
+    let eraser =
+      PenDevice::new("Wacom Intuos Pro M Pen eraser", &axes).unwrap();
+
+    assert_eq!(pen.event(3, location()).tool, PenTool::Pen);
+    assert_eq!(eraser.event(4, location()).tool, PenTool::Eraser);
+  }
+
+  #[test]
+  fn replays_a_stroke() {
+    let mut pen =
+      PenDevice::new("Wacom Intuos Pro M Pen stylus", &intuos_axes()).unwrap();
+
+    // Hovering, only some axes are sent with the first event.
+    pen.set_values(vec![(0, 1000.0), (1, 2000.0), (3, 0.0), (4, 0.0)]);
+    let event = pen.event(7, location());
+    assert_eq!(event.device_id, 7);
+    assert_eq!(event.location, location());
+    assert!(!event.contact);
+    assert_eq!(event.pressure, 0.0);
+    assert_eq!(event.tilt, Some((0.0, 0.0)));
+
+    // The tip touches the tablet, leaning right and away from the user.
+    pen.set_button(TIP_BUTTON, true);
+    pen.set_values(vec![(2, 65535.0 / 4.0), (3, 57.0), (4, -28.5)]);
+    let event = pen.event(7, location());
+    assert!(event.contact);
+    assert_eq!(event.pressure, 0.25);
+    let (x, y) = event.tilt.unwrap();
+    assert!((x - 1.0f64.to_degrees()).abs() < 1e-9);
+    assert!((y + 0.5f64.to_degrees()).abs() < 1e-9);
+
+    // Axes that are not sent keep their values.
+    pen.set_values(vec![(2, 65535.0)]);
+    let event = pen.event(7, location());
+    assert_eq!(event.pressure, 1.0);
+    assert_eq!(event.tilt, Some((x, y)));
+
+    pen.set_button(TIP_BUTTON, false);
+    assert!(!pen.event(7, location()).contact);
+  }
+
+  #[test]
+  fn reports_barrel_buttons() {
+    let mut pen =
+      PenDevice::new("Wacom Intuos Pro M Pen stylus", &intuos_axes()).unwrap();
+
+    // The button state of motion events, indexed by button number.
+    pen.set_buttons(vec![false, false, true, false]);
+    let event = pen.event(7, location());
+    assert!(event.barrel_button);
+    assert!(!event.secondary_barrel_button);
+
+    pen.set_button(SECONDARY_BARREL_BUTTON, true);
+    pen.set_button(BARREL_BUTTON, false);
+    let event = pen.event(7, location());
+    assert!(!event.barrel_button);
+    assert!(event.secondary_barrel_button);
+    assert!(!event.contact);
+  }
+
+  #[test]
+  fn reports_tilt_in_degrees() {
+    let axes = vec![
+      ("Abs Pressure".to_string(), axis(0, 0.0, 1.0, 0.0)),
+      ("Abs Tilt X".to_string(), axis(1, -90.0, 90.0, 0.0)),
+      // One unit per hundredth of a radian.
+      ("Abs Tilt Y".to_string(), axis(2, -157.0, 157.0, 100.0)),
+    ];
+    let mut pen = PenDevice::new("Generic Tablet Pen", &axes).unwrap();
+
+    pen.set_values(vec![(1, 30.0), (2, 157.0)]);
+    let (x, y) = pen.event(1, location()).tilt.unwrap();
+    assert_eq!(x, 30.0);
+    assert!((y - 1.57f64.to_degrees()).abs() < 1e-9);
+
+    // Tilt never exceeds lying flat.
+    pen.set_values(vec![(1, -120.0)]);
+    assert_eq!(pen.event(1, location()).tilt.unwrap().0, -90.0);
+  }
+
+  #[test]
+  fn reports_twist_around_the_pen() {
+    let mut pen =
+      PenDevice::new("Wacom Intuos Pro M Art Pen stylus", &intuos_axes())
+        .unwrap();
+
+    assert_eq!(pen.event(7, location()).twist, Some(0.0));
+    pen.set_values(vec![(5, 1023.0 / 4.0)]);
+    assert_eq!(pen.event(7, location()).twist, Some(90.0));
+
+    let axes = &intuos_axes()[..3];
+    let pen = PenDevice::new("Wacom Bamboo Pen stylus", axes).unwrap();
+    let event = pen.event(7, location());
+    assert_eq!(event.twist, None);
+    assert_eq!(event.tilt, None);
+  }
+
+  #[test]
+  fn ignores_unknown_axes() {
+    let mut pen =
+      PenDevice::new("Wacom Intuos Pro M Pen stylus", &intuos_axes()).unwrap();
+
+    pen.set_values(vec![(40, 1.0), (2, 65535.0)]);
+    assert_eq!(pen.event(7, location()).pressure, 1.0);
+  }
+}
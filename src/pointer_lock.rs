@@ -0,0 +1,300 @@
+use std::cell::Cell;
+
+use deno_core::error::AnyError;
+
+use winit::dpi::PhysicalPosition;
+use winit::event::WindowEvent;
+use winit::window::Window;
+
+use crate::state::WindowState;
+
+/// The cursor of a window, as a pointer lock changes it.
+pub trait Cursor {
+  fn set_grab(&self, grab: bool) -> Result<(), AnyError>;
+  fn set_visible(&self, visible: bool);
+  /// Moves the cursor to the middle of the window.
+  fn recenter(&self) -> Result<(), AnyError>;
+}
+
+impl Cursor for Window {
+  fn set_grab(&self, grab: bool) -> Result<(), AnyError> {
+    Ok(self.set_cursor_grab(grab)?)
+  }
+
+  fn set_visible(&self, visible: bool) {
+    self.set_cursor_visible(visible);
+  }
+
+  fn recenter(&self) -> Result<(), AnyError> {
+    let size = self.inner_size();
+    let center = PhysicalPosition::new(size.width / 2, size.height / 2);
+
+    Ok(self.set_cursor_position(center)?)
+  }
+}
+
+/// Locks the pointer to a window, hiding and confining the cursor so only its
+/// relative motion is reported, as for first person cameras.
+///
+/// The lock is only engaged while the window is focused, it is released when
+/// the window loses focus and engaged again once it regains it.
+#[derive(Default)]
+pub struct PointerLock {
+  requested: Cell<bool>,
+  engaged: Cell<bool>,
+  focused: Cell<bool>,
+}
+
+impl PointerLock {
+  /// Whether the pointer is currently locked to the window.
+  pub fn is_engaged(&self) -> bool {
+    self.engaged.get()
+  }
+
+  /// Locks or unlocks the pointer, right away if the window is focused or else
+  /// once it is. Unlocking restores the cursor grab and visibility of the
+  /// window state.
+  pub fn set(
+    &self,
+    cursor: &impl Cursor,
+    state: &WindowState,
+    locked: bool,
+  ) -> Result<(), AnyError> {
+    if !locked {
+      self.release(cursor, state);
+    } else if self.focused.get() {
+      self.engage(cursor, state)?;
+    }
+    self.requested.set(locked);
+
+    Ok(())
+  }
+
+  /// Grabs the cursor and moves it to the middle of the window before hiding
+  /// it. If either fails the cursor grab and visibility of the window state
+  /// are restored, and the pointer is not locked.
+  fn engage(
+    &self,
+    cursor: &impl Cursor,
+    state: &WindowState,
+  ) -> Result<(), AnyError> {
+    if let Err(error) = cursor.set_grab(true).and_then(|()| cursor.recenter()) {
+      self.engaged.set(false);
+      let _ = cursor.set_grab(state.cursor_grab);
+      cursor.set_visible(state.cursor_visible);
+
+      return Err(error);
+    }
+
+    cursor.set_visible(false);
+    self.engaged.set(true);
+
+    Ok(())
+  }
+
+  fn release(&self, cursor: &impl Cursor, state: &WindowState) {
+    if self.engaged.replace(false) {
+      let _ = cursor.set_grab(state.cursor_grab);
+      cursor.set_visible(state.cursor_visible);
+    }
+  }
+
+  /// Handles an event of the window, returning whether it must be dropped as
+  /// the pointer is locked.
+  pub fn handle_event(
+    &self,
+    cursor: &impl Cursor,
+    state: &WindowState,
+    event: &WindowEvent,
+  ) -> bool {
+    match event {
+      WindowEvent::Focused(true) => {
+        self.focused.set(true);
+        if self.requested.get() && !self.engaged.get() {
+          let _ = self.engage(cursor, state);
+        }
+        false
+      }
+      WindowEvent::Focused(false) => {
+        self.focused.set(false);
+        self.release(cursor, state);
+        false
+      }
+      // The cursor is kept in the middle of the window, so it never reaches
+      // the edges of the screen.
+      WindowEvent::CursorMoved { .. }
+      | WindowEvent::CursorEntered { .. }
+      | WindowEvent::CursorLeft { .. }
+        if self.engaged.get() =>
+      {
+        let _ = cursor.recenter();
+        true
+      }
+      _ => false,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::cell::RefCell;
+
+  use deno_core::error::generic_error;
+
+  use winit::event::DeviceId;
+
+  use super::*;
+
+  /// Records what the pointer lock does to the cursor.
+  #[derive(Default)]
+  struct FakeCursor {
+    grabbed: Cell<bool>,
+    visible: Cell<bool>,
+    recentered: Cell<usize>,
+    /// Makes grabbing the cursor fail, as it does while another application
+    /// has grabbed it.
+    grab_fails: Cell<bool>,
+    grabs: RefCell<Vec<bool>>,
+  }
+
+  impl Cursor for FakeCursor {
+    fn set_grab(&self, grab: bool) -> Result<(), AnyError> {
+      self.grabs.borrow_mut().push(grab);
+      if grab && self.grab_fails.get() {
+        return Err(generic_error("The cursor is grabbed"));
+      }
+      self.grabbed.set(grab);
+
+      Ok(())
+    }
+
+    fn set_visible(&self, visible: bool) {
+      self.visible.set(visible);
+    }
+
+    fn recenter(&self) -> Result<(), AnyError> {
+      self.recentered.set(self.recentered.get() + 1);
+
+      Ok(())
+    }
+  }
+
+  fn cursor() -> FakeCursor {
+    let cursor = FakeCursor::default();
+    cursor.visible.set(true);
+    cursor
+  }
+
+  fn focused(focused: bool) -> WindowEvent<'static> {
+    WindowEvent::Focused(focused)
+  }
+
+  #[allow(deprecated)]
+  fn cursor_moved() -> WindowEvent<'static> {
+    WindowEvent::CursorMoved {
+      device_id: unsafe { DeviceId::dummy() },
+      position: PhysicalPosition::new(1.0, 2.0),
+      modifiers: Default::default(),
+    }
+  }
+
+  /// A pointer lock of a window that has focus.
+  fn focused_lock(cursor: &FakeCursor, state: &WindowState) -> PointerLock {
+    let lock = PointerLock::default();
+    lock.handle_event(cursor, state, &focused(true));
+    lock
+  }
+
+  #[test]
+  fn engages_right_away_in_focused_windows() {
+    let (cursor, state) = (cursor(), WindowState::default());
+    let lock = focused_lock(&cursor, &state);
+
+    lock.set(&cursor, &state, true).unwrap();
+    assert!(lock.is_engaged());
+    assert!(cursor.grabbed.get());
+    assert!(!cursor.visible.get());
+    assert_eq!(cursor.recentered.get(), 1);
+  }
+
+  #[test]
+  fn engages_once_an_unfocused_window_is_focused() {
+    let (cursor, state) = (cursor(), WindowState::default());
+    let lock = PointerLock::default();
+
+    lock.set(&cursor, &state, true).unwrap();
+    assert!(!lock.is_engaged());
+    assert!(cursor.grabs.borrow().is_empty());
+    assert!(cursor.visible.get());
+
+    assert!(!lock.handle_event(&cursor, &state, &focused(true)));
+    assert!(lock.is_engaged());
+    assert!(cursor.grabbed.get());
+    assert!(!cursor.visible.get());
+  }
+
+  #[test]
+  fn releases_while_unfocused() {
+    let (cursor, state) = (cursor(), WindowState::default());
+    let lock = focused_lock(&cursor, &state);
+    lock.set(&cursor, &state, true).unwrap();
+
+    assert!(!lock.handle_event(&cursor, &state, &focused(false)));
+    assert!(!lock.is_engaged());
+    assert!(!cursor.grabbed.get());
+    assert!(cursor.visible.get());
+
+    // Cursor events are reported again while released.
+    assert!(!lock.handle_event(&cursor, &state, &cursor_moved()));
+
+    assert!(!lock.handle_event(&cursor, &state, &focused(true)));
+    assert!(lock.is_engaged());
+    assert!(cursor.grabbed.get());
+  }
+
+  #[test]
+  fn drops_cursor_events_while_engaged() {
+    let (cursor, state) = (cursor(), WindowState::default());
+    let lock = focused_lock(&cursor, &state);
+    assert!(!lock.handle_event(&cursor, &state, &cursor_moved()));
+
+    lock.set(&cursor, &state, true).unwrap();
+    assert!(lock.handle_event(&cursor, &state, &cursor_moved()));
+    assert_eq!(cursor.recentered.get(), 2);
+  }
+
+  #[test]
+  fn restores_the_window_state_when_unlocked() {
+    let (cursor, mut state) = (cursor(), WindowState::default());
+    state.cursor_grab = true;
+    state.cursor_visible = false;
+    let lock = focused_lock(&cursor, &state);
+    lock.set(&cursor, &state, true).unwrap();
+
+    lock.set(&cursor, &state, false).unwrap();
+    assert!(!lock.is_engaged());
+    assert!(cursor.grabbed.get());
+    assert!(!cursor.visible.get());
+
+    // Focus no longer engages the lock.
+    lock.handle_event(&cursor, &state, &focused(false));
+    lock.handle_event(&cursor, &state, &focused(true));
+    assert!(!lock.is_engaged());
+  }
+
+  #[test]
+  fn rolls_back_failed_grabs() {
+    let (cursor, state) = (cursor(), WindowState::default());
+    cursor.grab_fails.set(true);
+    let lock = focused_lock(&cursor, &state);
+
+    assert!(lock.set(&cursor, &state, true).is_err());
+    assert!(!lock.is_engaged());
+    assert_eq!(*cursor.grabs.borrow(), [true, false]);
+    assert!(cursor.visible.get());
+
+    // A failed lock is not engaged by focus either.
+    lock.handle_event(&cursor, &state, &focused(true));
+    assert!(!lock.is_engaged());
+  }
+}
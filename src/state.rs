@@ -0,0 +1,143 @@
+use deno_core::error::AnyError;
+use deno_core::serde::Serialize;
+
+use winit::dpi::Size;
+use winit::window::Window;
+
+/// The state of a window as last set through pane, merged with what can be
+/// queried from the platform by `WindowState::query`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowState {
+  pub title: String,
+  pub visible: bool,
+  pub resizable: bool,
+  pub minimized: bool,
+  pub maximized: bool,
+  pub fullscreen: bool,
+  pub decorations: bool,
+  pub always_on_top: bool,
+  pub transparent: bool,
+  pub opacity: f64,
+  pub min_inner_size: Option<Size>,
+  pub max_inner_size: Option<Size>,
+  pub cursor_visible: bool,
+  pub cursor_grab: bool,
+}
+
+impl Default for WindowState {
+  /// The state of a window created with winit's defaults.
+  fn default() -> Self {
+    Self {
+      title: "winit window".into(),
+      visible: true,
+      resizable: true,
+      minimized: false,
+      maximized: false,
+      fullscreen: false,
+      decorations: true,
+      always_on_top: false,
+      transparent: false,
+      opacity: 1.0,
+      min_inner_size: None,
+      max_inner_size: None,
+      cursor_visible: true,
+      cursor_grab: false,
+    }
+  }
+}
+
+impl WindowState {
+  /// Returns the cached state, updated with the live state of the window
+  /// where it can be queried. The window manager may have changed it since it
+  /// was set, for example when the user minimizes the window.
+  pub fn query(&self, window: &Window) -> Result<Self, AnyError> {
+    let mut state = self.clone();
+
+    state.maximized = window.is_maximized();
+    state.fullscreen = window.fullscreen().is_some();
+
+    #[cfg(any(
+      target_os = "linux",
+      target_os = "dragonfly",
+      target_os = "freebsd",
+      target_os = "netbsd",
+      target_os = "openbsd"
+    ))]
+    if let Some(window) = crate::x11::XWindow::new(window)? {
+      let net_wm_state =
+        window.get_longs("_NET_WM_STATE", x11_dl::xlib::XA_ATOM)?;
+      let has_state = |name| net_wm_state.contains(&window.atom(name));
+
+      state.visible = window.is_mapped()? || has_state("_NET_WM_STATE_HIDDEN");
+      state.minimized = has_state("_NET_WM_STATE_HIDDEN");
+      state.always_on_top = has_state("_NET_WM_STATE_ABOVE");
+      state.fullscreen = has_state("_NET_WM_STATE_FULLSCREEN");
+    }
+
+    Ok(state)
+  }
+}
+
+#[cfg(test)]
+#[cfg(any(
+  target_os = "linux",
+  target_os = "dragonfly",
+  target_os = "freebsd",
+  target_os = "netbsd",
+  target_os = "openbsd"
+))]
+mod tests {
+  use winit::event_loop::EventLoop;
+  use winit::platform::unix::EventLoopExtUnix;
+  use winit::window::WindowBuilder;
+
+  use crate::x11::XWindow;
+
+  use super::*;
+
+  /// Queries the state of a window after replacing its `_NET_WM_STATE`, as a
+  /// window manager would.
+  fn query_net_wm_state(names: &[&str]) -> WindowState {
+    let event_loop = EventLoop::<()>::new_any_thread();
+    let window = WindowBuilder::new().build(&event_loop).unwrap();
+    XWindow::new(&window)
+      .unwrap()
+      .expect("an X11 window")
+      .set_atoms("_NET_WM_STATE", names);
+
+    WindowState::default().query(&window).unwrap()
+  }
+
+  #[test]
+  #[ignore = "needs an X server, run with xvfb-run cargo test -- --ignored"]
+  fn reads_minimized_windows() {
+    let state = query_net_wm_state(&["_NET_WM_STATE_HIDDEN"]);
+
+    assert!(state.minimized);
+    assert!(state.visible);
+    assert!(!state.always_on_top);
+    assert!(!state.fullscreen);
+  }
+
+  #[test]
+  #[ignore = "needs an X server, run with xvfb-run cargo test -- --ignored"]
+  fn reads_always_on_top_and_fullscreen_windows() {
+    let state =
+      query_net_wm_state(&["_NET_WM_STATE_ABOVE", "_NET_WM_STATE_FULLSCREEN"]);
+
+    assert!(!state.minimized);
+    assert!(state.always_on_top);
+    assert!(state.fullscreen);
+  }
+
+  #[test]
+  #[ignore = "needs an X server, run with xvfb-run cargo test -- --ignored"]
+  fn reads_windows_without_state() {
+    let state = query_net_wm_state(&[]);
+
+    assert!(!state.minimized);
+    assert!(!state.always_on_top);
+    assert!(!state.fullscreen);
+  }
+}
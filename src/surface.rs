@@ -0,0 +1,281 @@
+use std::cell::Cell;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use winit::dpi::PhysicalSize;
+
+/// Tells a surface created from a window's raw handle that it needs to be
+/// reconfigured, or that it must not be used anymore.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SurfaceEvent {
+  /// The window's client area changed to a new, non-zero size.
+  Resized(PhysicalSize<u32>),
+  /// The window's scale factor changed, along with its client area.
+  ScaleFactorChanged {
+    scale_factor: f64,
+    inner_size: PhysicalSize<u32>,
+  },
+  /// The window was minimized, its client area may have no size. Surfaces
+  /// should not be presented to until it is resized.
+  Minimized,
+  /// The window is about to be destroyed, surfaces must be dropped before
+  /// this listener returns.
+  Destroying,
+}
+
+impl SurfaceEvent {
+  /// Returns whether the window is minimized after a window event, and the
+  /// surface event the window event implies. Not every platform reports
+  /// minimizing with a zero size, so `is_minimized` is queried on events that
+  /// may come with minimizing or restoring the window. A restored window is
+  /// reported as resized to `inner_size`.
+  pub(crate) fn from_window_event(
+    event: &winit::event::WindowEvent,
+    was_minimized: bool,
+    is_minimized: impl FnOnce() -> bool,
+    inner_size: impl FnOnce() -> PhysicalSize<u32>,
+  ) -> (bool, Option<Self>) {
+    let minimized = match event {
+      winit::event::WindowEvent::Resized(size)
+        if size.width == 0 || size.height == 0 =>
+      {
+        true
+      }
+      winit::event::WindowEvent::Resized(_)
+      | winit::event::WindowEvent::Moved(_)
+      | winit::event::WindowEvent::Focused(_) => is_minimized(),
+      _ => was_minimized,
+    };
+
+    let surface_event = match event {
+      _ if minimized => {
+        if was_minimized {
+          None
+        } else {
+          Some(SurfaceEvent::Minimized)
+        }
+      }
+      winit::event::WindowEvent::Resized(size) => {
+        Some(SurfaceEvent::Resized(*size))
+      }
+      winit::event::WindowEvent::ScaleFactorChanged {
+        scale_factor,
+        new_inner_size,
+      } => Some(SurfaceEvent::ScaleFactorChanged {
+        scale_factor: *scale_factor,
+        inner_size: **new_inner_size,
+      }),
+      _ if was_minimized => Some(SurfaceEvent::Resized(inner_size())),
+      _ => None,
+    };
+
+    (minimized, surface_event)
+  }
+}
+
+/// Identifies a listener added with `WindowResource::add_surface_listener`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListenerId(u64);
+
+type Listener = Rc<dyn Fn(&SurfaceEvent)>;
+
+#[derive(Default)]
+pub(crate) struct SurfaceListeners {
+  next_id: Cell<u64>,
+  listeners: RefCell<Vec<(ListenerId, Listener)>>,
+  /// Whether the window was minimized as of the last window event.
+  minimized: Cell<bool>,
+}
+
+impl SurfaceListeners {
+  pub fn add(&self, listener: Listener) -> ListenerId {
+    let id = ListenerId(self.next_id.get());
+    self.next_id.set(id.0 + 1);
+    self.listeners.borrow_mut().push((id, listener));
+    id
+  }
+
+  pub fn remove(&self, id: ListenerId) -> bool {
+    let mut listeners = self.listeners.borrow_mut();
+    let len = listeners.len();
+    listeners.retain(|(listener_id, _)| *listener_id != id);
+    listeners.len() != len
+  }
+
+  /// Calls the listeners with an event. The listeners are collected first, so
+  /// they can add and remove listeners.
+  pub fn dispatch(&self, event: &SurfaceEvent) {
+    let listeners: Vec<Listener> = self
+      .listeners
+      .borrow()
+      .iter()
+      .map(|(_, listener)| listener.clone())
+      .collect();
+
+    for listener in listeners {
+      listener(event);
+    }
+  }
+
+  /// Calls the listeners with the surface event a window event implies, see
+  /// `SurfaceEvent::from_window_event`.
+  pub fn dispatch_window_event(
+    &self,
+    event: &winit::event::WindowEvent,
+    is_minimized: impl FnOnce() -> bool,
+    inner_size: impl FnOnce() -> PhysicalSize<u32>,
+  ) {
+    let (minimized, surface_event) = SurfaceEvent::from_window_event(
+      event,
+      self.minimized.get(),
+      is_minimized,
+      inner_size,
+    );
+    self.minimized.set(minimized);
+
+    if let Some(surface_event) = surface_event {
+      self.dispatch(&surface_event);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use winit::event::WindowEvent;
+
+  use super::*;
+
+  fn map(
+    event: &WindowEvent,
+    was_minimized: bool,
+    is_minimized: bool,
+  ) -> (bool, Option<SurfaceEvent>) {
+    SurfaceEvent::from_window_event(
+      event,
+      was_minimized,
+      || is_minimized,
+      || PhysicalSize::new(640, 480),
+    )
+  }
+
+  fn unqueried(
+    event: &WindowEvent,
+    was_minimized: bool,
+  ) -> (bool, Option<SurfaceEvent>) {
+    SurfaceEvent::from_window_event(
+      event,
+      was_minimized,
+      || panic!("the window state was queried"),
+      || panic!("the size was queried"),
+    )
+  }
+
+  #[test]
+  fn maps_resizes() {
+    let event = WindowEvent::Resized(PhysicalSize::new(800, 600));
+
+    assert_eq!(
+      map(&event, false, false),
+      (
+        false,
+        Some(SurfaceEvent::Resized(PhysicalSize::new(800, 600)))
+      )
+    );
+  }
+
+  #[test]
+  fn maps_scale_factor_changes() {
+    let mut new_inner_size = PhysicalSize::new(1600, 1200);
+    let event = WindowEvent::ScaleFactorChanged {
+      scale_factor: 2.0,
+      new_inner_size: &mut new_inner_size,
+    };
+
+    let surface_event = SurfaceEvent::ScaleFactorChanged {
+      scale_factor: 2.0,
+      inner_size: PhysicalSize::new(1600, 1200),
+    };
+    assert_eq!(unqueried(&event, false), (false, Some(surface_event)));
+  }
+
+  #[test]
+  fn reports_zero_sizes_as_minimized() {
+    let event = WindowEvent::Resized(PhysicalSize::new(0, 0));
+
+    assert_eq!(
+      unqueried(&event, false),
+      (true, Some(SurfaceEvent::Minimized))
+    );
+    assert_eq!(unqueried(&event, true), (true, None));
+  }
+
+  #[test]
+  fn reports_minimizing_from_the_window_state() {
+    let unfocused = WindowEvent::Focused(false);
+    let resized = WindowEvent::Resized(PhysicalSize::new(800, 600));
+
+    assert_eq!(
+      map(&unfocused, false, true),
+      (true, Some(SurfaceEvent::Minimized))
+    );
+    // Only once, while the window stays minimized.
+    assert_eq!(map(&unfocused, true, true), (true, None));
+    assert_eq!(map(&resized, true, true), (true, None));
+  }
+
+  #[test]
+  fn reports_restored_windows_as_resized() {
+    let resized = WindowEvent::Resized(PhysicalSize::new(800, 600));
+
+    assert_eq!(
+      map(&WindowEvent::Focused(true), true, false),
+      (
+        false,
+        Some(SurfaceEvent::Resized(PhysicalSize::new(640, 480)))
+      )
+    );
+    assert_eq!(
+      map(&resized, true, false),
+      (
+        false,
+        Some(SurfaceEvent::Resized(PhysicalSize::new(800, 600)))
+      )
+    );
+  }
+
+  #[test]
+  fn ignores_other_events() {
+    let event = WindowEvent::ReceivedCharacter('a');
+
+    assert_eq!(unqueried(&event, false), (false, None));
+    assert_eq!(unqueried(&event, true), (true, None));
+    assert_eq!(
+      map(&WindowEvent::Focused(true), false, false),
+      (false, None)
+    );
+  }
+
+  #[test]
+  fn listeners_can_add_and_remove_listeners() {
+    let listeners = Rc::new(SurfaceListeners::default());
+    let calls = Rc::new(Cell::new(0));
+
+    let added = listeners.clone();
+    let added_calls = calls.clone();
+    let first = listeners.add(Rc::new(move |_| {
+      let calls = added_calls.clone();
+      added.add(Rc::new(move |_| calls.set(calls.get() + 1)));
+    }));
+    let removing = listeners.clone();
+    listeners.add(Rc::new(move |_| {
+      removing.remove(first);
+    }));
+
+    listeners.dispatch(&SurfaceEvent::Minimized);
+    assert_eq!(calls.get(), 0);
+    assert_eq!(listeners.listeners.borrow().len(), 2);
+
+    listeners.dispatch(&SurfaceEvent::Minimized);
+    assert_eq!(calls.get(), 1);
+  }
+}
@@ -0,0 +1,843 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::time::Duration;
+
+use deno_core::error::AnyError;
+use deno_core::serde::Serialize;
+use deno_core::OpState;
+use deno_core::Resource;
+use deno_core::ResourceId;
+
+use dbus::arg::AppendAll;
+use dbus::arg::PropMap;
+use dbus::arg::RefArg;
+use dbus::arg::Variant;
+use dbus::blocking::LocalConnection;
+use dbus::channel::MatchingReceiver;
+use dbus::channel::Sender;
+use dbus::message::MatchRule;
+use dbus::Message;
+use dbus_crossroads::Crossroads;
+use dbus_crossroads::IfaceBuilder;
+use dbus_crossroads::MethodErr;
+
+use crate::event::Event;
+use crate::menu::submenu_items;
+use crate::menu::MenuItem;
+use crate::menu::MenuResource;
+
+const ITEM_INTERFACE: &str = "org.kde.StatusNotifierItem";
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const MENU_INTERFACE: &str = "com.canonical.dbusmenu";
+const MENU_PATH: &str = "/MenuBar";
+const WATCHER_NAME: &str = "org.kde.StatusNotifierWatcher";
+const WATCHER_PATH: &str = "/StatusNotifierWatcher";
+
+/// The number of trays created by this process, to give each a unique name.
+static TRAY_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// An icon in the `IconPixmap` format, a width and height followed by ARGB32
+/// pixels in network byte order.
+pub type Pixmap = (i32, i32, Vec<u8>);
+
+/// A dbusmenu layout node, an id with properties and child nodes.
+type Layout = (i32, PropMap, Vec<Variant<Box<dyn RefArg>>>);
+
+/// A dbusmenu event, an item id, event id, data and timestamp.
+type MenuItemEvent = (i32, String, Variant<Box<dyn RefArg>>, u32);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase", tag = "type", content = "value")]
+pub enum TrayEvent {
+  /// The icon was clicked, at screen coordinates if the host provides them.
+  Activate { x: i32, y: i32 },
+  /// The icon was middle clicked.
+  SecondaryActivate { x: i32, y: i32 },
+  /// A context menu was requested for a tray without a menu.
+  ContextMenu { x: i32, y: i32 },
+  /// The mouse wheel was used on the icon, `orientation` is `"vertical"` or
+  /// `"horizontal"`.
+  Scroll { delta: i32, orientation: String },
+  /// An item of the tray menu was activated.
+  #[serde(rename_all = "camelCase")]
+  MenuEvent {
+    menu_id: u32,
+    item_id: String,
+    checked: Option<bool>,
+  },
+  /// The tray's D-Bus connection failed, the tray may no longer be shown.
+  Error { message: String },
+}
+
+/// Requests from the status notifier host, received while processing the
+/// connection.
+enum TrayRequest {
+  Event(TrayEvent),
+  MenuItemClicked(i32),
+}
+
+/// What the tray exposes over D-Bus, shared with the object callbacks.
+struct TrayData {
+  title: String,
+  tooltip: String,
+  icons: Vec<Pixmap>,
+  /// The menu as last published, with its dbusmenu layout revision.
+  menu: Vec<MenuItem>,
+  revision: u32,
+  requests: mpsc::Sender<TrayRequest>,
+}
+
+type SharedTrayData = Arc<Mutex<TrayData>>;
+
+/// A system tray icon implementing the freedesktop StatusNotifierItem and
+/// dbusmenu protocols, on its own session bus connection.
+pub struct TrayResource {
+  connection: LocalConnection,
+  data: SharedTrayData,
+  requests: mpsc::Receiver<TrayRequest>,
+  menu: RefCell<Option<(ResourceId, Rc<MenuResource>)>>,
+  menu_revision: RefCell<Option<u32>>,
+}
+
+impl Resource for TrayResource {
+  fn name(&self) -> Cow<str> {
+    "tray".into()
+  }
+}
+
+impl TrayResource {
+  /// Creates the tray and registers it with the status notifier watcher of
+  /// the session bus.
+  pub fn new(
+    title: String,
+    tooltip: String,
+    icons: Vec<Pixmap>,
+  ) -> Result<Self, AnyError> {
+    Self::with_connection(
+      LocalConnection::new_session()?,
+      title,
+      tooltip,
+      icons,
+    )
+  }
+
+  /// Creates the tray on a bus connection. It is registered with the status
+  /// notifier watcher whenever one starts, as the watcher is part of the tray
+  /// host and may not be running yet.
+  fn with_connection(
+    connection: LocalConnection,
+    title: String,
+    tooltip: String,
+    icons: Vec<Pixmap>,
+  ) -> Result<Self, AnyError> {
+    let name = format!(
+      "org.kde.StatusNotifierItem-{}-{}",
+      std::process::id(),
+      TRAY_COUNT.fetch_add(1, Ordering::Relaxed) + 1
+    );
+    connection.request_name(name.as_str(), false, true, true)?;
+
+    let (sender, requests) = mpsc::channel();
+    let data = Arc::new(Mutex::new(TrayData {
+      title,
+      tooltip,
+      icons,
+      menu: Vec::new(),
+      revision: 0,
+      requests: sender,
+    }));
+
+    let mut crossroads = Crossroads::new();
+    let item = crossroads.register(ITEM_INTERFACE, register_item);
+    let menu = crossroads.register(MENU_INTERFACE, register_menu);
+    crossroads.insert(ITEM_PATH, &[item], data.clone());
+    crossroads.insert(MENU_PATH, &[menu], data.clone());
+
+    connection.start_receive(
+      MatchRule::new_method_call(),
+      Box::new(move |message, connection| {
+        let _ = crossroads.handle_message(message, connection);
+        true
+      }),
+    );
+
+    let rule =
+      MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged")
+        .with_sender("org.freedesktop.DBus");
+    let item_name = name.clone();
+    connection.add_match(
+      rule,
+      move |(watched, _, owner): (String, String, String), connection, _| {
+        if watched == WATCHER_NAME && !owner.is_empty() {
+          let _ = connection.send(register_message(&item_name));
+        }
+        true
+      },
+    )?;
+    // There is no reply to wait for if no watcher is running.
+    let _ = connection.send(register_message(&name));
+    connection.channel().flush();
+
+    Ok(Self {
+      connection,
+      data,
+      requests,
+      menu: RefCell::new(None),
+      menu_revision: RefCell::new(None),
+    })
+  }
+
+  pub fn set_icons(&self, icons: Vec<Pixmap>) -> Result<(), AnyError> {
+    lock(&self.data)?.icons = icons;
+    self.signal(ITEM_PATH, ITEM_INTERFACE, "NewIcon", ());
+
+    Ok(())
+  }
+
+  pub fn set_tooltip(&self, tooltip: String) -> Result<(), AnyError> {
+    lock(&self.data)?.tooltip = tooltip;
+    self.signal(ITEM_PATH, ITEM_INTERFACE, "NewToolTip", ());
+
+    Ok(())
+  }
+
+  pub fn set_menu(
+    &self,
+    menu: Option<(ResourceId, Rc<MenuResource>)>,
+  ) -> Result<(), AnyError> {
+    self.menu.replace(menu);
+    self.publish_menu()
+  }
+
+  /// Publishes the current state of the menu, so hosts reload it.
+  fn publish_menu(&self) -> Result<(), AnyError> {
+    let menu = self.menu.borrow();
+    let mut data = lock(&self.data)?;
+
+    data.menu = menu
+      .as_ref()
+      .map(|(_, menu)| menu.items(&[]))
+      .unwrap_or_default();
+    data.revision += 1;
+    self
+      .menu_revision
+      .replace(menu.as_ref().map(|(_, menu)| menu.revision()));
+
+    let revision = data.revision;
+    drop(data);
+    self.signal(MENU_PATH, MENU_INTERFACE, "LayoutUpdated", (revision, 0i32));
+
+    Ok(())
+  }
+
+  fn signal(
+    &self,
+    path: &str,
+    interface: &str,
+    member: &str,
+    args: impl AppendAll,
+  ) {
+    let mut message = Message::new_signal(path, interface, member)
+      .expect("signal names are valid");
+    args.append(&mut dbus::arg::IterAppend::new(&mut message));
+
+    let _ = self.connection.send(message);
+  }
+
+  /// Handles pending requests from the host, returning the resulting events.
+  fn process(&self) -> Result<Vec<TrayEvent>, AnyError> {
+    while self.connection.process(Duration::from_millis(0))? {}
+
+    let mut events = Vec::new();
+
+    for request in self.requests.try_iter() {
+      match request {
+        TrayRequest::Event(event) => events.push(event),
+        TrayRequest::MenuItemClicked(id) => {
+          events.extend(self.activate_menu_item(id)?);
+        }
+      }
+    }
+
+    let revision = self.menu.borrow().as_ref().map(|(_, menu)| menu.revision());
+    if revision != *self.menu_revision.borrow() {
+      self.publish_menu()?;
+    }
+
+    Ok(events)
+  }
+
+  fn activate_menu_item(&self, id: i32) -> Result<Option<TrayEvent>, AnyError> {
+    let path = item_path(&lock(&self.data)?.menu, id, &mut 1);
+
+    Ok(path.and_then(|path| {
+      let (&index, path) = path.split_last()?;
+      let menu = self.menu.borrow();
+      let (menu_id, menu) = menu.as_ref()?;
+      let activation = menu.activate(path, index)?;
+
+      Some(TrayEvent::MenuEvent {
+        menu_id: *menu_id,
+        item_id: activation.id,
+        checked: activation.checked,
+      })
+    }))
+  }
+}
+
+/// Processes the D-Bus connections of all trays, adding their events. A
+/// failing connection is reported as an error event of its tray, rather than
+/// failing every following event loop step.
+pub fn process_trays(state: &OpState, events: &mut Vec<Event>) {
+  for (rid, _) in state.resource_table.names() {
+    if let Some(tray) = state.resource_table.get::<TrayResource>(rid) {
+      let tray_events = tray.process().unwrap_or_else(|error| {
+        vec![TrayEvent::Error {
+          message: error.to_string(),
+        }]
+      });
+
+      events.extend(tray_events.into_iter().map(|event| Event::TrayEvent {
+        tray_id: rid,
+        event,
+      }));
+    }
+  }
+}
+
+/// Locks the data of a tray, which is only poisoned if a D-Bus callback
+/// panicked while holding it.
+fn lock(data: &SharedTrayData) -> Result<MutexGuard<TrayData>, MethodErr> {
+  data
+    .lock()
+    .map_err(|_| MethodErr::failed("The tray data is poisoned"))
+}
+
+fn send_request(
+  data: &SharedTrayData,
+  request: TrayRequest,
+) -> Result<(), MethodErr> {
+  let _ = lock(data)?.requests.send(request);
+
+  Ok(())
+}
+
+fn register_message(name: &str) -> Message {
+  Message::new_method_call(
+    WATCHER_NAME,
+    WATCHER_PATH,
+    WATCHER_NAME,
+    "RegisterStatusNotifierItem",
+  )
+  .expect("method names are valid")
+  .append1(name)
+}
+
+fn register_item(builder: &mut IfaceBuilder<SharedTrayData>) {
+  builder
+    .property("Category")
+    .get(|_, _| Ok("ApplicationStatus".to_string()));
+  builder.property("Id").get(|_, _| Ok("pane".to_string()));
+  builder
+    .property("Title")
+    .get(|_, data| Ok(lock(data)?.title.clone()));
+  builder
+    .property("Status")
+    .get(|_, _| Ok("Active".to_string()));
+  builder
+    .property("IconPixmap")
+    .get(|_, data| Ok(lock(data)?.icons.clone()));
+  builder.property("ToolTip").get(|_, data| {
+    let data = lock(data)?;
+    Ok((
+      String::new(),
+      Vec::<Pixmap>::new(),
+      data.tooltip.clone(),
+      String::new(),
+    ))
+  });
+  builder.property("ItemIsMenu").get(|_, _| Ok(false));
+  builder
+    .property("Menu")
+    .get(|_, _| Ok(dbus::Path::from(MENU_PATH)));
+
+  builder.method("Activate", ("x", "y"), (), |_, data, (x, y)| {
+    send_request(data, TrayRequest::Event(TrayEvent::Activate { x, y }))
+  });
+  builder.method("SecondaryActivate", ("x", "y"), (), |_, data, (x, y)| {
+    send_request(
+      data,
+      TrayRequest::Event(TrayEvent::SecondaryActivate { x, y }),
+    )
+  });
+  builder.method("ContextMenu", ("x", "y"), (), |_, data, (x, y)| {
+    send_request(data, TrayRequest::Event(TrayEvent::ContextMenu { x, y }))
+  });
+  builder.method(
+    "Scroll",
+    ("delta", "orientation"),
+    (),
+    |_, data, (delta, orientation): (i32, String)| {
+      send_request(
+        data,
+        TrayRequest::Event(TrayEvent::Scroll {
+          delta,
+          orientation: orientation.to_lowercase(),
+        }),
+      )
+    },
+  );
+
+  builder.signal::<(), _>("NewTitle", ());
+  builder.signal::<(), _>("NewIcon", ());
+  builder.signal::<(), _>("NewToolTip", ());
+  builder.signal::<(String,), _>("NewStatus", ("status",));
+}
+
+fn register_menu(builder: &mut IfaceBuilder<SharedTrayData>) {
+  builder.property("Version").get(|_, _| Ok(3u32));
+  builder
+    .property("TextDirection")
+    .get(|_, _| Ok("ltr".to_string()));
+  builder
+    .property("Status")
+    .get(|_, _| Ok("normal".to_string()));
+  builder
+    .property("IconThemePath")
+    .get(|_, _| Ok(Vec::<String>::new()));
+
+  builder.method(
+    "GetLayout",
+    ("parentId", "recursionDepth", "propertyNames"),
+    ("revision", "layout"),
+    |_, data, (parent_id, _, _): (i32, i32, Vec<String>)| {
+      let data = lock(data)?;
+      Ok((data.revision, layout(&data.menu, parent_id)))
+    },
+  );
+  builder.method(
+    "GetGroupProperties",
+    ("ids", "propertyNames"),
+    ("properties",),
+    |_, data, (ids, _): (Vec<i32>, Vec<String>)| {
+      let data = lock(data)?;
+      let properties: Vec<(i32, PropMap)> = ids
+        .into_iter()
+        .filter_map(|id| {
+          Some((id, item_properties(find_item(&data.menu, id)?)))
+        })
+        .collect();
+
+      Ok((properties,))
+    },
+  );
+  builder.method(
+    "GetProperty",
+    ("id", "name"),
+    ("value",),
+    |_, data, (id, name): (i32, String)| {
+      let data = lock(data)?;
+      let value = find_item(&data.menu, id)
+        .and_then(|item| item_properties(item).remove(&name))
+        .ok_or_else(|| {
+          dbus_crossroads::MethodErr::failed(&format!(
+            "No property {} on menu item {}",
+            name, id
+          ))
+        })?;
+
+      Ok((value,))
+    },
+  );
+  builder.method(
+    "Event",
+    ("id", "eventId", "data", "timestamp"),
+    (),
+    |_, data, (id, event_id, _, _): MenuItemEvent| {
+      if event_id == "clicked" {
+        send_request(data, TrayRequest::MenuItemClicked(id))?;
+      }
+      Ok(())
+    },
+  );
+  builder.method(
+    "EventGroup",
+    ("events",),
+    ("idErrors",),
+    |_, data, (events,): (Vec<MenuItemEvent>,)| {
+      for (id, event_id, _, _) in events {
+        if event_id == "clicked" {
+          send_request(data, TrayRequest::MenuItemClicked(id))?;
+        }
+      }
+      Ok((Vec::<i32>::new(),))
+    },
+  );
+  builder.method(
+    "AboutToShow",
+    ("id",),
+    ("needUpdate",),
+    |_, _, (_,): (i32,)| Ok((false,)),
+  );
+  builder.method(
+    "AboutToShowGroup",
+    ("ids",),
+    ("updatesNeeded", "idErrors"),
+    |_, _, (_,): (Vec<i32>,)| Ok((Vec::<i32>::new(), Vec::<i32>::new())),
+  );
+
+  builder.signal::<(u32, i32), _>("LayoutUpdated", ("revision", "parent"));
+}
+
+fn variant(value: impl RefArg + 'static) -> Variant<Box<dyn RefArg>> {
+  Variant(Box::new(value))
+}
+
+/// The dbusmenu properties of an item. Underscores are doubled as single ones
+/// mark mnemonics.
+fn item_properties(item: &MenuItem) -> PropMap {
+  let mut properties = HashMap::new();
+
+  if let Some(label) = item.label() {
+    properties.insert("label".to_string(), variant(label.replace('_', "__")));
+    properties.insert("enabled".to_string(), variant(item.is_enabled()));
+  }
+
+  match item {
+    MenuItem::Checkbox { checked, .. } => {
+      properties
+        .insert("toggle-type".to_string(), variant("checkmark".to_string()));
+      properties.insert("toggle-state".to_string(), variant(*checked as i32));
+    }
+    MenuItem::Separator => {
+      properties.insert("type".to_string(), variant("separator".to_string()));
+    }
+    MenuItem::Submenu { .. } => {
+      properties.insert(
+        "children-display".to_string(),
+        variant("submenu".to_string()),
+      );
+    }
+    MenuItem::Item { .. } => {}
+  }
+
+  properties
+}
+
+/// Finds the item indices leading to the item with a dbusmenu id. Items are
+/// numbered depth first, starting with 1 as the root menu is 0.
+fn item_path(
+  items: &[MenuItem],
+  id: i32,
+  next_id: &mut i32,
+) -> Option<Vec<usize>> {
+  for (index, item) in items.iter().enumerate() {
+    let item_id = *next_id;
+    *next_id += 1;
+
+    if item_id == id {
+      return Some(vec![index]);
+    }
+    if let MenuItem::Submenu { items, .. } = item {
+      if let Some(mut path) = item_path(items, id, next_id) {
+        path.insert(0, index);
+        return Some(path);
+      }
+    }
+  }
+
+  None
+}
+
+fn find_item(items: &[MenuItem], id: i32) -> Option<&MenuItem> {
+  let path = item_path(items, id, &mut 1)?;
+  let (&index, path) = path.split_last()?;
+
+  submenu_items(items, path).get(index)
+}
+
+/// Returns the layout of the menu below `parent_id`. As items are numbered
+/// depth first, the children of an item are numbered from its id onwards.
+fn layout(items: &[MenuItem], parent_id: i32) -> Layout {
+  let (properties, items) = match find_item(items, parent_id) {
+    Some(item) => match item {
+      MenuItem::Submenu { items, .. } => (item_properties(item), &items[..]),
+      _ => (item_properties(item), &[][..]),
+    },
+    None => {
+      let mut properties = HashMap::new();
+      properties.insert(
+        "children-display".to_string(),
+        variant("submenu".to_string()),
+      );
+      (properties, if parent_id == 0 { items } else { &[][..] })
+    }
+  };
+
+  (
+    parent_id,
+    properties,
+    layout_children(items, &mut (parent_id + 1)),
+  )
+}
+
+fn layout_children(
+  items: &[MenuItem],
+  next_id: &mut i32,
+) -> Vec<Variant<Box<dyn RefArg>>> {
+  items
+    .iter()
+    .map(|item| {
+      let id = *next_id;
+      *next_id += 1;
+
+      let children = match item {
+        MenuItem::Submenu { items, .. } => layout_children(items, next_id),
+        _ => Vec::new(),
+      };
+
+      variant((id, item_properties(item), children))
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use std::cell::RefCell;
+  use std::rc::Rc;
+  use std::sync::mpsc;
+  use std::thread;
+
+  use deno_core::JsRuntime;
+  use deno_core::RuntimeOptions;
+
+  use crate::helpers::test_bus::wait_for;
+  use crate::helpers::test_bus::TestBus;
+
+  use super::*;
+
+  fn tray(bus: &TestBus) -> TrayResource {
+    TrayResource::with_connection(
+      bus.connect(),
+      "Pane".into(),
+      "A tray".into(),
+      Vec::new(),
+    )
+    .unwrap()
+  }
+
+  /// Stands in for the status notifier watcher, recording the items that
+  /// register with it.
+  fn watcher(bus: &TestBus) -> (LocalConnection, Rc<RefCell<Vec<String>>>) {
+    let connection = bus.connect();
+    connection
+      .request_name(WATCHER_NAME, false, true, true)
+      .unwrap();
+
+    let items = Rc::new(RefCell::new(Vec::new()));
+    let registered = items.clone();
+    connection.start_receive(
+      MatchRule::new_method_call(),
+      Box::new(move |message, connection| {
+        if message.member().as_deref() == Some("RegisterStatusNotifierItem") {
+          registered.borrow_mut().push(message.read1().unwrap());
+          let _ = connection.send(message.method_return());
+        }
+        true
+      }),
+    );
+
+    (connection, items)
+  }
+
+  /// Sends a method call to the tray without waiting for a reply.
+  fn call(
+    bus: &TestBus,
+    tray: &TrayResource,
+    path: &str,
+    interface: &str,
+    member: &str,
+    args: impl AppendAll,
+  ) {
+    let host = bus.connect();
+    let mut message = Message::new_method_call(
+      tray.connection.unique_name(),
+      path,
+      interface,
+      member,
+    )
+    .unwrap();
+    args.append(&mut dbus::arg::IterAppend::new(&mut message));
+
+    host.send(message).unwrap();
+    host.channel().flush();
+  }
+
+  fn next_event(tray: &TrayResource) -> TrayEvent {
+    wait_for(|| tray.process().unwrap().pop())
+  }
+
+  fn registered_item(
+    tray: &TrayResource,
+    watcher: &LocalConnection,
+    items: &RefCell<Vec<String>>,
+  ) -> String {
+    wait_for(|| {
+      tray.process().unwrap();
+      watcher.process(Duration::from_millis(0)).unwrap();
+      items.borrow().first().cloned()
+    })
+  }
+
+  #[test]
+  fn registers_with_a_running_watcher() {
+    let bus = match TestBus::start() {
+      Some(bus) => bus,
+      None => return,
+    };
+    let (watcher, items) = watcher(&bus);
+    let tray = tray(&bus);
+
+    let item = registered_item(&tray, &watcher, &items);
+    assert!(item.starts_with(&format!(
+      "org.kde.StatusNotifierItem-{}-",
+      std::process::id()
+    )));
+  }
+
+  #[test]
+  fn registers_once_a_watcher_starts() {
+    let bus = match TestBus::start() {
+      Some(bus) => bus,
+      None => return,
+    };
+    let tray = tray(&bus);
+    tray.process().unwrap();
+
+    let (watcher, items) = watcher(&bus);
+    let item = registered_item(&tray, &watcher, &items);
+    assert!(item.starts_with("org.kde.StatusNotifierItem-"));
+  }
+
+  #[test]
+  fn reports_activations() {
+    let bus = match TestBus::start() {
+      Some(bus) => bus,
+      None => return,
+    };
+    let tray = tray(&bus);
+
+    call(
+      &bus,
+      &tray,
+      ITEM_PATH,
+      ITEM_INTERFACE,
+      "Activate",
+      (3i32, 4i32),
+    );
+    assert!(matches!(
+      next_event(&tray),
+      TrayEvent::Activate { x: 3, y: 4 }
+    ));
+
+    let args = (-1i32, "Vertical");
+    call(&bus, &tray, ITEM_PATH, ITEM_INTERFACE, "Scroll", args);
+    assert!(matches!(
+      next_event(&tray),
+      TrayEvent::Scroll { delta: -1, orientation } if orientation == "vertical"
+    ));
+  }
+
+  #[test]
+  fn activates_menu_items() {
+    let bus = match TestBus::start() {
+      Some(bus) => bus,
+      None => return,
+    };
+    let tray = tray(&bus);
+    let menu = Rc::new(MenuResource::new(vec![
+      MenuItem::Separator,
+      MenuItem::Checkbox {
+        id: "mute".into(),
+        label: "Mute".into(),
+        checked: false,
+        accelerator: None,
+        enabled: true,
+      },
+    ]));
+    tray.set_menu(Some((7, menu))).unwrap();
+
+    let event = (2i32, "clicked", Variant(0i32), 0u32);
+    call(&bus, &tray, MENU_PATH, MENU_INTERFACE, "Event", event);
+    assert!(matches!(
+      next_event(&tray),
+      TrayEvent::MenuEvent { menu_id: 7, item_id, checked: Some(true) }
+        if item_id == "mute"
+    ));
+  }
+
+  #[test]
+  fn serves_menu_properties() {
+    let bus = match TestBus::start() {
+      Some(bus) => bus,
+      None => return,
+    };
+    let tray = tray(&bus);
+    let menu = Rc::new(MenuResource::new(vec![MenuItem::Item {
+      id: "save_as".into(),
+      label: "Save_as".into(),
+      accelerator: None,
+      enabled: true,
+    }]));
+    tray.set_menu(Some((1, menu))).unwrap();
+
+    let host = bus.connect_sync();
+    let name = tray.connection.unique_name().to_string();
+    let (sender, label) = mpsc::channel();
+    thread::spawn(move || {
+      let reply: Result<(Variant<String>,), _> = host
+        .with_proxy(name, MENU_PATH, Duration::from_secs(5))
+        .method_call(MENU_INTERFACE, "GetProperty", (1i32, "label"));
+      let _ = sender.send(reply.map(|(label,)| label.0));
+    });
+
+    let label = wait_for(|| {
+      tray.process().unwrap();
+      label.try_recv().ok()
+    });
+    assert_eq!(label.unwrap(), "Save__as");
+  }
+
+  #[test]
+  fn reports_connection_errors_as_events() {
+    let bus = match TestBus::start() {
+      Some(bus) => bus,
+      None => return,
+    };
+    let mut runtime = JsRuntime::new(RuntimeOptions::default());
+    let state = runtime.op_state();
+    let rid = state.borrow_mut().resource_table.add(tray(&bus));
+    drop(bus);
+
+    // Every step reports the error, rather than failing.
+    for _ in 0..2 {
+      let mut events = Vec::new();
+      process_trays(&state.borrow(), &mut events);
+      assert!(matches!(
+        events.as_slice(),
+        [Event::TrayEvent {
+          tray_id,
+          event: TrayEvent::Error { .. },
+        }] if *tray_id == rid
+      ));
+    }
+  }
+}
@@ -0,0 +1,599 @@
+use std::ffi::CString;
+use std::mem::MaybeUninit;
+use std::os::raw::c_char;
+use std::os::raw::c_int;
+use std::os::raw::c_long;
+use std::os::raw::c_ulong;
+use std::ptr;
+
+use deno_core::error::generic_error;
+use deno_core::error::AnyError;
+
+use raw_window_handle::HasRawWindowHandle;
+use raw_window_handle::RawWindowHandle;
+
+use winit::event_loop::EventLoopWindowTarget;
+use winit::platform::unix::EventLoopWindowTargetExtUnix;
+
+use x11_dl::xlib;
+use x11_dl::xlib::Xlib;
+
+/// Returns a 32 bit TrueColor visual of the default screen, which compositors
+/// blend using its alpha channel. Returns `None` if the server has no such
+/// visual or the event loop is not backed by Xlib.
+pub fn argb_visual<T>(
+  event_loop: &EventLoopWindowTarget<T>,
+) -> Result<Option<xlib::XVisualInfo>, AnyError> {
+  let connection = match event_loop.xlib_xconnection() {
+    Some(connection) => connection,
+    None => return Ok(None),
+  };
+  let display = connection.display as *mut xlib::Display;
+  let xlib = Xlib::open()?;
+  let mut info = MaybeUninit::uninit();
+
+  unsafe {
+    let screen = (xlib.XDefaultScreen)(display);
+    let found = (xlib.XMatchVisualInfo)(
+      display,
+      screen,
+      32,
+      xlib::TrueColor,
+      info.as_mut_ptr(),
+    );
+    if found == 0 {
+      return Ok(None);
+    }
+
+    Ok(Some(info.assume_init()))
+  }
+}
+
+/// A window on the X server, used for the functionality winit does not expose.
+/// Requests go through the same `Display` connection as winit's own requests.
+pub struct XWindow {
+  xlib: Xlib,
+  display: *mut xlib::Display,
+  window: xlib::Window,
+}
+
+impl XWindow {
+  /// Returns `None` if the window is not backed by Xlib, for example when
+  /// running under Wayland.
+  pub fn new(
+    window: &impl HasRawWindowHandle,
+  ) -> Result<Option<Self>, AnyError> {
+    match window.raw_window_handle() {
+      RawWindowHandle::Xlib(handle) => Ok(Some(Self {
+        xlib: Xlib::open()?,
+        display: handle.display as *mut xlib::Display,
+        window: handle.window,
+      })),
+      _ => Ok(None),
+    }
+  }
+
+  pub fn atom(&self, name: &str) -> xlib::Atom {
+    let name = CString::new(name).expect("atom names do not contain nul");
+
+    unsafe { (self.xlib.XInternAtom)(self.display, name.as_ptr(), xlib::False) }
+  }
+
+  /// Replaces a 32 bit `CARDINAL` property of the window. Note that Xlib
+  /// expects 32 bit property data as an array of `long`.
+  pub fn set_cardinals(&self, property: &str, data: &[c_ulong]) {
+    let property = self.atom(property);
+
+    unsafe {
+      (self.xlib.XChangeProperty)(
+        self.display,
+        self.window,
+        property,
+        xlib::XA_CARDINAL,
+        32,
+        xlib::PropModeReplace,
+        data.as_ptr() as *const u8,
+        data.len() as c_int,
+      );
+      (self.xlib.XFlush)(self.display);
+    }
+  }
+
+  /// Replaces an `ATOM` list property of the window.
+  pub fn set_atoms(&self, property: &str, names: &[&str]) {
+    let property = self.atom(property);
+    let data: Vec<_> = names.iter().map(|name| self.atom(name)).collect();
+
+    unsafe {
+      (self.xlib.XChangeProperty)(
+        self.display,
+        self.window,
+        property,
+        xlib::XA_ATOM,
+        32,
+        xlib::PropModeReplace,
+        data.as_ptr() as *const u8,
+        data.len() as c_int,
+      );
+      (self.xlib.XFlush)(self.display);
+    }
+  }
+
+  /// Marks the window as transient for `owner`, so the window manager keeps
+  /// it above and minimizes it with its owner.
+  pub fn set_transient_for(&self, owner: &XWindow) {
+    unsafe {
+      (self.xlib.XSetTransientForHint)(self.display, self.window, owner.window);
+      (self.xlib.XFlush)(self.display);
+    }
+  }
+
+  /// Reads a 32 bit property of the window, such as a `CARDINAL` or `ATOM`
+  /// list. Returns an empty list if the property is not set.
+  pub fn get_longs(
+    &self,
+    property: &str,
+    property_type: xlib::Atom,
+  ) -> Result<Vec<c_ulong>, AnyError> {
+    let property = self.atom(property);
+    let mut actual_type = 0;
+    let mut actual_format = 0;
+    let mut item_count = 0;
+    let mut bytes_after = 0;
+    let mut data = ptr::null_mut();
+
+    let status = unsafe {
+      (self.xlib.XGetWindowProperty)(
+        self.display,
+        self.window,
+        property,
+        0,
+        c_long::MAX,
+        xlib::False,
+        property_type,
+        &mut actual_type,
+        &mut actual_format,
+        &mut item_count,
+        &mut bytes_after,
+        &mut data,
+      )
+    };
+
+    if status != xlib::Success as c_int {
+      return Err(generic_error("XGetWindowProperty failed"));
+    }
+
+    let longs = if data.is_null() || actual_format != 32 {
+      Vec::new()
+    } else {
+      unsafe {
+        std::slice::from_raw_parts(data as *const c_ulong, item_count as usize)
+      }
+      .to_vec()
+    };
+
+    if !data.is_null() {
+      unsafe { (self.xlib.XFree)(data as *mut _) };
+    }
+
+    Ok(longs)
+  }
+
+  /// Whether the window is mapped, which is what showing it means on X11.
+  pub fn is_mapped(&self) -> Result<bool, AnyError> {
+    Ok(self.attributes()?.map_state != xlib::IsUnmapped)
+  }
+
+  fn attributes(&self) -> Result<xlib::XWindowAttributes, AnyError> {
+    let mut attributes = MaybeUninit::uninit();
+
+    let status = unsafe {
+      (self.xlib.XGetWindowAttributes)(
+        self.display,
+        self.window,
+        attributes.as_mut_ptr(),
+      )
+    };
+
+    if status == 0 {
+      return Err(generic_error("XGetWindowAttributes failed"));
+    }
+
+    Ok(unsafe { attributes.assume_init() })
+  }
+
+  /// Returns the window attributes, erroring unless the window has a true
+  /// color visual with 8 bits per channel. X servers, including Xvfb, default
+  /// to such a visual.
+  fn true_color_attributes(&self) -> Result<xlib::XWindowAttributes, AnyError> {
+    let attributes = self.attributes()?;
+    let visual = unsafe { &*attributes.visual };
+
+    if attributes.depth < 24
+      || visual.red_mask != 0xff0000
+      || visual.green_mask != 0xff00
+      || visual.blue_mask != 0xff
+    {
+      return Err(generic_error(format!(
+        "Unsupported X11 visual with depth {}",
+        attributes.depth
+      )));
+    }
+
+    Ok(attributes)
+  }
+
+  /// Draws 32bpp BGRA pixels into the top left of the window, anything outside
+  /// of the window is clipped.
+  pub fn put_image(
+    &self,
+    bgra: &[u8],
+    width: u32,
+    height: u32,
+  ) -> Result<(), AnyError> {
+    let attributes = self.true_color_attributes()?;
+
+    unsafe {
+      let image = (self.xlib.XCreateImage)(
+        self.display,
+        attributes.visual,
+        attributes.depth as u32,
+        xlib::ZPixmap,
+        0,
+        bgra.as_ptr() as *mut _,
+        width,
+        height,
+        32,
+        width as c_int * 4,
+      );
+
+      if image.is_null() {
+        return Err(generic_error("XCreateImage failed"));
+      }
+
+      // The pixels are little endian `0xAARRGGBB` values, Xlib swaps them if
+      // the server expects another byte order.
+      (*image).byte_order = xlib::LSBFirst;
+
+      let gc =
+        (self.xlib.XCreateGC)(self.display, self.window, 0, ptr::null_mut());
+      (self.xlib.XPutImage)(
+        self.display,
+        self.window,
+        gc,
+        image,
+        0,
+        0,
+        0,
+        0,
+        width,
+        height,
+      );
+      (self.xlib.XFreeGC)(self.display, gc);
+
+      // The pixel data is borrowed, so it must not be freed with the image.
+      (*image).data = ptr::null_mut();
+      (self.xlib.XDestroyImage)(image);
+      (self.xlib.XFlush)(self.display);
+    }
+
+    Ok(())
+  }
+
+  /// Starts drawing into the window with the named core font, such as
+  /// `"fixed"` which every X server provides. Drawing is flushed to the server
+  /// when the canvas is dropped.
+  pub fn canvas(&self, font: &str) -> Result<XCanvas, AnyError> {
+    let name = CString::new(font).expect("font names do not contain nul");
+
+    unsafe {
+      let font = (self.xlib.XLoadQueryFont)(self.display, name.as_ptr());
+
+      if font.is_null() {
+        return Err(generic_error(format!(
+          "Could not load the X11 font {:?}",
+          name
+        )));
+      }
+
+      let gc =
+        (self.xlib.XCreateGC)(self.display, self.window, 0, ptr::null_mut());
+      (self.xlib.XSetFont)(self.display, gc, (*font).fid);
+
+      Ok(XCanvas {
+        window: self,
+        gc,
+        font,
+      })
+    }
+  }
+
+  /// Reads the contents of the window into `rgba` as 32bpp RGBA pixels,
+  /// returning the width and height of the window. Nothing is read if `rgba`
+  /// is too small, so callers can retry with a buffer of the returned size.
+  /// Parts of the window that are obscured by other windows may be undefined
+  /// without a compositor.
+  pub fn get_image(&self, rgba: &mut [u8]) -> Result<(u32, u32), AnyError> {
+    let attributes = self.true_color_attributes()?;
+    let (width, height) = (attributes.width as u32, attributes.height as u32);
+
+    if rgba.len() < width as usize * height as usize * 4 {
+      return Ok((width, height));
+    }
+
+    unsafe {
+      let image = (self.xlib.XGetImage)(
+        self.display,
+        self.window,
+        0,
+        0,
+        width,
+        height,
+        (self.xlib.XAllPlanes)(),
+        xlib::ZPixmap,
+      );
+
+      if image.is_null() {
+        return Err(generic_error("XGetImage failed"));
+      }
+
+      let image_ref = &*image;
+      let data = std::slice::from_raw_parts(
+        image_ref.data as *const u8,
+        image_ref.bytes_per_line as usize * image_ref.height as usize,
+      );
+      let layout = ImageLayout {
+        width: image_ref.width as usize,
+        height: image_ref.height as usize,
+        bytes_per_line: image_ref.bytes_per_line as usize,
+        bits_per_pixel: image_ref.bits_per_pixel as usize,
+        msb_first: image_ref.byte_order == xlib::MSBFirst,
+        has_alpha: attributes.depth == 32,
+      };
+      let result = layout.to_rgba(data, rgba);
+
+      (self.xlib.XDestroyImage)(image);
+      result?;
+    }
+
+    Ok((width, height))
+  }
+}
+
+/// The layout of the pixels of an `XImage` in the `ZPixmap` format.
+struct ImageLayout {
+  width: usize,
+  height: usize,
+  bytes_per_line: usize,
+  bits_per_pixel: usize,
+  /// Whether the most significant byte of a pixel comes first.
+  msb_first: bool,
+  has_alpha: bool,
+}
+
+impl ImageLayout {
+  /// Converts the true color pixels of an image to RGBA, `rgba` must be large
+  /// enough for the image.
+  fn to_rgba(&self, data: &[u8], rgba: &mut [u8]) -> Result<(), AnyError> {
+    let bytes_per_pixel = match self.bits_per_pixel {
+      24 | 32 => self.bits_per_pixel / 8,
+      bits_per_pixel => {
+        return Err(generic_error(format!(
+          "Unsupported X11 image with {} bits per pixel",
+          bits_per_pixel
+        )))
+      }
+    };
+
+    let rows = data.chunks(self.bytes_per_line).take(self.height);
+    let pixels = rgba.chunks_exact_mut(self.width * 4);
+    for (row, pixels) in rows.zip(pixels) {
+      let row = row.chunks_exact(bytes_per_pixel).take(self.width);
+
+      for (source, pixel) in row.zip(pixels.chunks_exact_mut(4)) {
+        let (a, r, g, b) = match (self.msb_first, source) {
+          (true, [a, r, g, b]) => (*a, *r, *g, *b),
+          (true, [r, g, b]) => (0xff, *r, *g, *b),
+          (false, [b, g, r, a]) => (*a, *r, *g, *b),
+          (false, [b, g, r]) => (0xff, *r, *g, *b),
+          _ => unreachable!(),
+        };
+
+        pixel.copy_from_slice(&[
+          r,
+          g,
+          b,
+          if self.has_alpha { a } else { 0xff },
+        ]);
+      }
+    }
+
+    Ok(())
+  }
+}
+
+/// Draws into a window with a graphics context and a core font. Colors are
+/// `0xRRGGBB` values for the true color visuals X servers default to.
+pub struct XCanvas<'a> {
+  window: &'a XWindow,
+  gc: xlib::GC,
+  font: *mut xlib::XFontStruct,
+}
+
+impl XCanvas<'_> {
+  pub fn ascent(&self) -> i32 {
+    unsafe { (*self.font).ascent }
+  }
+
+  pub fn descent(&self) -> i32 {
+    unsafe { (*self.font).descent }
+  }
+
+  pub fn text_width(&self, text: &str) -> i32 {
+    let text = latin1(text);
+
+    unsafe {
+      (self.window.xlib.XTextWidth)(
+        self.font,
+        text.as_ptr(),
+        text.len() as c_int,
+      )
+    }
+  }
+
+  fn set_color(&self, color: u32) {
+    unsafe {
+      (self.window.xlib.XSetForeground)(
+        self.window.display,
+        self.gc,
+        color as c_ulong,
+      );
+    }
+  }
+
+  pub fn fill_rect(&self, color: u32, x: i32, y: i32, width: u32, height: u32) {
+    self.set_color(color);
+
+    unsafe {
+      (self.window.xlib.XFillRectangle)(
+        self.window.display,
+        self.window.window,
+        self.gc,
+        x,
+        y,
+        width,
+        height,
+      );
+    }
+  }
+
+  pub fn stroke_rect(
+    &self,
+    color: u32,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+  ) {
+    self.set_color(color);
+
+    unsafe {
+      (self.window.xlib.XDrawRectangle)(
+        self.window.display,
+        self.window.window,
+        self.gc,
+        x,
+        y,
+        width,
+        height,
+      );
+    }
+  }
+
+  /// Draws `text` with its baseline at `y`.
+  pub fn draw_text(&self, color: u32, x: i32, y: i32, text: &str) {
+    let text = latin1(text);
+    self.set_color(color);
+
+    unsafe {
+      (self.window.xlib.XDrawString)(
+        self.window.display,
+        self.window.window,
+        self.gc,
+        x,
+        y,
+        text.as_ptr(),
+        text.len() as c_int,
+      );
+    }
+  }
+}
+
+impl Drop for XCanvas<'_> {
+  fn drop(&mut self) {
+    unsafe {
+      (self.window.xlib.XFreeFont)(self.window.display, self.font);
+      (self.window.xlib.XFreeGC)(self.window.display, self.gc);
+      (self.window.xlib.XFlush)(self.window.display);
+    }
+  }
+}
+
+/// Core fonts are indexed by Latin-1 bytes, other characters are replaced.
+fn latin1(text: &str) -> Vec<c_char> {
+  text
+    .chars()
+    .map(|c| if (c as u32) < 0x100 { c as u8 } else { b'?' } as c_char)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn layout(bits_per_pixel: usize, msb_first: bool) -> ImageLayout {
+    ImageLayout {
+      width: 2,
+      height: 2,
+      bytes_per_line: 2 * bits_per_pixel / 8 + 2,
+      bits_per_pixel,
+      msb_first,
+      has_alpha: true,
+    }
+  }
+
+  #[test]
+  fn converts_lsb_first_images() {
+    #[rustfmt::skip]
+    let data = [
+      3, 2, 1, 4, 7, 6, 5, 8, 0, 0,
+      11, 10, 9, 12, 15, 14, 13, 16, 0, 0,
+    ];
+    let mut rgba = [0; 16];
+
+    layout(32, false).to_rgba(&data, &mut rgba).unwrap();
+    assert_eq!(
+      rgba,
+      [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]
+    );
+  }
+
+  #[test]
+  fn converts_msb_first_images() {
+    #[rustfmt::skip]
+    let data = [
+      4, 1, 2, 3, 8, 5, 6, 7, 0, 0,
+      12, 9, 10, 11, 16, 13, 14, 15, 0, 0,
+    ];
+    let mut rgba = [0; 16];
+
+    layout(32, true).to_rgba(&data, &mut rgba).unwrap();
+    assert_eq!(
+      rgba,
+      [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16]
+    );
+  }
+
+  #[test]
+  fn converts_packed_images_to_opaque_pixels() {
+    #[rustfmt::skip]
+    let data = [
+      3, 2, 1, 6, 5, 4, 0, 0,
+      9, 8, 7, 12, 11, 10, 0, 0,
+    ];
+    let mut rgba = [0; 16];
+
+    layout(24, false).to_rgba(&data, &mut rgba).unwrap();
+    assert_eq!(
+      rgba,
+      [1, 2, 3, 255, 4, 5, 6, 255, 7, 8, 9, 255, 10, 11, 12, 255]
+    );
+  }
+
+  #[test]
+  fn rejects_other_pixel_sizes() {
+    let mut rgba = [0; 16];
+
+    assert!(layout(16, false).to_rgba(&[0; 12], &mut rgba).is_err());
+  }
+}
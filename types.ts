@@ -1,3 +1,6 @@
+/** The unit positions and sizes are given in. */
+export type Unit = "physical" | "logical";
+
 /** A position that's either physical or logical. */
 export type Position = { physical: PhysicalPosition } | {
   logical: LogicalPosition;
@@ -14,6 +17,155 @@ export type PhysicalSize = { width: number; height: number };
 /** A size represented in logical pixels. */
 export type LogicalSize = { width: number; height: number };
 
+/** A window icon as 32bpp RGBA pixels in rows from top to bottom. */
+export type Icon = { rgba: Uint8Array; width: number; height: number };
+
+/** The channel order of 32bpp pixel data. */
+export type PixelFormat = "rgba" | "bgra";
+
+/** The encodings of images the plugin can decode when built with `image`. */
+export type ImageFormat = "png" | "ico" | "bmp";
+
+/**
+ * Describes the platform window handle of a window, pointers are given as
+ * addresses.
+ */
+export type RawWindowHandle =
+  | {
+    type: "ios";
+    value: { uiWindow: number; uiView: number; uiViewController: number };
+  }
+  | { type: "macOs"; value: { nsWindow: number; nsView: number } }
+  | { type: "xlib"; value: { window: number; display: number } }
+  | { type: "xcb"; value: { window: number; connection: number } }
+  | { type: "wayland"; value: { surface: number; display: number } }
+  | { type: "windows"; value: { hwnd: number; hinstance: number } }
+  | { type: "web"; value: { id: number } }
+  | { type: "android"; value: { aNativeWindow: number } };
+
+/** The attributes a window is created with. */
+export type WindowAttributes = {
+  title?: string;
+  innerSize?: Size;
+  resizable?: boolean;
+  visible?: boolean;
+  decorations?: boolean;
+  alwaysOnTop?: boolean;
+  /**
+   * Whether the window background is transparent, can only be set on creation.
+   * On X11 this requires a compositor.
+   */
+  transparent?: boolean;
+  /**
+   * The `rid` of the `PaneWindow` that owns this one, such as the main window
+   * of a dialog or tool palette. Owned windows stay above their owner and
+   * minimize with it. Supported on X11 and Windows.
+   */
+  owner?: number;
+  /** Whether the window blocks its owner, only a hint on X11. */
+  modal?: boolean;
+  /**
+   * Makes the window a popup, such as a menu, tooltip or dropdown. Popups are
+   * undecorated and are closed with a `popupClosed` event when focus moves
+   * away from them and their parent. On X11 popups are override-redirect
+   * windows, which are left out of the taskbar.
+   */
+  popup?: {
+    /** The `rid` of the parent `PaneWindow`. */
+    parent: number;
+    /** The position relative to the client area of the parent. */
+    position: Position;
+  };
+  /** Attributes only relevant on Linux and the BSDs, ignored elsewhere. */
+  unix?: UnixWindowAttributes;
+};
+
+/**
+ * Window attributes specific to Linux and the BSDs, which are ignored on other
+ * platforms. Most of them only apply to X11.
+ */
+export type UnixWindowAttributes = {
+  /** The `WM_CLASS` of the window on X11, defaults to the name of the binary. */
+  class?: { instance: string; class: string };
+  /** The `_NET_WM_WINDOW_TYPE` of the window on X11, in order of preference. */
+  windowTypes?: X11WindowType[];
+  /** Whether the window manager should ignore the window on X11. */
+  overrideRedirect?: boolean;
+  /** The application id on Wayland, matching the `.desktop` file. */
+  appId?: string;
+  /** The screen to create the window on on X11. */
+  x11Screen?: number;
+};
+
+/** Maps to the `_NET_WM_WINDOW_TYPE` hints of the window manager spec. */
+export type X11WindowType =
+  | "desktop"
+  | "dock"
+  | "toolbar"
+  | "menu"
+  | "utility"
+  | "splash"
+  | "dialog"
+  | "dropdownMenu"
+  | "popupMenu"
+  | "tooltip"
+  | "notification"
+  | "combo"
+  | "dnd"
+  | "normal";
+
+/**
+ * The state of a window as last set through pane, updated with what the
+ * platform reports where it can be queried.
+ */
+export type WindowState = {
+  title: string;
+  visible: boolean;
+  resizable: boolean;
+  minimized: boolean;
+  maximized: boolean;
+  fullscreen: boolean;
+  decorations: boolean;
+  alwaysOnTop: boolean;
+  transparent: boolean;
+  opacity: number;
+  /** The minimum inner size, or `null` if the window has none. */
+  minInnerSize: Size | null;
+  /** The maximum inner size, or `null` if the window has none. */
+  maxInnerSize: Size | null;
+  cursorVisible: boolean;
+  cursorGrab: boolean;
+};
+
+/**
+ * The layout of a window, which can be persisted with `JSON.stringify` and
+ * restored in a later session.
+ */
+export type WindowGeometry = {
+  /** Missing where windows can not be positioned, as on Wayland. */
+  outerPosition?: PhysicalPosition;
+  innerSize: PhysicalSize;
+  maximized: boolean;
+  fullscreen: boolean;
+  /** The name of the monitor the window was on, or fullscreen on. */
+  monitor?: string;
+};
+
+/**
+ * The field or caret text is entered in, relative to the top left of the client
+ * area. The candidate window of the input method is placed below it.
+ */
+export type TextInputArea = { position: Position; size: Size };
+
+/** Decides the new inner size of a window when its scale factor changes. */
+export type ScaleFactorPolicy =
+  /** Keeps the logical size, scaling the physical size. This is the default. */
+  | { type: "keepLogicalSize" }
+  /** Keeps the physical size, scaling the logical size. */
+  | { type: "keepPhysicalSize" }
+  /** Resizes the window to the given size. */
+  | { type: "size"; value: Size };
+
 /** Describes the appearance of the mouse cursor. */
 export type CursorIcon =
   /** The platform-dependent default cursor. */
@@ -85,6 +237,29 @@ export type PaneEvent =
     type: "deviceEvent";
     value: { deviceId: number; event: DeviceEvent };
   }
+  | {
+    /**
+     * Emitted when a menu item is activated, by a click or its accelerator.
+     * `checked` is the new state of checkbox items.
+     */
+    type: "menuEvent";
+    value: {
+      windowId: number;
+      menuId: number;
+      itemId: string;
+      checked?: boolean;
+    };
+  }
+  | {
+    /** Emitted when a `PaneTray` is clicked or its menu is used. */
+    type: "trayEvent";
+    value: { trayId: number; event: TrayEvent };
+  }
+  | {
+    /** Emitted when a `PaneNotification` is clicked or closed. */
+    type: "notificationEvent";
+    value: { notificationId: number; event: NotificationEvent };
+  }
   | {
     /** Unused in pane. */
     type: "userEvent";
@@ -124,6 +299,127 @@ export type PaneEvent =
     type: "loopDestroyed";
   };
 
+/**
+ * An entry of a `PaneMenu`. Accelerators are key combinations such as
+ * `"Ctrl+Shift+S"`, with keys named like `VirtualKeyCode`.
+ */
+export type MenuItem =
+  | {
+    type: "item";
+    id: string;
+    label: string;
+    accelerator?: string;
+    enabled?: boolean;
+  }
+  | {
+    type: "checkbox";
+    id: string;
+    label: string;
+    checked?: boolean;
+    accelerator?: string;
+    enabled?: boolean;
+  }
+  | { type: "separator" }
+  | {
+    type: "submenu";
+    label: string;
+    items: MenuItem[];
+    enabled?: boolean;
+  };
+
+/** Describes an interaction with a `PaneTray`. */
+export type TrayEvent =
+  | {
+    /** The icon was clicked, at screen coordinates if the host provides them. */
+    type: "activate";
+    value: { x: number; y: number };
+  }
+  | {
+    /** The icon was middle clicked. */
+    type: "secondaryActivate";
+    value: { x: number; y: number };
+  }
+  | {
+    /** A context menu was requested for a tray without a menu. */
+    type: "contextMenu";
+    value: { x: number; y: number };
+  }
+  | {
+    /** The mouse wheel was used on the icon. */
+    type: "scroll";
+    value: { delta: number; orientation: "vertical" | "horizontal" };
+  }
+  | {
+    /** An item of the tray menu was activated. */
+    type: "menuEvent";
+    value: { menuId: number; itemId: string; checked?: boolean };
+  }
+  | {
+    /** The tray's D-Bus connection failed, the tray may no longer be shown. */
+    type: "error";
+    value: { message: string };
+  };
+
+/** Describes an interaction with a `PaneNotification`. */
+export type NotificationEvent =
+  | {
+    /**
+     * An action of the notification was invoked, `"default"` when the
+     * notification itself was clicked.
+     */
+    type: "actionInvoked";
+    value: { actionId: string };
+  }
+  | {
+    /** The notification was closed, it will not emit any further events. */
+    type: "closed";
+    value: {
+      reason: "expired" | "dismissed" | "closed" | "undefined";
+    };
+  }
+  | {
+    /**
+     * The connection to the notification server failed, the notification may
+     * not report further events.
+     */
+    type: "error";
+    value: { message: string };
+  };
+
+/** A button of a message box. */
+export type MessageBoxButton = "ok" | "cancel" | "yes" | "no";
+
+/** Options for the message boxes of a `PaneWindow`. */
+export interface MessageBoxOptions {
+  title?: string;
+  /**
+   * The message, which is wrapped at spaces and line breaks. Characters
+   * outside of printable ASCII are shown as `?`.
+   */
+  text: string;
+  /** Defaults to `"info"`. */
+  icon?: "info" | "warning" | "error" | "question";
+  /** The buttons to show, the first is the default. Defaults to `"ok"`. */
+  buttons?: "ok" | "okCancel" | "yesNo" | "yesNoCancel";
+}
+
+/** Options for the file dialogs of a `PaneWindow`. */
+export interface FileDialogOptions {
+  title?: string;
+  /** The label of the button accepting the selection. */
+  acceptLabel?: string;
+  /**
+   * Named groups of file extensions the user can choose to show, such as
+   * `{ name: "Images", extensions: ["png", "jpg"] }`. Extensions match in
+   * any case.
+   */
+  filters?: { name: string; extensions: string[] }[];
+  /** The directory the dialog starts in. */
+  directory?: string;
+  /** The file name suggested by save dialogs. */
+  fileName?: string;
+}
+
 /** Describes the reason the event loop is resuming. */
 export type StartCause =
   /** Unused in pane. */
@@ -151,14 +447,20 @@ export type StartCause =
 /** Describes an event from a `Pane` window. */
 export type WindowEvent =
   | {
-    /** The size of the window has changed. Contains the client area's new dimensions. */
+    /**
+     * The size of the window has changed. Contains the client area's new
+     * dimensions, in the unit the event loop was created with.
+     */
     type: "resized";
-    value: PhysicalSize;
+    value: PhysicalSize | LogicalSize;
   }
   | {
-    /** The position of the window has changed. Contains the window's new position. */
+    /**
+     * The position of the window has changed. Contains the window's new position,
+     * in the unit the event loop was created with.
+     */
     type: "moved";
-    value: PhysicalPosition;
+    value: PhysicalPosition | LogicalPosition;
   }
   | {
     /** The window has been requested to close. */
@@ -223,9 +525,15 @@ export type WindowEvent =
     value: ModifiersState;
   }
   | {
-    /** The cursor has moved on the window. */
+    /**
+     * The cursor has moved on the window. The position is in the unit the event
+     * loop was created with.
+     */
     type: "cursorMoved";
-    value: { deviceId: number; position: PhysicalPosition };
+    value: {
+      deviceId: number;
+      position: PhysicalPosition | LogicalPosition;
+    };
   }
   | {
     /**  The cursor has entered the window. */
@@ -284,7 +592,10 @@ export type WindowEvent =
      * * Moving the window to a display with a different scale factor.
     */
     type: "scaleFactorChanged";
-    value: { scaleFactor: number; newInnerSize: PhysicalSize };
+    value: {
+      scaleFactor: number;
+      newInnerSize: PhysicalSize | LogicalSize;
+    };
   }
   | {
     /**
@@ -297,8 +608,125 @@ export type WindowEvent =
     */
     type: "themeChanged";
     value: Theme;
+  }
+  | {
+    /**
+     * A composition of the input method, only emitted for windows with IME
+     * enabled.
+     *
+     * At the moment this is only supported on X11.
+     */
+    type: "ime";
+    value: Ime;
+  }
+  | {
+    /**
+     * A gesture recognized from the touches of the window, only emitted for
+     * windows with gestures enabled.
+     */
+    type: "gesture";
+    value: Gesture;
+  }
+  | {
+    /**
+     * The state of a pen on a tablet, emitted whenever it moves over the
+     * window.
+     *
+     * At the moment this is only supported on X11.
+     */
+    type: "pen";
+    value: PenEvent;
+  }
+  | {
+    /**
+     * The relative motion of the mouse while the pointer is locked to the
+     * window, which replaces `cursorMoved` events.
+     */
+    type: "pointerMotion";
+    value: { delta: [number, number] };
+  }
+  | {
+    /**
+     * The popup was closed as focus moved away from it and its parent. The
+     * `PaneWindow` of the popup can no longer be used.
+     */
+    type: "popupClosed";
+  };
+
+/**
+ * A gesture recognized from the touches of a window, locations are in physical
+ * pixels like those of `Touch`.
+ *
+ * Pans, pinches and rotations report their phase, they start once the touches
+ * have moved far enough and end when the touches involved lift.
+ */
+export type Gesture =
+  | {
+    /** A single touch lifted shortly after it started, without moving. */
+    type: "tap";
+    value: { location: PhysicalPosition };
+  }
+  | {
+    /**
+     * A tap shortly after and close to the previous tap, which is also reported
+     * as a tap.
+     */
+    type: "doubleTap";
+    value: { location: PhysicalPosition };
+  }
+  | {
+    /** A single touch that has been held without moving. */
+    type: "longPress";
+    value: { location: PhysicalPosition };
+  }
+  | {
+    /**
+     * The touches moved, `location` is their centroid. `translation` is the
+     * movement since the last pan event and `velocity` is in pixels per second.
+     */
+    type: "pan";
+    value: {
+      phase: TouchPhase;
+      location: PhysicalPosition;
+      translation: [number, number];
+      velocity: [number, number];
+    };
+  }
+  | {
+    /**
+     * Two touches moved apart or together, `scale` is their distance relative
+     * to when they were put down.
+     */
+    type: "pinch";
+    value: { phase: TouchPhase; location: PhysicalPosition; scale: number };
+  }
+  | {
+    /**
+     * Two touches turned around each other, `rotation` is the clockwise angle
+     * in radians since they were put down.
+     */
+    type: "rotate";
+    value: { phase: TouchPhase; location: PhysicalPosition; rotation: number };
   };
 
+/** An input method composition, offsets are in UTF-16 code units. */
+export type Ime =
+  | { type: "start" }
+  | {
+    /**
+     * The composition text changed, `cursor` is the range of the converted
+     * clause, or an empty range at the caret.
+     */
+    type: "update";
+    value: { text: string; cursor: [number, number] | null };
+  }
+  | {
+    /** Text was committed, it is not also sent as `receivedCharacter` events. */
+    type: "commit";
+    value: string;
+  }
+  | { type: "end" };
+
 /** Hardware-dependent keyboard scan code. */
 export type ScanCode = number;
 /** Identifier for a specific analog axis on some device. */
@@ -562,6 +990,31 @@ export type UserAttentionType =
  * A `cancelled` event is emitted when the system has canceled tracking this touch,
  * such as when the window loses focus.
  */
+export type PenEvent = {
+  deviceId: number;
+  tool: PenTool;
+  location: PhysicalPosition;
+  /** Whether the tip, or the eraser, touches the tablet. */
+  contact: boolean;
+  /** How hard the tablet is pressed, from 0 to 1. */
+  pressure: number;
+  /**
+   * How far the pen leans to the right and towards the bottom, in degrees
+   * from -90 to 90, if the tablet reports it.
+   */
+  tilt: [number, number] | null;
+  /**
+   * The clockwise rotation of the pen around its own axis, in degrees, if the
+   * pen reports it.
+   */
+  twist: number | null;
+  barrelButton: boolean;
+  secondaryBarrelButton: boolean;
+};
+
+/** The end of the pen that is used. */
+export type PenTool = "pen" | "eraser";
+
 export type Touch = {
   deviceId: number;
   phase: TouchPhase;